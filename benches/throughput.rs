@@ -0,0 +1,155 @@
+//! Upload and drawing throughput of the 2.9" and 7.5" drivers
+//!
+//! Runs against an SPI which only counts the bytes, so the numbers show the overhead of the
+//! crate itself, e.g. the number of transfers per byte. Run with `cargo bench`.
+
+use std::convert::Infallible;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use embedded_graphics::{
+    pixelcolor::BinaryColor,
+    prelude::*,
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+};
+use embedded_hal::{
+    blocking::{delay::DelayMs, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+use epd_waveshare::{epd2in9, epd7in5_v2, prelude::*};
+
+/// Counts the bytes and transfers written to it
+#[derive(Default)]
+struct CountingSpi {
+    bytes: usize,
+    transfers: usize,
+}
+
+impl Write<u8> for CountingSpi {
+    type Error = Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.bytes += black_box(words).len();
+        self.transfers += 1;
+        Ok(())
+    }
+}
+
+/// A pin which always reads `level`, so the busy pin can be set to idle
+struct Pin(bool);
+
+impl OutputPin for Pin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl InputPin for Pin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.0)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(!self.0)
+    }
+}
+
+struct NoDelay;
+
+impl DelayMs<u8> for NoDelay {
+    fn delay_ms(&mut self, _ms: u8) {}
+}
+
+const ITERATIONS: u32 = 50;
+
+/// Runs `f` a few times and prints the time per run and the throughput of `bytes` per run
+fn bench<F: FnMut()>(name: &str, bytes: usize, mut f: F) {
+    // warm up
+    f();
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let per_run = start.elapsed() / ITERATIONS;
+    let mb_per_s = bytes as f64 / per_run.max(Duration::from_nanos(1)).as_secs_f64() / 1e6;
+    println!("{:<32} {:>10.1?} {:>10.1} MB/s", name, per_run, mb_per_s);
+}
+
+/// Uploads a frame and clears the display RAM, reports the transfers of one frame
+fn bench_upload<EPD>(name: &str, mut epd: EPD, buffer: &[u8])
+where
+    EPD: WaveshareDisplay<CountingSpi, Pin, Pin, Pin, Pin, NoDelay>,
+{
+    let mut spi = CountingSpi::default();
+    epd.assume_initialized();
+
+    epd.update_frame(&mut spi, buffer, &mut NoDelay).unwrap();
+    println!(
+        "{:<32} {} bytes in {} transfers",
+        name, spi.bytes, spi.transfers
+    );
+
+    bench(&format!("{} update_frame", name), buffer.len(), || {
+        epd.update_frame(&mut spi, black_box(buffer), &mut NoDelay)
+            .unwrap()
+    });
+    bench(&format!("{} clear_frame", name), buffer.len(), || {
+        epd.clear_frame(&mut spi, &mut NoDelay).unwrap()
+    });
+}
+
+/// Draws a screen full of shapes, which packs every pixel into the buffer
+fn draw<D>(display: &mut D)
+where
+    D: DrawTarget<Color = BinaryColor>,
+    D::Error: core::fmt::Debug,
+{
+    let size = display.bounding_box().size;
+    Rectangle::new(Point::zero(), size)
+        .into_styled(PrimitiveStyle::with_fill(BinaryColor::On))
+        .draw(display)
+        .unwrap();
+    for x in (0..size.width as i32).step_by(4) {
+        Line::new(Point::new(x, 0), Point::new(x, size.height as i32 - 1))
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::Off, 1))
+            .draw(display)
+            .unwrap();
+    }
+    Circle::new(Point::zero(), size.width.min(size.height))
+        .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 3))
+        .draw(display)
+        .unwrap();
+}
+
+fn main() {
+    // 7.5" V2, the busy pin is active low
+    let mut display = epd7in5_v2::Display7in5::default();
+    bench("7in5 v2 draw", display.buffer().len(), || {
+        draw(&mut display)
+    });
+    display.set_rotation(DisplayRotation::Rotate90);
+    bench("7in5 v2 draw rotated", display.buffer().len(), || {
+        draw(&mut display)
+    });
+    let epd = epd7in5_v2::Epd7in5::new_without_init(Pin(false), Pin(true), Pin(false), Pin(false));
+    bench_upload("7in5 v2", epd, display.buffer());
+
+    // 2.9", the busy pin is active high
+    let mut display = epd2in9::Display2in9::default();
+    bench("2in9 draw", display.buffer().len(), || draw(&mut display));
+    display.set_rotation(DisplayRotation::Rotate90);
+    bench("2in9 draw rotated", display.buffer().len(), || {
+        draw(&mut display)
+    });
+    let epd = epd2in9::Epd2in9::new_without_init(Pin(false), Pin(false), Pin(false), Pin(false));
+    bench_upload("2in9", epd, display.buffer());
+}
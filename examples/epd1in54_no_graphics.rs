@@ -12,13 +12,14 @@ use linux_embedded_hal::{
 // needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
 // see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), DisplayError<std::io::Error>> {
     // Configure SPI
     // SPI settings are from eink-waveshare-rs documenation
     let mut spi = Spidev::open("/dev/spidev0.0")?;
     let options = SpidevOptions::new()
         .bits_per_word(8)
         .max_speed_hz(4_000_000)
+        // the displays expect epd_waveshare::SPI_MODE, which is mode 0
         .mode(spidev::SpiModeFlags::SPI_MODE_0)
         .build();
     spi.configure(&options).expect("spi configuration");
@@ -61,8 +62,7 @@ fn main() -> Result<(), std::io::Error> {
     let mut epd = Epd1in54::new(&mut spi, cs_pin, busy, dc, rst, &mut delay)?;
 
     // Clear the full screen
-    epd.clear_frame(&mut spi, &mut delay)?;
-    epd.display_frame(&mut spi, &mut delay)?;
+    epd.clear_and_display(&mut spi, &mut delay)?;
 
     // Speeddemo
     epd.set_lut(&mut spi, Some(RefreshLut::Quick))?;
@@ -75,8 +75,7 @@ fn main() -> Result<(), std::io::Error> {
     }
 
     // Clear the full screen
-    epd.clear_frame(&mut spi, &mut delay)?;
-    epd.display_frame(&mut spi, &mut delay)?;
+    epd.clear_and_display(&mut spi, &mut delay)?;
 
     // Draw some squares
     let small_buffer = [Color::Black.get_byte_value(); 3200]; //160x160
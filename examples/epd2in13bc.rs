@@ -34,7 +34,7 @@ use linux_embedded_hal::{
 //
 // after finishing, put the display to sleep
 
-fn main() -> Result<(), std::io::Error> {
+fn main() -> Result<(), DisplayError<std::io::Error>> {
     let busy = Pin::new(24); // GPIO 24, board J-18
     busy.export().expect("busy export");
     while !busy.is_exported() {}
@@ -65,6 +65,7 @@ fn main() -> Result<(), std::io::Error> {
     let options = SpidevOptions::new()
         .bits_per_word(8)
         .max_speed_hz(10_000_000)
+        // the displays expect epd_waveshare::SPI_MODE, which is mode 0
         .mode(spidev::SpiModeFlags::SPI_MODE_0)
         .build();
     spi.configure(&options).expect("spi configuration");
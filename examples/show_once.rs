@@ -0,0 +1,89 @@
+#![deny(warnings)]
+
+use embedded_graphics::{
+    mono_font::MonoTextStyleBuilder,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle},
+    text::{Baseline, Text, TextStyleBuilder},
+};
+use epd_waveshare::{
+    color::*,
+    epd4in2::{Display4in2, Epd4in2},
+    graphics::show_once,
+    prelude::*,
+};
+use linux_embedded_hal::{
+    spidev::{self, SpidevOptions},
+    sysfs_gpio::Direction,
+    Delay, Pin, Spidev,
+};
+
+// activate spi, gpio in raspi-config
+// needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
+// see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
+
+fn main() -> Result<(), DisplayError<std::io::Error>> {
+    // Configure SPI
+    // Settings are taken from
+    let mut spi = Spidev::open("/dev/spidev0.0").expect("spidev directory");
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(4_000_000)
+        // the displays expect epd_waveshare::SPI_MODE, which is mode 0
+        .mode(spidev::SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options).expect("spi configuration");
+
+    // Configure Digital I/O Pin to be used as Chip Select for SPI
+    let cs = Pin::new(26); //BCM7 CE0
+    cs.export().expect("cs export");
+    while !cs.is_exported() {}
+    cs.set_direction(Direction::Out).expect("CS Direction");
+    cs.set_value(1).expect("CS Value set to 1");
+
+    let busy = Pin::new(5); //pin 29
+    busy.export().expect("busy export");
+    while !busy.is_exported() {}
+    busy.set_direction(Direction::In).expect("busy Direction");
+    //busy.set_value(1).expect("busy Value set to 1");
+
+    let dc = Pin::new(6); //pin 31 //bcm6
+    dc.export().expect("dc export");
+    while !dc.is_exported() {}
+    dc.set_direction(Direction::Out).expect("dc Direction");
+    dc.set_value(1).expect("dc Value set to 1");
+
+    let rst = Pin::new(16); //pin 36 //bcm16
+    rst.export().expect("rst export");
+    while !rst.is_exported() {}
+    rst.set_direction(Direction::Out).expect("rst Direction");
+    rst.set_value(1).expect("rst Value set to 1");
+
+    let mut delay = Delay {};
+
+    // init, draw, refresh and sleep in a single call
+    let _epd: Epd4in2<_, _, _, _, _, _> = show_once(
+        &mut spi,
+        cs,
+        busy,
+        dc,
+        rst,
+        &mut delay,
+        |display: &mut Display4in2| {
+            let style = MonoTextStyleBuilder::new()
+                .font(&embedded_graphics::mono_font::ascii::FONT_6X10)
+                .text_color(Black)
+                .background_color(White)
+                .build();
+            let text_style = TextStyleBuilder::new().baseline(Baseline::Top).build();
+            let _ = Text::with_text_style("Hello once!", Point::new(5, 50), style, text_style)
+                .draw(display);
+
+            let _ = Circle::with_center(Point::new(200, 150), 80)
+                .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+                .draw(display);
+        },
+    )?;
+
+    Ok(())
+}
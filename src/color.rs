@@ -45,6 +45,46 @@ pub enum TriColor {
     Chromatic,
 }
 
+/// A [TriColor] or nothing, for overlays which leave the pixels below unchanged
+///
+/// `TriOverlay(None)` is transparent, see [TriDisplay::overlay](crate::graphics::TriDisplay::overlay).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TriOverlay(pub Option<TriColor>);
+
+impl TriOverlay {
+    /// Leaves the pixel as it is
+    pub const TRANSPARENT: TriOverlay = TriOverlay(None);
+}
+
+impl From<TriColor> for TriOverlay {
+    fn from(color: TriColor) -> Self {
+        TriOverlay(Some(color))
+    }
+}
+
+/// The color the chromatic pixels of a tri-color panel show
+///
+/// The black/white/red and the black/white/yellow variants of a panel share the driver,
+/// only the presented color differs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ChromaticColor {
+    /// Red panels, e.g. the (B) variants
+    #[default]
+    Red,
+    /// Yellow panels, e.g. the (C) variants
+    Yellow,
+}
+
+impl ChromaticColor {
+    /// Converts to limited range of RGB values.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            ChromaticColor::Red => (0xff, 0x00, 0x00),
+            ChromaticColor::Yellow => (0xff, 0xff, 0x00),
+        }
+    }
+}
+
 /// For the 5in65 7 Color Display
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum OctColor {
@@ -66,6 +106,19 @@ pub enum OctColor {
     HiZ = 0x07,
 }
 
+/// For the 4 Color Displays of the G series
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QuadColor {
+    /// Black Color
+    Black = 0x00,
+    /// White Color
+    White = 0x01,
+    /// Yellow Color
+    Yellow = 0x02,
+    /// Red Color
+    Red = 0x03,
+}
+
 impl From<()> for OctColor {
     fn from(_: ()) -> OctColor {
         OctColor::White
@@ -94,16 +147,7 @@ impl From<OctColor> for embedded_graphics_core::pixelcolor::Rgb888 {
 impl From<embedded_graphics_core::pixelcolor::Rgb888> for OctColor {
     fn from(p: embedded_graphics_core::pixelcolor::Rgb888) -> OctColor {
         use embedded_graphics_core::prelude::RgbColor;
-        let colors = [
-            OctColor::Black,
-            OctColor::White,
-            OctColor::Green,
-            OctColor::Blue,
-            OctColor::Red,
-            OctColor::Yellow,
-            OctColor::Orange,
-            OctColor::HiZ,
-        ];
+        let colors = OctColor::all();
         // if the user has already mapped to the right color space, it will just be in the list
         if let Some(found) = colors.iter().find(|c| c.rgb() == (p.r(), p.g(), p.b())) {
             return *found;
@@ -139,6 +183,20 @@ impl PixelColor for OctColor {
 }
 
 impl OctColor {
+    /// Returns the whole palette, ordered by the nibble value of each color
+    pub const fn all() -> [OctColor; 8] {
+        [
+            OctColor::Black,
+            OctColor::White,
+            OctColor::Green,
+            OctColor::Blue,
+            OctColor::Red,
+            OctColor::Yellow,
+            OctColor::Orange,
+            OctColor::HiZ,
+        ]
+    }
+
     /// Gets the Nibble representation of the Color as needed by the display
     pub fn get_nibble(self) -> u8 {
         self as u8
@@ -182,6 +240,159 @@ impl OctColor {
         }
     }
 }
+
+/// Substitutes [OctColor]s while a frame is sent to the display
+///
+/// Starts out as the identity, e.g. to show orange as red on a panel whose orange looks poor:
+///
+/// ```
+/// use epd_waveshare::color::{OctColor, OctColorMap};
+///
+/// let map = OctColorMap::new().remap(OctColor::Orange, OctColor::Red);
+/// assert_eq!(map.get(OctColor::Orange), OctColor::Red);
+/// assert_eq!(map.get(OctColor::Blue), OctColor::Blue);
+/// ```
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct OctColorMap([OctColor; 8]);
+
+impl Default for OctColorMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OctColorMap {
+    /// Creates a map which keeps every color as it is
+    pub const fn new() -> Self {
+        OctColorMap(OctColor::all())
+    }
+
+    /// Shows `from` as `to`
+    pub const fn remap(mut self, from: OctColor, to: OctColor) -> Self {
+        self.0[from as usize] = to;
+        self
+    }
+
+    /// Returns the color `color` is shown as
+    pub fn get(&self, color: OctColor) -> OctColor {
+        self.0[color as usize]
+    }
+
+    /// Substitutes both nibbles of a buffer byte, nibbles outside of the palette are kept
+    pub fn map_byte(&self, byte: u8) -> u8 {
+        let map_nibble = |nibble: u8| match OctColor::from_nibble(nibble) {
+            Ok(color) => self.get(color).get_nibble(),
+            Err(_) => nibble,
+        };
+        map_nibble(byte >> 4) << 4 | map_nibble(byte & 0xf)
+    }
+}
+#[cfg(feature = "graphics")]
+impl From<BinaryColor> for QuadColor {
+    fn from(b: BinaryColor) -> QuadColor {
+        match b {
+            BinaryColor::On => QuadColor::Black,
+            BinaryColor::Off => QuadColor::White,
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<QuadColor> for embedded_graphics_core::pixelcolor::Rgb888 {
+    fn from(b: QuadColor) -> Self {
+        let (r, g, b) = b.rgb();
+        Self::new(r, g, b)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::Rgb888> for QuadColor {
+    fn from(p: embedded_graphics_core::pixelcolor::Rgb888) -> QuadColor {
+        use embedded_graphics_core::prelude::RgbColor;
+        // pick the nearest color, exact matches have a distance of 0
+        QuadColor::all()
+            .iter()
+            .min_by_key(|c| {
+                let (r, g, b) = c.rgb();
+                (i32::from(r) - i32::from(p.r())).pow(2)
+                    + (i32::from(g) - i32::from(p.g())).pow(2)
+                    + (i32::from(b) - i32::from(p.b())).pow(2)
+            })
+            .copied()
+            .unwrap_or(QuadColor::White)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::raw::RawU2> for QuadColor {
+    fn from(b: embedded_graphics_core::pixelcolor::raw::RawU2) -> Self {
+        use embedded_graphics_core::prelude::RawData;
+        QuadColor::from_bits(b.into_inner())
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl PixelColor for QuadColor {
+    type Raw = embedded_graphics_core::pixelcolor::raw::RawU2;
+}
+
+impl QuadColor {
+    /// Returns the whole palette, ordered by the 2 bit value of each color
+    pub const fn all() -> [QuadColor; 4] {
+        [
+            QuadColor::Black,
+            QuadColor::White,
+            QuadColor::Yellow,
+            QuadColor::Red,
+        ]
+    }
+
+    /// Gets the 2 bit representation of the Color as needed by the display
+    pub fn get_bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Takes the lowest 2 bits and converts them to a QuadColor
+    pub fn from_bits(bits: u8) -> QuadColor {
+        match bits & 0x3 {
+            0x00 => QuadColor::Black,
+            0x01 => QuadColor::White,
+            0x02 => QuadColor::Yellow,
+            _ => QuadColor::Red,
+        }
+    }
+
+    /// Packs four colors into a single byte for the Display, the first color in the highest bits
+    pub fn colors_byte(a: QuadColor, b: QuadColor, c: QuadColor, d: QuadColor) -> u8 {
+        a.get_bits() << 6 | b.get_bits() << 4 | c.get_bits() << 2 | d.get_bits()
+    }
+
+    /// Gets a full byte of pixels in this color
+    pub fn get_byte_value(self) -> u8 {
+        QuadColor::colors_byte(self, self, self, self)
+    }
+
+    /// Splits a single byte into its four colors, the highest bits first
+    pub fn split_byte(byte: u8) -> [QuadColor; 4] {
+        [
+            QuadColor::from_bits(byte >> 6),
+            QuadColor::from_bits(byte >> 4),
+            QuadColor::from_bits(byte >> 2),
+            QuadColor::from_bits(byte),
+        ]
+    }
+
+    /// Converts to limited range of RGB values.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            QuadColor::Black => (0x00, 0x00, 0x00),
+            QuadColor::White => (0xff, 0xff, 0xff),
+            QuadColor::Yellow => (0xff, 0xff, 0x00),
+            QuadColor::Red => (0xff, 0x00, 0x00),
+        }
+    }
+}
+
 //TODO: Rename get_bit_value to bit() and get_byte_value to byte() ?
 
 impl Color {
@@ -201,6 +412,18 @@ impl Color {
         }
     }
 
+    /// Gets a full byte of black or white pixels for the frame memory of a panel,
+    /// `black_bit` is the value the panel takes for black pixels
+    ///
+    /// The graphics buffers always use 0 for black, see [get_byte_value](Color::get_byte_value).
+    pub(crate) fn panel_byte(self, black_bit: bool) -> u8 {
+        if black_bit {
+            !self.get_byte_value()
+        } else {
+            self.get_byte_value()
+        }
+    }
+
     /// Parses from u8 to Color
     fn from_u8(val: u8) -> Self {
         match val {
@@ -231,6 +454,58 @@ impl From<u8> for Color {
 }
 
 impl TriColor {
+    /// Returns the inverse of the given color
+    ///
+    /// Black returns White and White returns Black, Chromatic is kept.
+    pub fn inverse(self) -> TriColor {
+        self.inverse_with(TriColor::Chromatic)
+    }
+
+    /// Returns the inverse of the given color with Chromatic mapped to `chromatic`
+    ///
+    /// Like [inverse](Self::inverse), e.g. `inverse_with(TriColor::White)` turns a
+    /// highlight into the background of the inverted theme.
+    pub fn inverse_with(self, chromatic: TriColor) -> TriColor {
+        match self {
+            TriColor::White => TriColor::Black,
+            TriColor::Black => TriColor::White,
+            TriColor::Chromatic => chromatic,
+        }
+    }
+
+    /// Merges a black and a red layer into the b/w and the chromatic plane of a tri-color upload
+    ///
+    /// Both layers use the [Color] bit encoding of a b/w [Display](crate::graphics::Display)
+    /// (cleared bit means black or red, e.g. drawn with [Black]). Where both layers are set,
+    /// red wins, so the black layer is turned into the b/w plane in place by whitening every red pixel.
+    /// The red layer is returned as the chromatic plane unchanged, which matches
+    /// [DisplayColorRendering::Positive](crate::graphics::DisplayColorRendering::Positive).
+    /// Displays with negative rendering need the chromatic plane inverted.
+    ///
+    /// Both layers need to have the same length, otherwise `None` is returned and the black
+    /// layer is left as it is.
+    pub fn compose<'a>(
+        black_layer: &'a mut [u8],
+        red_layer: &'a [u8],
+    ) -> Option<(&'a [u8], &'a [u8])> {
+        if black_layer.len() != red_layer.len() {
+            return None;
+        }
+        for (black, red) in black_layer.iter_mut().zip(red_layer.iter()) {
+            *black |= !red;
+        }
+        Some((black_layer, red_layer))
+    }
+
+    /// Converts to limited range of RGB values, with `chromatic` the color of the panel
+    pub fn rgb(self, chromatic: ChromaticColor) -> (u8, u8, u8) {
+        match self {
+            TriColor::White => (0xff, 0xff, 0xff),
+            TriColor::Black => (0x00, 0x00, 0x00),
+            TriColor::Chromatic => chromatic.rgb(),
+        }
+    }
+
     /// Get the color encoding of the color for one bit
     pub fn get_bit_value(self) -> u8 {
         match self {
@@ -246,6 +521,16 @@ impl TriColor {
             TriColor::Black | TriColor::Chromatic => 0x00,
         }
     }
+
+    /// Gets a full byte of pixels for the frame memory of a panel,
+    /// `black_bit` is the value the panel takes for set pixels, see [Color::panel_byte]
+    pub(crate) fn panel_byte(self, black_bit: bool) -> u8 {
+        if black_bit {
+            !self.get_byte_value()
+        } else {
+            self.get_byte_value()
+        }
+    }
 }
 
 #[cfg(feature = "graphics")]
@@ -253,6 +538,41 @@ impl PixelColor for TriColor {
     type Raw = ();
 }
 
+#[cfg(feature = "graphics")]
+impl PixelColor for TriOverlay {
+    type Raw = ();
+}
+
+#[cfg(feature = "graphics")]
+impl From<BinaryColor> for TriColor {
+    fn from(b: BinaryColor) -> TriColor {
+        match b {
+            BinaryColor::On => TriColor::Black,
+            BinaryColor::Off => TriColor::White,
+        }
+    }
+}
+
+/// Picks the nearest of black, white, red and yellow, red and yellow become
+/// [TriColor::Chromatic], whichever of the two the panel shows
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::Rgb888> for TriColor {
+    fn from(p: embedded_graphics_core::pixelcolor::Rgb888) -> TriColor {
+        match QuadColor::from(p) {
+            QuadColor::Black => TriColor::Black,
+            QuadColor::White => TriColor::White,
+            QuadColor::Yellow | QuadColor::Red => TriColor::Chromatic,
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::Rgb565> for TriColor {
+    fn from(p: embedded_graphics_core::pixelcolor::Rgb565) -> TriColor {
+        TriColor::from(embedded_graphics_core::pixelcolor::Rgb888::from(p))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -266,7 +586,7 @@ mod tests {
     // test all values aside from 0 and 1 which all should panic
     #[test]
     fn from_u8_panic() {
-        for val in 2..=u8::max_value() {
+        for val in 2..=u8::MAX {
             extern crate std;
             let result = std::panic::catch_unwind(|| Color::from(val));
             assert!(result.is_err());
@@ -279,6 +599,31 @@ mod tests {
         assert_eq!(Color::from(0u8).get_bit_value(), 0u8);
     }
 
+    #[test]
+    fn inverse_of_every_color() {
+        assert_eq!(Color::Black.inverse(), Color::White);
+        assert_eq!(Color::White.inverse(), Color::Black);
+
+        assert_eq!(TriColor::Black.inverse(), TriColor::White);
+        assert_eq!(TriColor::White.inverse(), TriColor::Black);
+        assert_eq!(TriColor::Chromatic.inverse(), TriColor::Chromatic);
+
+        let mapped = TriColor::Chromatic.inverse_with(TriColor::Black);
+        assert_eq!(mapped, TriColor::Black);
+        assert_eq!(
+            TriColor::Black.inverse_with(TriColor::Black),
+            TriColor::White
+        );
+    }
+
+    #[test]
+    fn panel_byte_follows_black_bit() {
+        assert_eq!(Color::Black.panel_byte(false), 0x00);
+        assert_eq!(Color::White.panel_byte(false), 0xFF);
+        assert_eq!(Color::Black.panel_byte(true), 0xFF);
+        assert_eq!(Color::White.panel_byte(true), 0x00);
+    }
+
     #[test]
     fn u8_conversion_white() {
         assert_eq!(Color::from(Color::White.get_bit_value()), Color::White);
@@ -294,4 +639,105 @@ mod tests {
             Ok((left, right))
         );
     }
+
+    #[test]
+    fn oct_palette_nibble_round_trip() {
+        for (nibble, &color) in OctColor::all().iter().enumerate() {
+            assert_eq!(color.get_nibble(), nibble as u8);
+            assert_eq!(OctColor::from_nibble(color.get_nibble()), Ok(color));
+        }
+        assert_eq!(
+            OctColor::from_nibble(0x08),
+            Err(OutOfColorRangeParseError(0x08))
+        );
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn oct_palette_rgb_round_trip() {
+        use embedded_graphics_core::pixelcolor::Rgb888;
+        for &color in OctColor::all().iter() {
+            let (r, g, b) = color.rgb();
+            assert_eq!(OctColor::from(Rgb888::new(r, g, b)), color);
+            assert_eq!(Rgb888::from(color), Rgb888::new(r, g, b));
+        }
+    }
+
+    #[test]
+    fn oct_map_substitutes_nibbles() {
+        let map = OctColorMap::new().remap(OctColor::Orange, OctColor::Red);
+        assert_eq!(OctColorMap::default(), OctColorMap::new());
+        assert_eq!(
+            map.map_byte(OctColor::colors_byte(OctColor::Orange, OctColor::Green)),
+            OctColor::colors_byte(OctColor::Red, OctColor::Green)
+        );
+        assert_eq!(map.map_byte(0x68), 0x48);
+    }
+
+    #[test]
+    fn quad_packing() {
+        let byte = QuadColor::colors_byte(
+            QuadColor::Black,
+            QuadColor::White,
+            QuadColor::Yellow,
+            QuadColor::Red,
+        );
+        assert_eq!(byte, 0b00_01_10_11);
+        assert_eq!(QuadColor::split_byte(byte), QuadColor::all());
+        assert_eq!(QuadColor::White.get_byte_value(), 0x55);
+        assert_eq!(QuadColor::Red.get_byte_value(), 0xFF);
+    }
+
+    #[test]
+    fn quad_palette_round_trip() {
+        for (bits, &color) in QuadColor::all().iter().enumerate() {
+            assert_eq!(color.get_bits(), bits as u8);
+            assert_eq!(QuadColor::from_bits(color.get_bits()), color);
+        }
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn quad_palette_rgb_round_trip() {
+        use embedded_graphics_core::pixelcolor::Rgb888;
+        for &color in QuadColor::all().iter() {
+            assert_eq!(QuadColor::from(Rgb888::from(color)), color);
+        }
+        assert_eq!(
+            QuadColor::from(Rgb888::new(0xF0, 0x10, 0x20)),
+            QuadColor::Red
+        );
+        assert_eq!(
+            QuadColor::from(Rgb888::new(0xF0, 0xE0, 0x20)),
+            QuadColor::Yellow
+        );
+    }
+
+    #[test]
+    fn tri_compose_red_wins() {
+        // black on bits 7..4, red on bits 5..2 so bits 5 and 4 overlap
+        let mut black = [0b0000_1111, 0x00];
+        let red = [0b1100_0011, 0xFF];
+
+        let (bw_plane, chromatic_plane) = TriColor::compose(&mut black, &red).unwrap();
+
+        assert_eq!(bw_plane, [0b0011_1111, 0x00]);
+        assert_eq!(chromatic_plane, [0b1100_0011, 0xFF]);
+    }
+
+    #[test]
+    fn tri_compose_no_red() {
+        let mut black = [0x0F, 0xA5];
+        let red = [0xFF, 0xFF];
+        let (bw_plane, _) = TriColor::compose(&mut black, &red).unwrap();
+        assert_eq!(bw_plane, [0x0F, 0xA5]);
+    }
+
+    #[test]
+    fn tri_compose_refuses_different_lengths() {
+        let mut black = [0x0F, 0xA5];
+        let red = [0x00];
+        assert_eq!(TriColor::compose(&mut black, &red), None);
+        assert_eq!(black, [0x0F, 0xA5]);
+    }
 }
@@ -0,0 +1,513 @@
+//! A simple Driver for the Waveshare 1.02" E-Ink Display (D) via SPI
+//!
+//! The 1.02" panel is a tiny 80x128 black/white dot matrix display, which makes its
+//! whole framebuffer only 1280 bytes long.
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/1.02inch_e-Paper_Module)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/702def0/RaspberryPi%26JetsonNano/c/lib/e-Paper/EPD_1in02d.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/702def0/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd1in02.py)
+//!
+//! # Examples
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+//!use embedded_graphics::{
+//!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{epd1in02::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd1in02::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display1in02::default();
+//!
+//!// Use embedded graphics for drawing a line
+//!let _ = Line::new(Point::new(0, 0), Point::new(79, 127))
+//!    .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_frame(&mut spi, &display.buffer(), &mut delay)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::color::Color;
+use crate::interface::{DisplayInterface, InterfaceConfig, LutLoadOrder};
+use crate::traits::{
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+
+mod constants;
+use self::constants::*;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display1in02;
+
+/// Width of the display
+pub const WIDTH: u32 = 80;
+/// Height of the display
+pub const HEIGHT: u32 = 128;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = true;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
+
+/// Epd1in02 driver
+///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
+pub struct Epd1in02<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in02<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        // Reset the device
+        self.interface.reset(delay, 2);
+
+        self.cmd_with_data(spi, Command::UndocumentedD2, &[0x3F])?;
+        // LUT from registers, scan up, shift right, booster on
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x6F])?;
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2B, 0x2B])?;
+        self.cmd_with_data(spi, Command::ChargePump, &[0x3F])?;
+        self.cmd_with_data(spi, Command::LutOption, &[0x00, 0x00])?;
+        // 50Hz
+        self.cmd_with_data(spi, Command::PllControl, &[0x13])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x57])?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
+        self.send_resolution(spi)?;
+        // -1V
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x33])?;
+
+        let lut_first = self
+            .interface
+            .lut_before_power_on(LutLoadOrder::BeforePowerOn);
+        if lut_first {
+            self.set_lut(spi, None)?;
+        }
+
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface.power_on_settle(delay);
+
+        if !lut_first {
+            self.set_lut(spi, None)?;
+        }
+        self.interface.set_initialized();
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in02<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        Epd1in02 {
+            interface,
+            color,
+            refresh: RefreshLut::Full,
+        }
+    }
+
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn can_partial_refresh(&self) -> bool {
+        self.interface.has_base_image()
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xF7])?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle(spi, delay)?;
+
+        // the old data is always treated as all white, as done by the reference code
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, Color::White.panel_byte(BLACK_BIT), WIDTH / 8 * HEIGHT)?;
+
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
+        Ok(())
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface
+            .cmd_then_delay(spi, delay, Command::DisplayRefresh, 10)?;
+        self.wait_until_idle(spi, delay)?;
+        self.interface.refreshed(self.refresh);
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle(spi, delay)?;
+
+        let color_value = self.color.panel_byte(BLACK_BIT);
+
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, !color_value, WIDTH / 8 * HEIGHT)?;
+
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        match self.refresh {
+            RefreshLut::Full => self.set_lut_helper(spi, &LUT_WHITE_FULL, &LUT_BLACK_FULL),
+            RefreshLut::Quick => self.set_lut_helper(spi, &LUT_WHITE_QUICK, &LUT_BLACK_QUICK),
+        }
+    }
+
+    fn refresh_lut(&self) -> RefreshLut {
+        self.refresh
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in02<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        while self.interface.is_busy(IS_BUSY_LOW) {
+            self.interface.cmd(spi, Command::GetStatus)?;
+            self.interface.busy_poll_delay(delay);
+        }
+        Ok(())
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        let w = self.width();
+        let h = self.height();
+
+        self.cmd_with_data(spi, Command::TconResolution, &[w as u8, h as u8])
+    }
+
+    fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        lut_white: &[u8],
+        lut_black: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.cmd_with_data(spi, Command::LutWhite, lut_white)?;
+        self.cmd_with_data(spi, Command::LutBlack, lut_black)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Event, Recorder};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 80);
+        assert_eq!(HEIGHT, 128);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_commands() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd1in02::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            mock.commands(),
+            [0xD2, 0x00, 0x01, 0x06, 0x2A, 0x30, 0x50, 0x60, 0x61, 0x82, 0xE3, 0x23, 0x24, 0x04]
+        );
+        assert_eq!(mock.data_after(0x61).unwrap(), [80, 128]);
+        assert_eq!(mock.data_after(0x23).unwrap(), LUT_WHITE_FULL);
+
+        mock.clear();
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Quick))
+            .unwrap();
+        assert_eq!(mock.data_after(0x24).unwrap(), LUT_BLACK_QUICK);
+    }
+
+    #[test]
+    fn lut_follows_the_power_on_when_configured() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd1in02<_, _, _, _, _, crate::mock::MockDelay> =
+            Epd1in02::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.set_interface_config(InterfaceConfig::new().lut_load_order(LutLoadOrder::AfterPowerOn));
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+
+        let commands = mock.commands();
+        let power_on = commands.iter().position(|&c| c == 0x04).unwrap();
+        let lut = commands.iter().position(|&c| c == 0x23).unwrap();
+        assert!(power_on < lut);
+        assert_eq!(commands.iter().filter(|&&c| c == 0x23).count(), 1);
+    }
+
+    #[test]
+    fn busy_poll_interval_drives_the_delay() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd1in02<_, _, _, _, _, crate::mock::MockDelay> =
+            Epd1in02::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.set_interface_config(InterfaceConfig::new().busy_poll_interval_ms(5));
+        epd.assume_initialized();
+
+        mock.busy_for(3);
+        epd.sleep(&mut mock.spi(), &mut mock.delay()).unwrap();
+        let delays: std::vec::Vec<_> = mock
+            .events()
+            .into_iter()
+            .filter(|event| matches!(event, Event::Delay(_)))
+            .collect();
+        assert_eq!(delays, [Event::Delay(5), Event::Delay(5), Event::Delay(5)]);
+    }
+
+    #[test]
+    fn busy_wait_polls_status() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd1in02::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        mock.clear();
+        mock.busy_for(2);
+        epd.sleep(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(
+            mock.events(),
+            [
+                Event::Command(0x71),
+                Event::Delay(20),
+                Event::Command(0x71),
+                Event::Delay(20),
+                Event::Command(0x50),
+                Event::data(&[0xF7]),
+                Event::Command(0x02),
+                Event::Command(0x07),
+                Event::data(&[0xA5]),
+            ]
+        );
+    }
+}
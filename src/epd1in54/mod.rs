@@ -4,7 +4,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyleBuilder},
 //!};
@@ -52,6 +52,8 @@ pub const HEIGHT: u32 = 200;
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 //const DPI: u16 = 184;
 const IS_BUSY_LOW: bool = false;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
 
 use embedded_hal::{
     blocking::{delay::*, spi::Write},
@@ -65,9 +67,12 @@ use crate::type_a::{
 
 use crate::color::Color;
 
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::traits::{
+    DirectRam, DisplayError, DisplayState, DriverOutputControl, RefreshLut, SplitRefresh,
+    WaveshareDisplay,
+};
 
-use crate::interface::DisplayInterface;
+use crate::interface::{DisplayInterface, InterfaceConfig};
 
 #[cfg(feature = "graphics")]
 mod graphics;
@@ -75,6 +80,7 @@ mod graphics;
 pub use crate::epd1in54::graphics::Display1in54;
 
 /// Epd1in54 driver
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd1in54<SPI, CS, BUSY, DC, RST, DELAY> {
     /// SPI
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
@@ -82,6 +88,8 @@ pub struct Epd1in54<SPI, CS, BUSY, DC, RST, DELAY> {
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Gate lines and scan direction
+    driver_output: DriverOutputControl,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
@@ -93,19 +101,17 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.reset(delay, 10);
 
         // 3 Databytes:
         // A[7:0]
         // 0.. A[8]
         // 0.. B[2:0]
-        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
-        self.interface.cmd_with_data(
-            spi,
-            Command::DriverOutputControl,
-            &[HEIGHT as u8, (HEIGHT >> 8) as u8, 0x00],
-        )?;
+        // Default Values: A = Height of Screen - 1 (0xC7), B = 0x00 (GD, SM and TB=0?)
+        let driver_output = self.driver_output.to_bytes();
+        self.interface
+            .cmd_with_data(spi, Command::DriverOutputControl, &driver_output)?;
 
         // 3 Databytes: (and default values from datasheet and arduino)
         // 1 .. A[6:0]  = 0xCF | 0xD7
@@ -135,6 +141,7 @@ where
         self.set_lut(spi, None)?;
 
         self.wait_until_idle();
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -165,30 +172,94 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
 
-        let mut epd = Epd1in54 {
+        Epd1in54 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
-        };
+            driver_output: DriverOutputControl::new(HEIGHT as u16),
+        }
+    }
 
-        epd.init(spi, delay)?;
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
 
-        Ok(epd)
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn can_partial_refresh(&self) -> bool {
+        self.interface.has_base_image()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::SwReset, 10, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         //TODO: is 0x00 needed here or would 0x01 be even more efficient?
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x00])?;
+        self.interface.set_sleeping();
         Ok(())
     }
 
@@ -197,11 +268,12 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.use_full_frame(spi)?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
         Ok(())
     }
 
@@ -214,7 +286,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.set_ram_area(spi, x, y, x + width, y + height)?;
         self.set_ram_counter(spi, x, y)?;
@@ -224,7 +297,13 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.wait_until_idle();
         // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
         //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
@@ -235,6 +314,7 @@ where
         // MASTER Activation should not be interupted to avoid currption of panel images
         // therefore a terminate command is send
         self.interface.cmd(spi, Command::Nop)?;
+        self.interface.refreshed(self.refresh);
         Ok(())
     }
 
@@ -243,18 +323,23 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.use_full_frame(spi)?;
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self.background_color.panel_byte(BLACK_BIT);
 
         self.interface.cmd(spi, Command::WriteRam)?;
         self.interface
@@ -274,7 +359,7 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
@@ -284,6 +369,16 @@ where
         }
     }
 
+    fn refresh_lut(&self) -> RefreshLut {
+        self.refresh
+    }
+
+    fn max_partial_refresh_hz(&self) -> u32 {
+        // the 1.54inch e-Paper Module (V1) takes 0.3s for a partial refresh in its
+        // specification on the Waveshare wiki, rounded down
+        3
+    }
+
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
@@ -298,11 +393,25 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
+    /// Sets the number of gate lines and the scan direction, for panels with another height
+    ///
+    /// The setting is kept for the next [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_driver_output_control(
+        &mut self,
+        spi: &mut SPI,
+        control: DriverOutputControl,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.driver_output = control;
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::DriverOutputControl, &control.to_bytes())
+    }
+
     fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    pub(crate) fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    pub(crate) fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -317,7 +426,7 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         assert!(start_x < end_x);
         assert!(start_y < end_y);
@@ -349,7 +458,7 @@ where
         spi: &mut SPI,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -365,7 +474,11 @@ where
         Ok(())
     }
 
-    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
+    fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         assert!(buffer.len() == 30);
 
@@ -375,9 +488,84 @@ where
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> SplitRefresh<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn start_refresh(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC4])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.interface.cmd(spi, Command::Nop)?;
+        self.interface.refreshed(self.refresh);
+        Ok(())
+    }
+
+    fn finish_refresh(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_for_idle(delay);
+        Ok(())
+    }
+
+    fn wait_for_idle(&mut self, delay: &mut DELAY) {
+        self.interface
+            .wait_until_idle_with_delay(IS_BUSY_LOW, delay);
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> DirectRam<SPI> for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    const RAM_WIDTH: u32 = WIDTH;
+    const RAM_HEIGHT: u32 = HEIGHT;
+
+    fn write_ram(
+        &mut self,
+        spi: &mut SPI,
+        x_byte: u32,
+        y: u32,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        // the window has to span the whole ram, the counter only wraps inside of it
+        self.use_full_frame(spi)?;
+        self.set_ram_counter(spi, x_byte * 8, y)?;
+
+        self.interface.cmd_with_data(spi, Command::WriteRam, data)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "graphics")]
+    use crate::{
+        color::Black,
+        graphics::{DirectDisplay, DisplayRotation},
+        mock::{Event, Recorder},
+    };
+    #[cfg(feature = "graphics")]
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{Line, PrimitiveStyle},
+    };
 
     #[test]
     fn epd_size() {
@@ -385,4 +573,163 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn direct_display_writes_ram_runs() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut spi = mock.spi();
+        let mut epd = Epd1in54::new(
+            &mut spi,
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+        mock.clear();
+
+        let mut display = DirectDisplay::new(&mut epd, &mut spi, Color::White);
+        // 12 pixels starting at x = 4 touch two bytes, which end up in one write
+        Line::new(Point::new(4, 3), Point::new(15, 3))
+            .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+            .draw(&mut display)
+            .unwrap();
+
+        let events = mock.events();
+        assert_eq!(
+            events[events.len() - 6..],
+            [
+                Event::Command(0x4E),
+                Event::data(&[0x00]),
+                Event::Command(0x4F),
+                Event::data(&[0x03, 0x00]),
+                Event::Command(0x24),
+                Event::data(&[0xF0, 0x00]),
+            ]
+        );
+
+        mock.clear();
+        display.set_rotation(DisplayRotation::Rotate90);
+        Pixel(Point::new(0, 0), Black).draw(&mut display).unwrap();
+        let events = mock.events();
+        assert_eq!(
+            events[events.len() - 6..],
+            [
+                Event::Command(0x4E),
+                Event::data(&[24]),
+                Event::Command(0x4F),
+                Event::data(&[0x00, 0x00]),
+                Event::Command(0x24),
+                Event::data(&[0xFE]),
+            ]
+        );
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn direct_display_keeps_the_pixels_of_shared_bytes() {
+        use crate::{epd1in54::Display1in54, graphics::Display};
+        use embedded_graphics::{
+            mono_font::{ascii::FONT_6X10, MonoTextStyle},
+            text::{Baseline, Text},
+        };
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut spi = mock.spi();
+        let mut epd = Epd1in54::new(
+            &mut spi,
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+        mock.clear();
+
+        // the glyphs are 6 pixels wide, so "A" and "B" share the first byte of each row
+        let style = MonoTextStyle::new(&FONT_6X10, Black);
+        let text = Text::with_baseline("AB", Point::new(1, 0), style, Baseline::Top);
+        let mut display = DirectDisplay::new(&mut epd, &mut spi, Color::White);
+        text.draw(&mut display).unwrap();
+        let mut expected = Display1in54::default();
+        text.draw(&mut expected).unwrap();
+
+        // replay the RAM writes into a cleared RAM
+        let mut ram = [0xFFu8; WIDTH as usize / 8 * HEIGHT as usize];
+        let (mut x, mut y) = (0, 0);
+        let mut last = 0;
+        for event in mock.events() {
+            match event {
+                Event::Command(command) => last = command,
+                Event::Data(data) if last == 0x4E => x = data[0] as usize,
+                Event::Data(data) if last == 0x4F => y = data[0] as usize,
+                Event::Data(data) if last == 0x24 => {
+                    let start = y * WIDTH as usize / 8 + x;
+                    ram[start..start + data.len()].copy_from_slice(&data);
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(ram[..], expected.buffer()[..]);
+        assert_ne!(ram[0..WIDTH as usize / 8 * 10], [0xFF; 250][..]);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn direct_display_resets_bytes_outside_the_shadow() {
+        use crate::graphics::DIRECT_SHADOW_LEN;
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut spi = mock.spi();
+        let mut epd = Epd1in54::new(
+            &mut spi,
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        let mut display = DirectDisplay::new(&mut epd, &mut spi, Color::White);
+        Pixel(Point::new(0, 0), Black).draw(&mut display).unwrap();
+        // push the first byte out of the shadow, one byte per row
+        for y in 1..=DIRECT_SHADOW_LEN as i32 {
+            Pixel(Point::new(0, y), Black).draw(&mut display).unwrap();
+        }
+
+        // the byte is sent as if the first pixel was never drawn
+        mock.clear();
+        Pixel(Point::new(1, 0), Black).draw(&mut display).unwrap();
+        assert_eq!(mock.data_after(0x24).unwrap(), [0xBF]);
+
+        // a byte still in the shadow keeps its pixels
+        mock.clear();
+        Pixel(Point::new(1, 2), Black).draw(&mut display).unwrap();
+        assert_eq!(mock.data_after(0x24).unwrap(), [0x3F]);
+    }
+
+    #[test]
+    fn max_partial_refresh_hz() {
+        use crate::mock::{MockDelay, MockPin, MockSpi, Recorder};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let epd: Epd1in54<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> =
+            Epd1in54::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(epd.max_partial_refresh_hz(), 3);
+    }
+
+    #[test]
+    fn driver_output_control_is_the_last_gate() {
+        use crate::mock::{MockDelay, Recorder};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd1in54<_, _, _, _, _, MockDelay> =
+            Epd1in54::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x01).unwrap(), [0xC7, 0x00, 0x00]);
+    }
 }
@@ -0,0 +1,97 @@
+use crate::epd1in54_v2::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
+use crate::graphics::{rotated_size, Display, DisplayRotation};
+use embedded_graphics_core::pixelcolor::BinaryColor;
+use embedded_graphics_core::prelude::*;
+
+/// Display with Fullsize buffer for use with the 1in54 EPD V2
+///
+/// Can also be manually constructed:
+/// `buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH / 8 * HEIGHT]`
+pub struct Display1in54 {
+    buffer: [u8; WIDTH as usize * HEIGHT as usize / 8],
+    rotation: DisplayRotation,
+}
+
+impl Default for Display1in54 {
+    fn default() -> Self {
+        Display1in54 {
+            buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value();
+                WIDTH as usize * HEIGHT as usize / 8],
+            rotation: DisplayRotation::default(),
+        }
+    }
+}
+
+impl DrawTarget for Display1in54 {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Display1in54 {
+    fn size(&self) -> Size {
+        rotated_size(WIDTH, HEIGHT, self.rotation)
+    }
+}
+
+impl Display for Display1in54 {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test buffer length
+    #[test]
+    fn graphics_size() {
+        let display = Display1in54::default();
+        assert_eq!(display.buffer().len(), 5000);
+    }
+
+    // test default background color on all bytes
+    #[test]
+    fn graphics_default() {
+        let display = Display1in54::default();
+        for &byte in display.buffer() {
+            assert_eq!(byte, DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    // pixel (0, 0) is the most significant bit of the first byte, black clears the bit
+    #[test]
+    fn graphics_origin_bit() {
+        use crate::color::Black;
+        use crate::graphics::Display;
+        use embedded_graphics::prelude::{Drawable, Pixel, Point};
+
+        let mut display = Display1in54::default();
+        let _ = Pixel(Point::new(0, 0), Black).draw(&mut display);
+        assert_eq!(display.buffer()[0], 0b0111_1111);
+        let _ = Pixel(Point::new(1, 0), Black).draw(&mut display);
+        assert_eq!(display.buffer()[0], 0b0011_1111);
+    }
+}
@@ -0,0 +1,746 @@
+//! A simple Driver for the Waveshare 1.54" E-Ink Display V2 (SSD1681) via SPI
+//!
+//! Specification: <https://www.waveshare.com/w/upload/e/e5/1.54inch_e-paper_V2_Datasheet.pdf>
+//!
+//! The V2 panel supports partial refreshes with its own LUT. Select it with
+//! `set_lut(spi, Some(RefreshLut::Quick))` after writing a base image with
+//! [sync_base_image](Epd1in54::sync_base_image), then update windows of the
+//! frame with `update_partial_frame` and show them with `display_frame`.
+//!
+//! # Example for the 1.54 in E-Ink Display V2
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+//!use embedded_graphics::{
+//!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{epd1in54_v2::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd1in54::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display1in54::default();
+//!
+//!// Display the base image with a full refresh
+//!epd.sync_base_image(&mut spi, display.buffer())?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Draw into a window of the frame and refresh only that window
+//!let _ = Line::new(Point::new(0, 0), Point::new(15, 0))
+//!    .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+//!    .draw(&mut display);
+//!epd.set_lut(&mut spi, Some(RefreshLut::Quick))?;
+//!epd.update_partial_frame(&mut spi, &display.buffer()[..2], 0, 0, 16, 1)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+/// Width of epd1in54 in pixels
+pub const WIDTH: u32 = 200;
+/// Height of epd1in54 in pixels
+pub const HEIGHT: u32 = 200;
+/// Default Background Color (white)
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = false;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
+
+/// The waveform of the partial refresh, sent to the LUT register
+#[rustfmt::skip]
+const LUT_PARTIAL_1IN54: [u8; 153] = [
+    0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x80, 0x80, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x40, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x80, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0xF, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x1, 0x1, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x0, 0x0, 0x0,
+];
+
+/// Voltages which belong to the partial LUT: end option, gate, source (VSH1, VSH2, VSL)
+/// and VCOM
+const LUT_PARTIAL_END_OPTION: u8 = 0x02;
+const LUT_PARTIAL_GATE_VOLTAGE: u8 = 0x17;
+const LUT_PARTIAL_SOURCE_VOLTAGE: [u8; 3] = [0x41, 0xB0, 0x32];
+const LUT_PARTIAL_VCOM: u8 = 0x28;
+
+/// Display update sequence of a full refresh: Enable clock signal, Enable Analog, Load
+/// temperature value, Load LUT from the OTP, DISPLAY with DISPLAY Mode 1, Disable Analog,
+/// Disable OSC
+const UPDATE_FULL: u8 = 0xF7;
+/// Display update sequence of a partial refresh: Enable clock signal, Enable Analog,
+/// DISPLAY with DISPLAY Mode 2 and the loaded LUT, Disable Analog, Disable OSC
+const UPDATE_PARTIAL: u8 = 0xCF;
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::type_a::command::Command;
+
+use crate::buffer_len;
+use crate::color::Color;
+
+use crate::traits::*;
+
+use crate::interface::{DisplayInterface, InterfaceConfig};
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use crate::epd1in54_v2::graphics::Display1in54;
+
+/// Epd1in54 V2 driver
+///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
+pub struct Epd1in54<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// SPI
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Color
+    background_color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Gate lines and scan direction
+    driver_output: DriverOutputControl,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.reset(delay, 10);
+
+        self.wait_until_idle();
+        self.interface.cmd(spi, Command::SwReset)?;
+        self.wait_until_idle();
+
+        let driver_output = self.driver_output.to_bytes();
+        self.interface
+            .cmd_with_data(spi, Command::DriverOutputControl, &driver_output)?;
+
+        // address: x increment, y increment, address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
+
+        self.use_full_frame(spi)?;
+
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])?;
+
+        // use the internal temperature sensor
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+
+        // a full refresh loads the LUT from the OTP again
+        self.refresh = RefreshLut::Full;
+
+        self.wait_until_idle();
+        self.interface.set_initialized();
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+
+        Epd1in54 {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            driver_output: DriverOutputControl::new(HEIGHT as u16),
+        }
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.interface.set_sleeping();
+        Ok(())
+    }
+
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn can_partial_refresh(&self) -> bool {
+        self.interface.has_base_image()
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::SwReset, 10, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle();
+        self.use_full_frame(spi)?;
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.frame(spi, buffer, HEIGHT)
+    }
+
+    /// Writes `buffer` into the window of `width` x `height` pixels at `x`, `y`
+    ///
+    /// `x` and `width` must be multiples of 8. With the quick LUT only the pixels which
+    /// differ from the base image are driven by the following `display_frame`.
+    ///
+    /// Fails with [DisplayError::InvalidData] if the window doesn't fit on the display or
+    /// `buffer` doesn't match its size.
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        let fits = |start: u32, len: u32, max: u32| {
+            len > 0 && start.checked_add(len).is_some_and(|end| end <= max)
+        };
+        if !x.is_multiple_of(8)
+            || !width.is_multiple_of(8)
+            || !fits(x, width, WIDTH)
+            || !fits(y, height, HEIGHT)
+            || buffer_len(width as usize, height as usize) != buffer.len()
+        {
+            return Err(DisplayError::InvalidData);
+        }
+        self.wait_until_idle();
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)?;
+        self.set_ram_counter(spi, x, y)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.frame(spi, buffer, height)
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.wait_until_idle();
+        let sequence = match self.refresh {
+            RefreshLut::Full => UPDATE_FULL,
+            RefreshLut::Quick => UPDATE_PARTIAL,
+        };
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[sequence])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle();
+        self.interface.refreshed(self.refresh);
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle();
+        self.use_full_frame(spi)?;
+
+        // clear the ram with the background color
+        let color = self.background_color.panel_byte(BLACK_BIT);
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)?;
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface.data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    /// Selects the LUT of the next refreshes
+    ///
+    /// [RefreshLut::Quick] writes the partial LUT and its voltages into the controller,
+    /// [RefreshLut::Full] lets the next refresh load the LUT from the OTP again.
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        if let RefreshLut::Quick = self.refresh {
+            self.set_partial_lut(spi)?;
+        }
+        Ok(())
+    }
+
+    fn refresh_lut(&self) -> RefreshLut {
+        self.refresh
+    }
+
+    fn max_partial_refresh_hz(&self) -> u32 {
+        // the 1.54inch e-Paper Module (V2) refreshes partially in 0.3s according to its
+        // specification on the Waveshare wiki, rounded down
+        3
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd1in54<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Writes the base image of partial refreshes into both RAMs of the controller
+    ///
+    /// The partial LUT compares the new frame with the old one, so both have to hold the
+    /// image which is on the panel. Show it with a full refresh before switching to
+    /// [RefreshLut::Quick]. Fails with [DisplayError::InvalidData] if `buffer` isn't a full frame.
+    pub fn sync_base_image(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        if buffer_len(WIDTH as usize, HEIGHT as usize) != buffer.len() {
+            return Err(DisplayError::InvalidData);
+        }
+        self.wait_until_idle();
+        self.use_full_frame(spi)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface.frame(spi, buffer, HEIGHT)
+    }
+
+    /// Sets the number of gate lines and the scan direction, for panels with another height
+    ///
+    /// The setting is kept for the next [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_driver_output_control(
+        &mut self,
+        spi: &mut SPI,
+        control: DriverOutputControl,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.driver_output = control;
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::DriverOutputControl, &control.to_bytes())
+    }
+
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    fn set_partial_lut(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegister, &LUT_PARTIAL_1IN54)?;
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::EndOption, &[LUT_PARTIAL_END_OPTION])?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::GateDrivingVoltage,
+            &[LUT_PARTIAL_GATE_VOLTAGE],
+        )?;
+        self.interface.cmd_with_data(
+            spi,
+            Command::SourceDrivingVoltage,
+            &LUT_PARTIAL_SOURCE_VOLTAGE,
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[LUT_PARTIAL_VCOM])?;
+
+        self.interface.cmd_with_data(
+            spi,
+            Command::WriteOtpSelection,
+            &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
+        )?;
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
+
+        // Enable clock signal and Analog, so they stay on between partial refreshes. Nothing
+        // is displayed, so this doesn't count as a refresh for the minimum interval.
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC0])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.wait_until_idle();
+        Ok(())
+    }
+
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0)
+    }
+
+    fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        assert!(start_x <= end_x);
+        assert!(start_y <= end_y);
+
+        // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
+        // aren't relevant
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[(start_x >> 3) as u8, (end_x >> 3) as u8],
+        )?;
+
+        // 2 Databytes: A[7:0] & 0..A[8] for each - start and end
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+    }
+
+    fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
+        // aren't relevant
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
+
+        // 2 Databytes: A[7:0] & 0..A[8]
+        self.interface.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockDelay, MockPin, MockSpi, Recorder};
+
+    fn initialized(
+        mock: &Recorder,
+    ) -> Epd1in54<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> {
+        let mut epd = Epd1in54::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        mock.clear();
+        epd
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 200);
+        assert_eq!(HEIGHT, 200);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn max_partial_refresh_hz() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let epd: Epd1in54<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> =
+            Epd1in54::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(epd.max_partial_refresh_hz(), 3);
+    }
+
+    #[test]
+    fn init_sets_the_gate_lines() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd1in54<_, _, _, _, _, MockDelay> =
+            Epd1in54::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x01), Some(std::vec![0xC7, 0x00, 0x00]));
+        assert_eq!(mock.data_after(0x11), Some(std::vec![0x03]));
+    }
+
+    #[test]
+    fn partial_frame_sets_the_window() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = initialized(&mock);
+
+        let buffer = [0x0F; 2 * 3];
+        epd.update_partial_frame(&mut mock.spi(), &buffer, 16, 150, 16, 3)
+            .unwrap();
+        assert_eq!(mock.data_after(0x44), Some(std::vec![2, 3]));
+        assert_eq!(mock.data_after(0x45), Some(std::vec![150, 0, 152, 0]));
+        assert_eq!(mock.data_after(0x4E), Some(std::vec![2]));
+        assert_eq!(mock.data_after(0x4F), Some(std::vec![150, 0]));
+        assert_eq!(mock.data_after(0x24), Some(buffer.to_vec()));
+
+        // a full frame covers the whole ram again
+        mock.clear();
+        epd.update_frame(&mut mock.spi(), &[0xFF; 5000], &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.data_after(0x44), Some(std::vec![0, 24]));
+        assert_eq!(mock.data_after(0x45), Some(std::vec![0, 0, 199, 0]));
+    }
+
+    #[test]
+    fn partial_frame_outside_the_display_is_refused() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = initialized(&mock);
+
+        let buffer = [0x0F; 2 * 3];
+        for (x, y, width, height) in [
+            (192, 0, 16, 3),
+            (0, 198, 16, 3),
+            (8, 0, 16, 0),
+            (4, 0, 16, 3),
+            (u32::MAX - 7, 0, 16, 3),
+            (0, u32::MAX, 16, 3),
+        ] {
+            assert_eq!(
+                epd.update_partial_frame(&mut mock.spi(), &buffer, x, y, width, height),
+                Err(DisplayError::InvalidData)
+            );
+        }
+        // the buffer has to match the window
+        assert_eq!(
+            epd.update_partial_frame(&mut mock.spi(), &buffer, 0, 0, 16, 2),
+            Err(DisplayError::InvalidData)
+        );
+        assert_eq!(
+            epd.sync_base_image(&mut mock.spi(), &buffer),
+            Err(DisplayError::InvalidData)
+        );
+        assert_eq!(mock.events(), []);
+    }
+
+    #[test]
+    fn sync_base_image_writes_both_rams() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = initialized(&mock);
+
+        epd.sync_base_image(&mut mock.spi(), &[0xAA; 5000]).unwrap();
+        let commands = mock.commands();
+        let old = commands.iter().position(|&c| c == 0x24).unwrap();
+        assert!(commands[old..].contains(&0x26));
+        let written: usize = mock
+            .events()
+            .iter()
+            .filter_map(|event| match event {
+                crate::mock::Event::Data(data) if data.iter().all(|&b| b == 0xAA) => {
+                    Some(data.len())
+                }
+                _ => None,
+            })
+            .sum();
+        assert_eq!(written, 2 * 5000);
+    }
+
+    #[test]
+    fn display_update_control_follows_the_lut() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = initialized(&mock);
+
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.data_after(0x22), Some(std::vec![UPDATE_FULL]));
+        assert_eq!(mock.commands().last(), Some(&0x20));
+
+        mock.clear();
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Quick))
+            .unwrap();
+        assert_eq!(mock.data_after(0x32), Some(LUT_PARTIAL_1IN54.to_vec()));
+        assert_eq!(mock.data_after(0x3F), Some(std::vec![0x02]));
+        assert_eq!(mock.data_after(0x03), Some(std::vec![0x17]));
+        assert_eq!(mock.data_after(0x04), Some(std::vec![0x41, 0xB0, 0x32]));
+        assert_eq!(mock.data_after(0x2C), Some(std::vec![0x28]));
+        assert_eq!(mock.data_after(0x22), Some(std::vec![0xC0]));
+
+        mock.clear();
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.data_after(0x22), Some(std::vec![UPDATE_PARTIAL]));
+
+        // waking up loads the OTP LUT again
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(epd.refresh_lut(), RefreshLut::Full);
+    }
+
+    #[test]
+    fn partial_lut_isnt_a_refresh() {
+        fn now_ms() -> u32 {
+            0
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = initialized(&mock);
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+
+        // loading the partial LUT activates the controller without displaying anything
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Quick))
+            .unwrap();
+        assert!(mock.commands().contains(&0x20));
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(
+            epd.display_frame(&mut mock.spi(), &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+    }
+}
@@ -5,9 +5,11 @@ use embedded_hal::{
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
+use crate::interface::{DisplayInterface, InterfaceConfig, LutLoadOrder};
+use crate::panel_setting::PanelSettingBuilder;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 //The Lookup Tables for the Display
@@ -21,6 +23,8 @@ pub const HEIGHT: u32 = 200;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
 
 use crate::color::Color;
 
@@ -33,6 +37,7 @@ mod graphics;
 pub use self::graphics::Display1in54b;
 
 /// Epd1in54b driver
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd1in54b<SPI, CS, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     color: Color,
@@ -48,7 +53,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.reset(delay, 10);
 
         // set the power settings
@@ -59,13 +64,22 @@ where
         self.interface
             .cmd_with_data(spi, Command::BoosterSoftStart, &[0x07, 0x07, 0x07])?;
 
+        let lut_first = self
+            .interface
+            .lut_before_power_on(LutLoadOrder::AfterPowerOn);
+        if lut_first {
+            self.set_lut(spi, None)?;
+        }
+
         // power on
-        self.command(spi, Command::PowerOn)?;
-        delay.delay_ms(5);
+        self.interface
+            .cmd_then_delay(spi, delay, Command::PowerOn, 5)?;
         self.wait_until_idle();
+        self.interface.power_on_settle(delay);
 
         // set the panel settings
-        self.cmd_with_data(spi, Command::PanelSetting, &[0xCF])?;
+        let setting = PanelSettingBuilder::new().resolution(3).build();
+        self.cmd_with_data(spi, Command::PanelSetting, &[setting])?;
 
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x37])?;
 
@@ -77,10 +91,13 @@ where
 
         self.cmd_with_data(spi, Command::VcmDcSetting, &[0x0E])?;
 
-        self.set_lut(spi, None)?;
+        if !lut_first {
+            self.set_lut(spi, None)?;
+        }
 
         self.wait_until_idle();
 
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -100,18 +117,24 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
 
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.send_resolution(spi)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
-        for b in black {
+        for b in self.interface.frame_rows(black, HEIGHT).flatten() {
             let expanded = expand_bits(*b);
             self.interface.data(spi, &expanded)?;
         }
@@ -122,9 +145,10 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface.data(spi, chromatic)?;
+        self.interface.frame(spi, chromatic, HEIGHT)?;
         Ok(())
     }
 }
@@ -147,18 +171,37 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd1in54b { interface, color };
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        Epd1in54b { interface, color }
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
@@ -174,11 +217,51 @@ where
         //NOTE: The example code has a 1s delay here
 
         self.command(spi, Command::PowerOff)?;
+        self.interface.set_sleeping();
 
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 10, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
@@ -203,13 +286,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.send_resolution(spi)?;
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
-        for b in buffer {
+        for b in self.interface.frame_rows(buffer, HEIGHT).flatten() {
             // Two bits per pixel
             let expanded = expand_bits(*b);
             self.interface.data(spi, &expanded)?;
@@ -229,19 +313,13 @@ where
     }
 
     #[allow(unused)]
-    fn update_partial_frame(
+    fn display_frame(
         &mut self,
         spi: &mut SPI,
-        buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
-    }
-
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.wait_until_idle();
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
@@ -252,13 +330,18 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.send_resolution(spi)?;
 
@@ -268,8 +351,11 @@ where
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
         // Uses 2 bits per pixel
-        self.interface
-            .data_x_times(spi, color, 2 * (WIDTH * HEIGHT / 8))?;
+        self.interface.data_x_times(
+            spi,
+            DEFAULT_BACKGROUND_COLOR.panel_byte(BLACK_BIT),
+            2 * (WIDTH * HEIGHT / 8),
+        )?;
 
         // Clear the red
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
@@ -282,7 +368,7 @@ where
         &mut self,
         spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface
             .cmd_with_data(spi, Command::LutForVcom, LUT_VCOM0)?;
         self.interface
@@ -315,11 +401,11 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -328,15 +414,15 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
     fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -1,5 +1,5 @@
 use crate::epd1in54c::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
-use crate::graphics::{Display, DisplayRotation};
+use crate::graphics::{rotated_size, Display, DisplayRotation};
 use embedded_graphics_core::pixelcolor::BinaryColor;
 use embedded_graphics_core::prelude::*;
 
@@ -37,7 +37,7 @@ impl DrawTarget for Display1in54c {
 
 impl OriginDimensions for Display1in54c {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        rotated_size(WIDTH, HEIGHT, self.rotation)
     }
 }
 
@@ -58,3 +58,22 @@ impl Display for Display1in54c {
         self.rotation
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // pixel (0, 0) is the most significant bit of the first byte, black clears the bit
+    #[test]
+    fn graphics_origin_bit() {
+        use crate::color::Black;
+        use crate::graphics::Display;
+        use embedded_graphics::prelude::{Drawable, Pixel, Point};
+
+        let mut display = Display1in54c::default();
+        let _ = Pixel(Point::new(0, 0), Black).draw(&mut display);
+        assert_eq!(display.buffer()[0], 0b0111_1111);
+        let _ = Pixel(Point::new(1, 0), Black).draw(&mut display);
+        assert_eq!(display.buffer()[0], 0b0011_1111);
+    }
+}
@@ -5,9 +5,10 @@ use embedded_hal::{
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
+use crate::interface::{DisplayInterface, InterfaceConfig};
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 /// Width of epd1in54 in pixels
@@ -17,6 +18,8 @@ pub const HEIGHT: u32 = 152;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
 const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
 
 use crate::color::Color;
@@ -31,6 +34,7 @@ mod graphics;
 pub use self::graphics::Display1in54c;
 
 /// Epd1in54c driver
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd1in54c<SPI, CS, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     color: Color,
@@ -46,7 +50,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         // Based on Reference Program Code from:
         // https://www.waveshare.com/w/upload/a/ac/1.54inch_e-Paper_Module_C_Specification.pdf
         // and:
@@ -57,9 +61,10 @@ where
         self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
 
         // power on
-        self.command(spi, Command::PowerOn)?;
-        delay.delay_ms(5);
+        self.interface
+            .cmd_then_delay(spi, delay, Command::PowerOn, 5)?;
         self.wait_until_idle();
+        self.interface.power_on_settle(delay);
 
         // set the panel settings
         self.cmd_with_data(spi, Command::PanelSetting, &[0x0f, 0x0d])?;
@@ -69,6 +74,7 @@ where
 
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x77])?;
 
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -88,14 +94,21 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
 
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
-        self.cmd_with_data(spi, Command::DataStartTransmission1, black)?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.frame(spi, black, HEIGHT)?;
 
         Ok(())
     }
@@ -104,9 +117,11 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
-        self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.frame(spi, chromatic, HEIGHT)?;
 
         Ok(())
     }
@@ -130,28 +145,87 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd1in54c { interface, color };
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        Epd1in54c { interface, color }
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
 
         self.command(spi, Command::PowerOff)?;
         self.wait_until_idle();
         self.cmd_with_data(spi, Command::DeepSleep, &[0xa5])?;
+        self.interface.set_sleeping();
 
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
@@ -176,7 +250,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.update_achromatic_frame(spi, buffer)?;
 
         // Clear the chromatic layer
@@ -189,19 +264,13 @@ where
     }
 
     #[allow(unused)]
-    fn update_partial_frame(
+    fn display_frame(
         &mut self,
         spi: &mut SPI,
-        buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!()
-    }
-
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.command(spi, Command::DisplayRefresh)?;
         self.wait_until_idle();
 
@@ -213,20 +282,29 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
 
         // Clear the black
         self.command(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
+        self.interface.data_x_times(
+            spi,
+            DEFAULT_BACKGROUND_COLOR.panel_byte(BLACK_BIT),
+            NUM_DISPLAY_BITS,
+        )?;
 
         // Clear the chromatic
         self.command(spi, Command::DataStartTransmission2)?;
@@ -239,7 +317,7 @@ where
         &mut self,
         _spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         Ok(())
     }
 
@@ -257,11 +335,11 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -270,15 +348,15 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
     fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -57,26 +57,6 @@ pub(crate) enum Command {
     Nop = 0x7F,
 }
 
-pub(crate) struct DriverOutput {
-    pub scan_is_linear: bool,
-    pub scan_g0_is_first: bool,
-    pub scan_dir_incr: bool,
-
-    pub width: u16,
-}
-
-impl DriverOutput {
-    pub fn to_bytes(&self) -> [u8; 3] {
-        [
-            self.width as u8,
-            (self.width >> 8) as u8,
-            *0u8.set_bit(0, !self.scan_dir_incr)
-                .set_bit(1, !self.scan_g0_is_first)
-                .set_bit(2, !self.scan_is_linear),
-        ]
-    }
-}
-
 /// These are not directly documented, but the bitfield is easily reversed from
 /// documentation and sample code
 /// [7|6|5|4|3|2|1|0]
@@ -89,7 +69,6 @@ impl DriverOutput {
 ///  | | `------------- load temp
 ///  | `--------------- enable clock
 ///  `----------------- enable analog
-
 pub(crate) struct DisplayUpdateControl2(pub u8);
 #[allow(dead_code)]
 impl DisplayUpdateControl2 {
@@ -133,8 +112,7 @@ impl DisplayUpdateControl2 {
     }
 }
 
-#[allow(dead_code)]
-
+#[allow(dead_code, clippy::enum_variant_names)]
 pub(crate) enum DataEntryModeIncr {
     XDecrYDecr = 0x0,
     XIncrYDecr = 0x1,
@@ -143,7 +121,6 @@ pub(crate) enum DataEntryModeIncr {
 }
 
 #[allow(dead_code)]
-
 pub(crate) enum DataEntryModeDir {
     XDir = 0x0,
     YDir = 0x4,
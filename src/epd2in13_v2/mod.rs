@@ -15,14 +15,17 @@ use embedded_hal::{
 
 use crate::buffer_len;
 use crate::color::Color;
-use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::interface::{DisplayInterface, InterfaceConfig};
+use crate::traits::{
+    DisplayError, DisplayState, DriverOutputControl, InternalWiAdditions, RefreshLut,
+    WaveshareDisplay,
+};
 
 pub(crate) mod command;
 use self::command::{
     BorderWaveForm, BorderWaveFormFixLevel, BorderWaveFormGs, BorderWaveFormVbd, Command,
-    DataEntryModeDir, DataEntryModeIncr, DeepSleepMode, DisplayUpdateControl2, DriverOutput,
-    GateDrivingVoltage, I32Ext, SourceDrivingVoltage, Vcom,
+    DataEntryModeDir, DataEntryModeIncr, DeepSleepMode, DisplayUpdateControl2, GateDrivingVoltage,
+    I32Ext, SourceDrivingVoltage, Vcom,
 };
 
 pub(crate) mod constants;
@@ -42,9 +45,22 @@ pub const HEIGHT: u32 = 250;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
+
+/// Update sequence of a full refresh with the LUT which is in the registers
+fn full_display_update() -> DisplayUpdateControl2 {
+    DisplayUpdateControl2::new()
+        .enable_clock()
+        .enable_analog()
+        .display()
+        .disable_analog()
+        .disable_clock()
+}
 
 /// Epd2in13 (V2) driver
 ///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd2in13<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
@@ -54,6 +70,8 @@ pub struct Epd2in13<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Background Color
     background_color: Color,
     refresh: RefreshLut,
+    /// Gate lines and scan direction
+    driver_output: DriverOutputControl,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -66,7 +84,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         // HW reset
         self.interface.reset(delay, 10);
 
@@ -80,7 +98,7 @@ where
             // self.cmd_with_data(spi, Command::WriteOtpSelection, &[0, 0, 0, 0, 0x40, 0, 0])?;
 
             // During partial update, clock/analog are not disabled between 2
-            // updates.
+            // updates. Nothing is displayed, so this isn't a refresh.
             self.set_display_update_control_2(
                 spi,
                 DisplayUpdateControl2::new().enable_analog().enable_clock(),
@@ -101,15 +119,8 @@ where
             self.command(spi, Command::SwReset)?;
             self.wait_until_idle();
 
-            self.set_driver_output(
-                spi,
-                DriverOutput {
-                    scan_is_linear: true,
-                    scan_g0_is_first: true,
-                    scan_dir_incr: true,
-                    width: (HEIGHT - 1) as u16,
-                },
-            )?;
+            let driver_output = self.driver_output.to_bytes();
+            self.cmd_with_data(spi, Command::DriverOutputControl, &driver_output)?;
 
             // These 2 are the reset values
             self.set_dummy_line_period(spi, 0x30)?;
@@ -146,6 +157,7 @@ where
         }
 
         self.wait_until_idle();
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -168,23 +180,87 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let mut epd = Epd2in13 {
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        Epd2in13 {
             interface: DisplayInterface::new(cs, busy, dc, rst),
             sleep_mode: DeepSleepMode::Mode1,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
-        };
+            driver_output: DriverOutputControl::new(HEIGHT as u16),
+        }
+    }
 
-        epd.init(spi, delay)?;
-        Ok(epd)
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn can_partial_refresh(&self) -> bool {
+        self.interface.has_base_image()
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::SwReset, 10, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
 
         // All sample code enables and disables analog/clocks...
@@ -199,6 +275,7 @@ where
         self.command(spi, Command::MasterActivation)?;
 
         self.set_sleep_mode(spi, self.sleep_mode)?;
+        self.interface.set_sleeping();
         Ok(())
     }
 
@@ -207,19 +284,22 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, 0, 0)?;
 
-        self.cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
 
         if self.refresh == RefreshLut::Full {
             // Always keep the base buffer equal to current if not doing partial refresh.
             self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
             self.set_ram_address_counters(spi, 0, 0)?;
 
-            self.cmd_with_data(spi, Command::WriteRamRed, buffer)?;
+            self.interface.cmd(spi, Command::WriteRamRed)?;
+            self.interface.frame(spi, buffer, HEIGHT)?;
         }
         Ok(())
     }
@@ -235,7 +315,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         assert!((width * height / 8) as usize == buffer.len());
 
         // This should not be used when doing partial refresh. The RAM_RED must
@@ -264,23 +345,22 @@ where
 
     /// Never use directly this function when using partial refresh, or also
     /// keep the base buffer in syncd using `set_partial_base_buffer` function.
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         if self.refresh == RefreshLut::Full {
-            self.set_display_update_control_2(
-                spi,
-                DisplayUpdateControl2::new()
-                    .enable_clock()
-                    .enable_analog()
-                    .display()
-                    .disable_analog()
-                    .disable_clock(),
-            )?;
+            self.set_display_update_control_2(spi, full_display_update())?;
         } else {
             self.set_display_update_control_2(spi, DisplayUpdateControl2::new().display())?;
         }
         self.command(spi, Command::MasterActivation)?;
         self.wait_until_idle();
 
+        self.interface.refreshed(self.refresh);
         Ok(())
     }
 
@@ -289,7 +369,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
 
@@ -299,8 +379,13 @@ where
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        let color = self.background_color.get_byte_value();
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        let color = self.background_color.panel_byte(BLACK_BIT);
 
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, 0, 0)?;
@@ -347,15 +432,27 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        let buffer = match refresh_rate {
-            Some(RefreshLut::Full) | None => &LUT_FULL_UPDATE,
-            Some(RefreshLut::Quick) => &LUT_PARTIAL_UPDATE,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        // None selects the full LUT, as before the LUT was remembered
+        self.refresh = refresh_rate.unwrap_or(RefreshLut::Full);
+        let buffer = match self.refresh {
+            RefreshLut::Full => &LUT_FULL_UPDATE,
+            RefreshLut::Quick => &LUT_PARTIAL_UPDATE,
         };
 
         self.cmd_with_data(spi, Command::WriteLutRegister, buffer)
     }
 
+    fn refresh_lut(&self) -> RefreshLut {
+        self.refresh
+    }
+
+    fn max_partial_refresh_hz(&self) -> u32 {
+        // Waveshare wiki, 2.13inch e-Paper HAT (V2) specification: 2s for a full and 0.3s
+        // for a partial refresh, rounded down
+        3
+    }
+
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
@@ -376,12 +473,51 @@ where
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         assert!(buffer_len(WIDTH as usize, HEIGHT as usize) == buffer.len());
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
         self.set_ram_address_counters(spi, 0, 0)?;
 
-        self.cmd_with_data(spi, Command::WriteRamRed, buffer)?;
+        self.interface.cmd(spi, Command::WriteRamRed)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
+        Ok(())
+    }
+
+    /// Writes `buffer` into both the new and the base (old) RAM
+    ///
+    /// Use it after a full refresh, so the first partial refresh compares with the image
+    /// which is on the panel and doesn't leave ghosts of the previous frame.
+    pub fn sync_base_image(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        assert!(buffer_len(WIDTH as usize, HEIGHT as usize) == buffer.len());
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
+        self.set_ram_address_counters(spi, 0, 0)?;
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
+
+        self.set_partial_base_buffer(spi, buffer)
+    }
+
+    /// Loads the base map of partial refreshes and shows it with a full refresh
+    ///
+    /// Writes `buffer` into both RAM banks, like `displayPartBaseImage` of the Waveshare
+    /// code. Call it once before the partial refreshes, otherwise the first one compares
+    /// with whatever the RAM held and leaves ghosts.
+    pub fn set_base_map(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.sync_base_image(spi, buffer)?;
+        self.interface.check_refresh_interval()?;
+        self.set_display_update_control_2(spi, full_display_update())?;
+        self.command(spi, Command::MasterActivation)?;
+        self.wait_until_idle();
+        self.interface.refreshed(RefreshLut::Full);
         Ok(())
     }
 
@@ -392,12 +528,15 @@ where
 
     /// Sets the refresh mode. When changing mode, the screen will be
     /// re-initialized accordingly.
+    ///
+    /// The re-initialisation resets the controller, so set the base image for the partial
+    /// refreshes afterwards, e.g. with [set_base_map](Epd2in13::set_base_map).
     pub fn set_refresh(
         &mut self,
         spi: &mut SPI,
         delay: &mut DELAY,
         refresh: RefreshLut,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         if self.refresh != refresh {
             self.refresh = refresh;
             self.init(spi, delay)?;
@@ -409,7 +548,7 @@ where
         &mut self,
         spi: &mut SPI,
         start: u16,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         assert!(start <= 295);
         self.cmd_with_data(
             spi,
@@ -422,7 +561,7 @@ where
         &mut self,
         spi: &mut SPI,
         borderwaveform: BorderWaveForm,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::BorderWaveformControl,
@@ -430,7 +569,11 @@ where
         )
     }
 
-    fn set_vcom_register(&mut self, spi: &mut SPI, vcom: Vcom) -> Result<(), SPI::Error> {
+    fn set_vcom_register(
+        &mut self,
+        spi: &mut SPI,
+        vcom: Vcom,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd_with_data(spi, Command::WriteVcomRegister, &[vcom.0])
     }
 
@@ -438,7 +581,7 @@ where
         &mut self,
         spi: &mut SPI,
         voltage: GateDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd_with_data(spi, Command::GateDrivingVoltageCtrl, &[voltage.0])
     }
 
@@ -446,12 +589,16 @@ where
         &mut self,
         spi: &mut SPI,
         number_of_lines: u8,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         assert!(number_of_lines <= 127);
         self.cmd_with_data(spi, Command::SetDummyLinePeriod, &[number_of_lines])
     }
 
-    fn set_gate_line_width(&mut self, spi: &mut SPI, width: u8) -> Result<(), SPI::Error> {
+    fn set_gate_line_width(
+        &mut self,
+        spi: &mut SPI,
+        width: u8,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd_with_data(spi, Command::SetGateLineWidth, &[width & 0x0F])
     }
 
@@ -462,7 +609,7 @@ where
         vsh1: SourceDrivingVoltage,
         vsh2: SourceDrivingVoltage,
         vsl: SourceDrivingVoltage,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::SourceDrivingVoltageCtrl,
@@ -476,19 +623,19 @@ where
         &mut self,
         spi: &mut SPI,
         value: DisplayUpdateControl2,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[value.0])
     }
 
     /// Triggers the deep sleep mode
-    fn set_sleep_mode(&mut self, spi: &mut SPI, mode: DeepSleepMode) -> Result<(), SPI::Error> {
+    fn set_sleep_mode(
+        &mut self,
+        spi: &mut SPI,
+        mode: DeepSleepMode,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd_with_data(spi, Command::DeepSleepMode, &[mode as u8])
     }
 
-    fn set_driver_output(&mut self, spi: &mut SPI, output: DriverOutput) -> Result<(), SPI::Error> {
-        self.cmd_with_data(spi, Command::DriverOutputControl, &output.to_bytes())
-    }
-
     /// Sets the data entry mode (ie. how X and Y positions changes when writing
     /// data to RAM)
     fn set_data_entry_mode(
@@ -496,7 +643,7 @@ where
         spi: &mut SPI,
         counter_incr_mode: DataEntryModeIncr,
         counter_direction: DataEntryModeDir,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         let mode = counter_incr_mode as u8 | counter_direction as u8;
         self.cmd_with_data(spi, Command::DataEntryModeSetting, &[mode])
     }
@@ -509,7 +656,7 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd_with_data(
             spi,
             Command::SetRamXAddressStartEndPosition,
@@ -534,7 +681,7 @@ where
         spi: &mut SPI,
         x: u32,
         y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])?;
 
@@ -546,7 +693,7 @@ where
         Ok(())
     }
 
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -555,18 +702,32 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
+    /// Sets the number of gate lines and the scan direction, for panels with another height
+    ///
+    /// The setting is kept for the next [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_driver_output_control(
+        &mut self,
+        spi: &mut SPI,
+        control: DriverOutputControl,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.driver_output = control;
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::DriverOutputControl, &control.to_bytes())
+    }
+
     fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{MockDelay, Recorder};
 
     #[test]
     fn epd_size() {
@@ -574,4 +735,106 @@ mod tests {
         assert_eq!(HEIGHT, 250);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn refresh_lut_reflects_last_set_mode() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut spi = mock.spi();
+        let mut epd: Epd2in13<_, _, _, _, _, MockDelay> =
+            Epd2in13::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        assert_eq!(epd.refresh_lut(), RefreshLut::Full);
+
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+        assert_eq!(epd.refresh_lut(), RefreshLut::Quick);
+        assert_eq!(mock.data_after(0x32).unwrap(), LUT_PARTIAL_UPDATE);
+
+        // None still selects the full LUT
+        mock.clear();
+        epd.set_lut(&mut spi, None).unwrap();
+        assert_eq!(epd.refresh_lut(), RefreshLut::Full);
+        assert_eq!(mock.data_after(0x32).unwrap(), LUT_FULL_UPDATE);
+
+        epd.set_lut(&mut spi, Some(RefreshLut::Quick)).unwrap();
+
+        epd.set_lut(&mut spi, Some(RefreshLut::Full)).unwrap();
+        assert_eq!(epd.refresh_lut(), RefreshLut::Full);
+    }
+
+    #[test]
+    fn sync_base_image_writes_both_rams() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in13<_, _, _, _, _, MockDelay> =
+            Epd2in13::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        let buffer = [0x5A; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        epd.sync_base_image(&mut mock.spi(), &buffer).unwrap();
+        assert_eq!(mock.data_after(0x24), Some(buffer.to_vec()));
+        assert_eq!(mock.data_after(0x26), Some(buffer.to_vec()));
+    }
+
+    #[test]
+    fn set_base_map_writes_both_rams_and_refreshes() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in13<_, _, _, _, _, MockDelay> =
+            Epd2in13::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Quick))
+            .unwrap();
+        mock.clear();
+
+        let buffer = [0x5A; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        epd.set_base_map(&mut mock.spi(), &buffer).unwrap();
+        assert_eq!(mock.data_after(0x24), Some(buffer.to_vec()));
+        assert_eq!(mock.data_after(0x26), Some(buffer.to_vec()));
+
+        // a full refresh of the base map, also with the partial LUT selected
+        let commands = mock.commands();
+        let old = commands.iter().position(|&c| c == 0x26).unwrap();
+        assert_eq!(commands[old + 1..], [0x22, 0x20]);
+        assert_eq!(mock.data_after(0x22), Some(std::vec![0xC7]));
+        assert!(epd.can_partial_refresh());
+    }
+
+    #[test]
+    fn max_partial_refresh_hz() {
+        use crate::mock::{MockPin, MockSpi};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let epd: Epd2in13<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> =
+            Epd2in13::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(epd.max_partial_refresh_hz(), 3);
+    }
+
+    #[test]
+    fn refresh_paths_keep_the_refresh_interval() {
+        fn now_ms() -> u32 {
+            0
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in13<_, _, _, _, _, MockDelay> =
+            Epd2in13::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Quick))
+            .unwrap();
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+
+        // the init of the quick mode activates the controller without displaying anything
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert!(mock.commands().contains(&0x20));
+        let buffer = [0xFF; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        epd.set_base_map(&mut mock.spi(), &buffer).unwrap();
+
+        mock.clear();
+        assert_eq!(
+            epd.set_base_map(&mut mock.spi(), &buffer),
+            Err(DisplayError::RefreshTooSoon)
+        );
+        assert_eq!(
+            epd.display_frame(&mut mock.spi(), &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+        assert!(!mock.commands().contains(&0x20));
+    }
 }
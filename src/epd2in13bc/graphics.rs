@@ -1,6 +1,6 @@
-use crate::color::TriColor;
+use crate::color::{ChromaticColor, TriColor};
 use crate::epd2in13bc::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
-use crate::graphics::{DisplayColorRendering, DisplayRotation, TriDisplay};
+use crate::graphics::{rotated_size, DisplayColorRendering, DisplayRotation, TriDisplay};
 use embedded_graphics_core::prelude::*;
 
 /// Full size buffer for use with the 2.13" b/c EPD
@@ -12,6 +12,7 @@ pub struct Display2in13bc {
     // * &buffer[NUM_DISPLAY_BITS..2*NUM_DISPLAY_BITS] for chromatic buffer
     buffer: [u8; 2 * NUM_DISPLAY_BITS as usize],
     rotation: DisplayRotation,
+    chromatic: ChromaticColor,
 }
 
 impl Default for Display2in13bc {
@@ -19,6 +20,7 @@ impl Default for Display2in13bc {
         Display2in13bc {
             buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 2 * NUM_DISPLAY_BITS as usize],
             rotation: DisplayRotation::default(),
+            chromatic: ChromaticColor::default(),
         }
     }
 }
@@ -39,7 +41,7 @@ impl DrawTarget for Display2in13bc {
 
 impl OriginDimensions for Display2in13bc {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        rotated_size(WIDTH, HEIGHT, self.rotation)
     }
 }
 
@@ -60,6 +62,14 @@ impl TriDisplay for Display2in13bc {
         self.rotation
     }
 
+    fn set_chromatic_color(&mut self, chromatic: ChromaticColor) {
+        self.chromatic = chromatic;
+    }
+
+    fn chromatic_color(&self) -> ChromaticColor {
+        self.chromatic
+    }
+
     fn chromatic_offset(&self) -> usize {
         NUM_DISPLAY_BITS as usize
     }
@@ -72,3 +82,26 @@ impl TriDisplay for Display2in13bc {
         &self.buffer[self.chromatic_offset()..]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // pixel (0, 0) is the most significant bit of the first byte of both planes, black
+    // clears the bit of the b/w plane, with positive rendering: a cleared bit in the chromatic plane is chromatic
+    #[test]
+    fn graphics_origin_bit() {
+        use crate::color::TriColor;
+        use crate::graphics::TriDisplay;
+        use embedded_graphics::prelude::{Drawable, Pixel, Point};
+
+        let mut display = Display2in13bc::default();
+        let _ = Pixel(Point::new(0, 0), TriColor::Black).draw(&mut display);
+        assert_eq!(display.bw_buffer()[0] & 0x80, 0b0000_0000);
+        assert_eq!(display.chromatic_buffer()[0] & 0x80, 0b1000_0000);
+
+        let _ = Pixel(Point::new(0, 0), TriColor::Chromatic).draw(&mut display);
+        assert_eq!(display.bw_buffer()[0] & 0x80, 0b1000_0000);
+        assert_eq!(display.chromatic_buffer()[0] & 0x80, 0b0000_0000);
+    }
+}
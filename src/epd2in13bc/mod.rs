@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
 //!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle, PrimitiveStyleBuilder}};
 //!use epd_waveshare::{epd2in13bc::*, prelude::*};
 //!#
@@ -54,9 +54,11 @@ use embedded_hal::{
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
+use crate::interface::{DisplayInterface, InterfaceConfig};
+use crate::panel_setting::PanelSettingBuilder;
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 /// Width of epd2in13bc in pixels
@@ -70,6 +72,8 @@ pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
 const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
 
 const IS_BUSY_LOW: bool = true;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
 const VCOM_DATA_INTERVAL: u8 = 0x07;
 const WHITE_BORDER: u8 = 0x70;
 const BLACK_BORDER: u8 = 0x30;
@@ -87,6 +91,7 @@ mod graphics;
 pub use self::graphics::Display2in13bc;
 
 /// Epd2in13bc driver
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd2in13bc<SPI, CS, BUSY, DC, RST, DELAY> {
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     color: TriColor,
@@ -102,7 +107,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         // Values taken from datasheet and sample code
 
         self.interface.reset(delay, 10);
@@ -112,12 +117,14 @@ where
             .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
 
         // power on
-        self.command(spi, Command::PowerOn)?;
-        delay.delay_ms(5);
+        self.interface
+            .cmd_then_delay(spi, delay, Command::PowerOn, 5)?;
         self.wait_until_idle();
+        self.interface.power_on_settle(delay);
 
         // set the panel settings
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x8F])?;
+        let setting = PanelSettingBuilder::new().resolution(2).build();
+        self.cmd_with_data(spi, Command::PanelSetting, &[setting])?;
 
         self.cmd_with_data(
             spi,
@@ -132,6 +139,7 @@ where
 
         self.wait_until_idle();
 
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -151,7 +159,8 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
@@ -159,9 +168,14 @@ where
     /// Update only the black/white data of the display.
     ///
     /// Finish by calling `update_chromatic_frame`.
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error> {
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.interface.data(spi, black)?;
+        self.interface.frame(spi, black, HEIGHT)?;
         Ok(())
     }
 
@@ -172,9 +186,10 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
-        self.interface.data(spi, chromatic)?;
+        self.interface.frame(spi, chromatic, HEIGHT)?;
 
         self.wait_until_idle();
         Ok(())
@@ -199,18 +214,37 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
-        let interface = DisplayInterface::new(cs, busy, dc, rst);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd2in13bc { interface, color };
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
 
         epd.init(spi, delay)?;
 
         Ok(epd)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        Epd2in13bc { interface, color }
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         // Section 8.2 from datasheet
         self.interface.cmd_with_data(
             spi,
@@ -223,11 +257,51 @@ where
         self.wait_until_idle();
 
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
 
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 10, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
@@ -252,13 +326,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
-        self.interface.data(spi, buffer)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
 
         // Clear the chromatic layer
-        let color = self.color.get_byte_value();
+        let color = self.color.panel_byte(BLACK_BIT);
 
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface.data_x_times(spi, color, NUM_DISPLAY_BITS)?;
@@ -267,7 +342,12 @@ where
         Ok(())
     }
 
-    #[allow(unused)]
+    /// Updates the black/white plane inside the window, the chromatic plane stays as it is
+    ///
+    /// Made for the B V3 panels, where the chromatic pixels stay in place while a black
+    /// region changes. The next `display_frame` still does a full tri-color refresh, so the
+    /// whole panel flashes, the red/yellow pixels are just not uploaded again.
+    /// `x` and `width` are rounded to multiples of 8.
     fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -276,11 +356,45 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        let x_end = x + width - 1;
+        let y_end = y + height - 1;
+
+        self.command(spi, Command::PartialIn)?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (x & 0xf8) as u8,
+                (x_end | 0x07) as u8,
+                (y >> 8) as u8,
+                y as u8,
+                (y_end >> 8) as u8,
+                y_end as u8,
+                // gates scan both inside and outside of the partial window
+                0x01,
+            ],
+        )?;
+
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.data(spi, buffer)?;
+
+        self.command(spi, Command::PartialOut)?;
+        self.wait_until_idle();
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.command(spi, Command::DisplayRefresh)?;
 
         self.wait_until_idle();
@@ -292,16 +406,21 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.send_resolution(spi)?;
 
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
+        let color = DEFAULT_BACKGROUND_COLOR.panel_byte(BLACK_BIT);
 
         // Clear the black
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
@@ -320,7 +439,7 @@ where
         &mut self,
         _spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         Ok(())
     }
 
@@ -338,11 +457,11 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -351,15 +470,15 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
     fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -371,7 +490,11 @@ where
     }
 
     /// Set the outer border of the display to the chosen color.
-    pub fn set_border_color(&mut self, spi: &mut SPI, color: TriColor) -> Result<(), SPI::Error> {
+    pub fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: TriColor,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         let border = match color {
             TriColor::Black => BLACK_BORDER,
             TriColor::White => WHITE_BORDER,
@@ -384,3 +507,46 @@ where
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Event, MockDelay, Recorder};
+
+    #[test]
+    fn partial_frame_only_writes_the_black_plane() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in13bc<_, _, _, _, _, MockDelay> =
+            Epd2in13bc::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        let buffer = [0x0F; 2 * 20];
+        epd.update_partial_frame(&mut mock.spi(), &buffer, 12, 200, 16, 20)
+            .unwrap();
+        assert_eq!(
+            mock.events(),
+            [
+                Event::Command(0x91),
+                Event::Command(0x90),
+                Event::data(&[0x08, 0x1F, 0x00, 200, 0x00, 219, 0x01]),
+                Event::Command(0x10),
+                Event::data(&buffer),
+                Event::Command(0x92),
+            ]
+        );
+        assert!(!mock.commands().contains(&0x13));
+    }
+
+    #[test]
+    fn clear_frame_sends_white_on_both_planes() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in13bc<_, _, _, _, _, MockDelay> =
+            Epd2in13bc::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+        let white = [0xFF; NUM_DISPLAY_BITS as usize];
+        assert_eq!(mock.data_after(0x10).unwrap(), white);
+        assert_eq!(mock.data_after(0x13).unwrap(), white);
+    }
+}
@@ -0,0 +1,98 @@
+//! SPI Commands for the Waveshare 2.36" (G) E-Ink Display
+
+use crate::traits;
+
+/// Epd2in36g commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// Several of the commands aren't part of any public datasheet, they are sent
+/// as is by the Waveshare reference code.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift
+    /// direction, booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0".
+    PowerOff = 0x02,
+
+    /// Setting Power OFF sequence
+    PowerOffSequenceSetting = 0x03,
+
+    /// Turning On the Power
+    ///
+    /// After the Power ON command, the driver will power on following the Power ON
+    /// sequence. Once complete, the BUSY signal will become "1".
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The deep sleep mode would return to stand-by by hardware reset.
+    ///
+    /// The only one parameter is a check code, the command would be excuted if check code = 0xA5.
+    DeepSleep = 0x07,
+
+    /// This command starts transmitting the 2 bit per pixel data and writes it into SRAM.
+    DataStartTransmission = 0x10,
+
+    /// After this command is issued, driver will refresh display (data/VCOM) according to
+    /// SRAM data and LUT.
+    DisplayRefresh = 0x12,
+
+    /// Enables the internal temperature sensor
+    TemperatureSensor = 0x41,
+
+    /// This command indicates the interval of Vcom and data output.
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// This command defines the non-overlap period of Gate and Source.
+    TconSetting = 0x60,
+
+    /// This command defines the resolution setting.
+    TconResolution = 0x61,
+
+    /// Undocumented, sent twice right after the reset
+    Undocumented66 = 0x66,
+
+    /// Undocumented, set to 0x01 before and to 0x00 after the data transmission
+    Undocumented68 = 0x68,
+
+    /// Undocumented, the last command of the init sequence before powering on
+    Undocumented84 = 0x84,
+
+    /// Undocumented, part of the init sequence
+    UndocumentedB0 = 0xB0,
+
+    /// Undocumented, part of the init sequence
+    UndocumentedF0 = 0xF0,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::DataStartTransmission.address(), 0x10);
+        assert_eq!(Command::TconResolution.address(), 0x61);
+        assert_eq!(Command::Undocumented68.address(), 0x68);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::color::QuadColor;
+use crate::epd2in36g::{buffer_len, DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
+use crate::graphics::{rotated_size, DisplayRotation, QuadDisplay};
+use embedded_graphics_core::prelude::*;
+
+/// Full size buffer for use with the 2in36g EPD
+///
+/// Can also be manually constructed:
+/// `buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH / 4 * HEIGHT]`
+pub struct Display2in36g {
+    buffer: [u8; buffer_len(WIDTH as usize, HEIGHT as usize)],
+    rotation: DisplayRotation,
+}
+
+impl Default for Display2in36g {
+    fn default() -> Self {
+        Display2in36g {
+            buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value();
+                buffer_len(WIDTH as usize, HEIGHT as usize)],
+            rotation: DisplayRotation::default(),
+        }
+    }
+}
+
+impl DrawTarget for Display2in36g {
+    type Color = QuadColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Display2in36g {
+    fn size(&self) -> Size {
+        rotated_size(WIDTH, HEIGHT, self.rotation)
+    }
+}
+
+impl QuadDisplay for Display2in36g {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd2in36g;
+    use crate::graphics::{DisplayRotation, QuadDisplay};
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{Line, PrimitiveStyle},
+    };
+
+    // test buffer length
+    #[test]
+    fn graphics_size() {
+        let display = Display2in36g::default();
+        assert_eq!(display.buffer().len(), 12432);
+    }
+
+    // test default background color on all bytes
+    #[test]
+    fn graphics_default() {
+        let display = Display2in36g::default();
+        for &byte in display.buffer() {
+            assert_eq!(byte, epd2in36g::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    #[test]
+    fn graphics_pixel_packing() {
+        let mut display = Display2in36g::default();
+
+        let _ = Pixel(Point::new(0, 0), QuadColor::Red).draw(&mut display);
+        let _ = Pixel(Point::new(2, 0), QuadColor::Yellow).draw(&mut display);
+        let _ = Pixel(Point::new(3, 0), QuadColor::Black).draw(&mut display);
+        let _ = Pixel(Point::new(4, 1), QuadColor::Red).draw(&mut display);
+
+        let buffer = display.buffer();
+        assert_eq!(buffer[0], 0b11_01_10_00);
+        assert_eq!(buffer[WIDTH as usize / 4 + 1], 0b11_01_01_01);
+    }
+
+    #[test]
+    fn graphics_rotation_0() {
+        let mut display = Display2in36g::default();
+
+        let _ = Line::new(Point::new(0, 0), Point::new(3, 0))
+            .into_styled(PrimitiveStyle::with_stroke(QuadColor::Black, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+
+        assert_eq!(buffer[0], QuadColor::Black.get_byte_value());
+
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, epd2in36g::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_90() {
+        let mut display = Display2in36g::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+
+        let _ = Line::new(Point::new(0, 164), Point::new(0, 167))
+            .into_styled(PrimitiveStyle::with_stroke(QuadColor::Black, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+
+        assert_eq!(buffer[0], QuadColor::Black.get_byte_value());
+
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, epd2in36g::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_180() {
+        let mut display = Display2in36g::default();
+        display.set_rotation(DisplayRotation::Rotate180);
+
+        let _ = Line::new(Point::new(164, 295), Point::new(167, 295))
+            .into_styled(PrimitiveStyle::with_stroke(QuadColor::Black, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+
+        assert_eq!(buffer[0], QuadColor::Black.get_byte_value());
+
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, epd2in36g::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    #[test]
+    fn graphics_rotation_270() {
+        let mut display = Display2in36g::default();
+        display.set_rotation(DisplayRotation::Rotate270);
+
+        let _ = Line::new(Point::new(295, 0), Point::new(295, 3))
+            .into_styled(PrimitiveStyle::with_stroke(QuadColor::Black, 1))
+            .draw(&mut display);
+
+        let buffer = display.buffer();
+
+        assert_eq!(buffer[0], QuadColor::Black.get_byte_value());
+
+        for &byte in buffer.iter().skip(1) {
+            assert_eq!(byte, epd2in36g::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+        }
+    }
+
+    // pixel (0, 0) is in the two most significant bits of the first byte
+    #[test]
+    fn graphics_origin_bits() {
+        use crate::color::QuadColor;
+        use crate::graphics::QuadDisplay;
+        use embedded_graphics::prelude::{Drawable, Pixel, Point};
+
+        let mut display = Display2in36g::default();
+        let _ = Pixel(Point::new(0, 0), QuadColor::Red).draw(&mut display);
+        assert_eq!(display.buffer()[0] >> 6, QuadColor::Red.get_bits());
+        let _ = Pixel(Point::new(1, 0), QuadColor::Black).draw(&mut display);
+        assert_eq!(display.buffer()[0] >> 4 & 0x03, QuadColor::Black.get_bits());
+    }
+}
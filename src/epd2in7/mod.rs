@@ -0,0 +1,562 @@
+//! A simple Driver for the Waveshare 2.7" E-Ink Display via SPI
+//!
+//! Besides black and white the panel can show 4 gray levels, see [Epd2in7::init_gray].
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/2.7inch_e-Paper_HAT)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in7.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in7.py)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::interface::{DisplayInterface, InterfaceConfig, LutLoadOrder};
+use crate::traits::{
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+
+// The Lookup Tables for the Display
+mod constants;
+use crate::epd2in7::constants::*;
+
+/// Width of the display
+pub const WIDTH: u32 = 176;
+/// Height of the display
+pub const HEIGHT: u32 = 264;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = true;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
+
+/// Buffer length of a full frame in the 4 level grayscale mode (2 bits per pixel)
+pub const GRAY_BUFFER_LEN: usize = WIDTH as usize / 4 * HEIGHT as usize;
+
+/// Undocumented power optimization registers and their values, sent by the reference code
+const POWER_OPTIMIZATION: [[u8; 2]; 5] = [
+    [0x60, 0xA5],
+    [0x89, 0xA5],
+    [0x90, 0x00],
+    [0x93, 0x2A],
+    [0x73, 0x41],
+];
+
+use crate::color::Color;
+
+pub(crate) mod command;
+use self::command::Command;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::{Display2in7, Display2in7Gray};
+
+/// Epd2in7 driver
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
+pub struct Epd2in7<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.reset(delay, 2);
+
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2b, 0x2b, 0x09])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x07, 0x07, 0x17])?;
+        for register in &POWER_OPTIMIZATION {
+            self.cmd_with_data(spi, Command::PowerOptimization, register)?;
+        }
+        self.cmd_with_data(spi, Command::PartialDisplayRefresh, &[0x00])?;
+
+        let lut_first = self
+            .interface
+            .lut_before_power_on(LutLoadOrder::AfterPowerOn);
+        if lut_first {
+            self.set_lut(spi, None)?;
+        }
+
+        self.interface
+            .cmd_then_delay(spi, delay, Command::PowerOn, 5)?;
+        self.wait_until_idle();
+        self.interface.power_on_settle(delay);
+
+        // LUT from the registers, black/white
+        self.cmd_with_data(spi, Command::PanelSetting, &[0xaf])?;
+        // 100Hz, 0x3A in the PLL table of the reference code
+        self.cmd_with_data(spi, Command::PllControl, &[0x3a])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x57])?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+
+        if !lut_first {
+            self.set_lut(spi, None)?;
+        }
+        self.interface.set_initialized();
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        Epd2in7 { interface, color }
+    }
+
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xf7])?;
+
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle();
+        // the old plane stays white, the LUTs only look at the new one
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, Color::White.panel_byte(BLACK_BIT), WIDTH * HEIGHT / 8)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
+        Ok(())
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.wait_until_idle();
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle();
+        let color = self.color.panel_byte(BLACK_BIT);
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, Color::White.panel_byte(BLACK_BIT), WIDTH * HEIGHT / 8)?;
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, color, WIDTH * HEIGHT / 8)?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_DC)?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW)?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW)?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB)?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB)?;
+        Ok(())
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in7<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.data(spi, data)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    /// Initialises the display for 4 level grayscale refreshes
+    ///
+    /// Use [Epd2in7::update_and_display_gray_frame] afterwards. Calling `wake_up`
+    /// switches back to the black/white mode.
+    pub fn init_gray(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.reset(delay, 2);
+
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2b, 0x2b])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x07, 0x07, 0x17])?;
+        for register in POWER_OPTIMIZATION[..4]
+            .iter()
+            .chain(&[[0xA0, 0xA5], [0xA1, 0x00]])
+            .chain(&POWER_OPTIMIZATION[4..])
+        {
+            self.cmd_with_data(spi, Command::PowerOptimization, register)?;
+        }
+        self.cmd_with_data(spi, Command::PartialDisplayRefresh, &[0x00])?;
+
+        self.interface
+            .cmd_then_delay(spi, delay, Command::PowerOn, 5)?;
+        self.wait_until_idle();
+        self.interface.power_on_settle(delay);
+
+        // LUT from the registers, black/white
+        self.cmd_with_data(spi, Command::PanelSetting, &[0xbf])?;
+        // value of the reference code for the gray mode
+        self.cmd_with_data(spi, Command::PllControl, &[0x90])?;
+        self.cmd_with_data(
+            spi,
+            Command::ResolutionSetting,
+            &[0x00, WIDTH as u8, (HEIGHT >> 8) as u8, HEIGHT as u8],
+        )?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x97])?;
+        self.interface.set_initialized();
+        Ok(())
+    }
+
+    /// Transmits a 4 level grayscale frame, writes the gray LUTs and refreshes the display
+    ///
+    /// `buffer` holds 2 bits per pixel with the first pixel in the highest bits:
+    /// `0b11` is white, `0b10` light gray, `0b01` dark gray and `0b00` black, as drawn by
+    /// [Display2in7Gray](crate::epd2in7::Display2in7Gray). Its length has to be
+    /// [GRAY_BUFFER_LEN], otherwise [DisplayError::InvalidData] is returned.
+    ///
+    /// The display needs to be initialised with [Epd2in7::init_gray] first.
+    pub fn update_and_display_gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        if buffer.len() != GRAY_BUFFER_LEN {
+            return Err(DisplayError::InvalidData);
+        }
+        self.wait_until_idle();
+
+        // the high bit of every pixel goes into the old plane, the low bit into the new one
+        self.command(spi, Command::DataStartTransmission1)?;
+        for pixels in buffer.chunks(2) {
+            self.send_data(spi, &[gray_plane_byte(pixels, 1)])?;
+        }
+        self.command(spi, Command::DataStartTransmission2)?;
+        for pixels in buffer.chunks(2) {
+            self.send_data(spi, &[gray_plane_byte(pixels, 0)])?;
+        }
+
+        self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_GRAY)?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW_GRAY)?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, &LUT_BW_GRAY)?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, &LUT_WB_GRAY)?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, &LUT_BB_GRAY)?;
+        self.cmd_with_data(spi, Command::LutGray, &LUT_WW_GRAY)?;
+
+        self.interface.check_refresh_interval()?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.wait_until_idle();
+        Ok(())
+    }
+}
+
+/// Collects the given bit of the eight 2 bit pixels in `pixels` into one plane byte
+fn gray_plane_byte(pixels: &[u8], bit: u8) -> u8 {
+    let mut byte = 0;
+    for &value in pixels {
+        for shift in (0..4).rev() {
+            byte = (byte << 1) | ((value >> (shift * 2 + bit)) & 0x01);
+        }
+    }
+    byte
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Event, MockDelay, Recorder};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 176);
+        assert_eq!(HEIGHT, 264);
+        assert_eq!(GRAY_BUFFER_LEN, 2 * 5808);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn gray_plane_split() {
+        // white, light gray, dark gray, black, then black, dark gray, light gray, white
+        let pixels = [0b11_10_01_00, 0b00_01_10_11];
+        assert_eq!(gray_plane_byte(&pixels, 1), 0b1100_0011);
+        assert_eq!(gray_plane_byte(&pixels, 0), 0b1010_0101);
+    }
+
+    #[test]
+    fn gray_frame_is_split_into_the_planes() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in7<_, _, _, _, _, MockDelay> =
+            Epd2in7::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        epd.init_gray(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x00).unwrap(), [0xbf]);
+        assert_eq!(mock.data_after(0x61).unwrap(), [0x00, 0xB0, 0x01, 0x08]);
+
+        // light gray everywhere: white in the old plane, black in the new one
+        mock.clear();
+        let buffer = [0b10_10_10_10; GRAY_BUFFER_LEN];
+        epd.update_and_display_gray_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+
+        let events = mock.events();
+        assert_eq!(events[0], Event::Command(0x10));
+        assert_eq!(events[1], Event::data(&[0xFF; GRAY_BUFFER_LEN / 2]));
+        assert_eq!(events[2], Event::Command(0x13));
+        assert_eq!(events[3], Event::data(&[0x00; GRAY_BUFFER_LEN / 2]));
+        assert_eq!(
+            mock.commands()[2..],
+            [0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x12]
+        );
+        assert_eq!(mock.data_after(0x20).unwrap(), LUT_VCOM_GRAY);
+        assert_eq!(mock.data_after(0x25).unwrap(), LUT_WW_GRAY);
+    }
+
+    #[test]
+    fn gray_frame_of_the_wrong_length_is_refused() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in7<_, _, _, _, _, MockDelay> =
+            Epd2in7::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        let buffer = [0xFF; GRAY_BUFFER_LEN + 1];
+        assert_eq!(
+            epd.update_and_display_gray_frame(&mut mock.spi(), &buffer, &mut mock.delay()),
+            Err(DisplayError::InvalidData)
+        );
+        assert_eq!(mock.events(), []);
+    }
+
+    #[test]
+    fn gray_frame_keeps_the_refresh_interval() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static NOW_MS: AtomicU32 = AtomicU32::new(0);
+        fn now_ms() -> u32 {
+            NOW_MS.load(Ordering::SeqCst)
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in7<_, _, _, _, _, MockDelay> =
+            Epd2in7::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+        let buffer = [0xFF; GRAY_BUFFER_LEN];
+
+        epd.update_and_display_gray_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        assert!(mock.commands().contains(&0x12));
+
+        mock.clear();
+        NOW_MS.store(999, Ordering::SeqCst);
+        assert_eq!(
+            epd.update_and_display_gray_frame(&mut mock.spi(), &buffer, &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+        assert!(!mock.commands().contains(&0x12));
+    }
+
+    #[test]
+    fn pll_control_of_both_modes() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in7<_, _, _, _, _, MockDelay> =
+            Epd2in7::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x30).unwrap(), [0x3A]);
+        mock.clear();
+        epd.init_gray(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x30).unwrap(), [0x90]);
+    }
+
+    #[test]
+    fn black_white_frame() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in7<_, _, _, _, _, MockDelay> =
+            Epd2in7::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.update_frame(
+            &mut mock.spi(),
+            &[Color::Black.get_byte_value(), 0x0F],
+            &mut mock.delay(),
+        )
+        .unwrap();
+        assert_eq!(mock.commands(), [0x10, 0x13]);
+        assert!(mock
+            .data_after(0x10)
+            .unwrap()
+            .iter()
+            .all(|&byte| byte == 0xFF));
+        assert_eq!(mock.data_after(0x13).unwrap(), [0x00, 0x0F]);
+    }
+}
@@ -7,9 +7,10 @@ use embedded_hal::{
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
+use crate::interface::{DisplayInterface, InterfaceConfig, LutLoadOrder};
 use crate::traits::{
-    InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+    WaveshareThreeColorDisplay,
 };
 
 // The Lookup Tables for the Display
@@ -23,6 +24,8 @@ pub const HEIGHT: u32 = 264;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = true;
 
 use crate::color::Color;
 
@@ -35,6 +38,7 @@ mod graphics;
 pub use self::graphics::Display2in7b;
 
 /// Epd2in7b driver
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd2in7b<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
@@ -52,14 +56,22 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         // reset the device
         self.interface.reset(delay, 2);
 
+        let lut_first = self
+            .interface
+            .lut_before_power_on(LutLoadOrder::AfterPowerOn);
+        if lut_first {
+            self.set_lut(spi, None)?;
+        }
+
         // power on
-        self.command(spi, Command::PowerOn)?;
-        delay.delay_ms(5);
+        self.interface
+            .cmd_then_delay(spi, delay, Command::PowerOn, 5)?;
         self.wait_until_idle();
+        self.interface.power_on_settle(delay);
 
         // set panel settings, 0xbf is bw, 0xaf is multi-color
         self.interface
@@ -98,12 +110,15 @@ where
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x87])?;
 
-        self.set_lut(spi, None)?;
+        if !lut_first {
+            self.set_lut(spi, None)?;
+        }
 
         self.interface
             .cmd_with_data(spi, Command::PartialDisplayRefresh, &[0x00])?;
 
         self.wait_until_idle();
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -126,22 +141,80 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd2in7b { interface, color };
+        Epd2in7b { interface, color }
+    }
 
-        epd.init(spi, delay)?;
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
 
-        Ok(epd)
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xf7])?;
@@ -150,6 +223,7 @@ where
         self.wait_until_idle();
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
         Ok(())
     }
 
@@ -158,9 +232,12 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
-        self.send_buffer_helper(spi, buffer)?;
+        for rows in self.interface.frame_rows(buffer, HEIGHT) {
+            self.send_buffer_helper(spi, rows)?;
+        }
 
         // Clear chromatic layer since we won't be using it here
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
@@ -179,7 +256,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.interface
             .cmd(spi, Command::PartialDataStartTransmission1)?;
 
@@ -198,7 +276,13 @@ where
         self.interface.cmd(spi, Command::DataStop)
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.command(spi, Command::DisplayRefresh)?;
         self.wait_until_idle();
         Ok(())
@@ -209,25 +293,30 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
+        self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
 
-        let color_value = self.color.get_byte_value();
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface
-            .data_x_times(spi, color_value, WIDTH * HEIGHT / 8)?;
+            .data_x_times(spi, self.color.panel_byte(BLACK_BIT), WIDTH * HEIGHT / 8)?;
 
         self.interface.cmd(spi, Command::DataStop)?;
 
+        // Clear the chromatic layer like update_frame does
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
         self.interface
-            .data_x_times(spi, color_value, WIDTH * HEIGHT / 8)?;
+            .data_x_times(spi, !self.color.get_byte_value(), WIDTH * HEIGHT / 8)?;
         self.interface.cmd(spi, Command::DataStop)?;
         Ok(())
     }
@@ -252,7 +341,7 @@ where
         &mut self,
         spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         self.cmd_with_data(spi, Command::LutForVcom, &LUT_VCOM_DC)?;
         self.cmd_with_data(spi, Command::LutWhiteToWhite, &LUT_WW)?;
@@ -282,7 +371,8 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.update_achromatic_frame(spi, black)?;
         self.update_chromatic_frame(spi, chromatic)
     }
@@ -294,10 +384,13 @@ where
         &mut self,
         spi: &mut SPI,
         achromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
 
-        self.send_buffer_helper(spi, achromatic)?;
+        for rows in self.interface.frame_rows(achromatic, HEIGHT) {
+            self.send_buffer_helper(spi, rows)?;
+        }
 
         self.interface.cmd(spi, Command::DataStop)
     }
@@ -309,10 +402,13 @@ where
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.interface.cmd(spi, Command::DataStartTransmission2)?;
 
-        self.send_buffer_helper(spi, chromatic)?;
+        for rows in self.interface.frame_rows(chromatic, HEIGHT) {
+            self.send_buffer_helper(spi, rows)?;
+        }
 
         self.interface.cmd(spi, Command::DataStop)?;
         self.wait_until_idle();
@@ -330,17 +426,21 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
-    fn send_buffer_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
-        // Based on the waveshare implementation, all data for color values is flipped. This helper
-        // method makes that transmission easier
+    fn send_buffer_helper(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        // Based on the waveshare implementation, all data for color values is flipped (see
+        // BLACK_BIT). This helper method makes that transmission easier
         for b in buffer.iter() {
             self.send_data(spi, &[!b])?;
         }
@@ -352,12 +452,12 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
     fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
     /// Refresh display for partial frame
@@ -368,7 +468,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.command(spi, Command::PartialDisplayRefresh)?;
         self.send_data(spi, &[(x >> 8) as u8])?;
         self.send_data(spi, &[(x & 0xf8) as u8])?;
@@ -391,7 +491,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface
             .cmd(spi, Command::PartialDataStartTransmission1)?;
         self.send_data(spi, &[(x >> 8) as u8])?;
@@ -421,7 +521,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface
             .cmd(spi, Command::PartialDataStartTransmission2)?;
         self.send_data(spi, &[(x >> 8) as u8])?;
@@ -446,6 +546,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{MockDelay, Recorder};
 
     #[test]
     fn epd_size() {
@@ -453,4 +554,47 @@ mod tests {
         assert_eq!(HEIGHT, 264);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn black_bit_polarity() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in7b<_, _, _, _, _, MockDelay> =
+            Epd2in7b::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.update_frame(
+            &mut mock.spi(),
+            &[Color::Black.get_byte_value(), 0x0F],
+            &mut mock.delay(),
+        )
+        .unwrap();
+        assert_eq!(mock.data_after(0x10).unwrap(), [0xFF, 0xF0]);
+
+        // the clear uses the same polarity as the frames
+        for (color, byte) in [(Color::White, 0x00), (Color::Black, 0xFF)] {
+            mock.clear();
+            epd.set_background_color(color);
+            epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+            let data = mock.data_after(0x10).unwrap();
+            assert!(data.iter().all(|&b| b == byte));
+        }
+    }
+
+    #[test]
+    fn plane_refresh_is_unsupported() {
+        use crate::traits::ColorPlane;
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in7b<_, _, _, _, _, MockDelay> =
+            Epd2in7b::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        for plane in [ColorPlane::Achromatic, ColorPlane::Chromatic] {
+            assert_eq!(
+                epd.display_frame_plane(&mut mock.spi(), &mut mock.delay(), plane),
+                Err(DisplayError::Unsupported)
+            );
+        }
+        assert_eq!(mock.events(), []);
+    }
 }
@@ -5,7 +5,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -48,6 +48,8 @@ pub const HEIGHT: u32 = 296;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
 
 use embedded_hal::{
     blocking::{delay::*, spi::Write},
@@ -63,7 +65,7 @@ use crate::color::Color;
 
 use crate::traits::*;
 
-use crate::interface::DisplayInterface;
+use crate::interface::{DisplayInterface, InterfaceConfig};
 
 #[cfg(feature = "graphics")]
 mod graphics;
@@ -72,6 +74,7 @@ pub use crate::epd2in9::graphics::Display2in9;
 
 /// Epd2in9 driver
 ///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd2in9<SPI, CS, BUSY, DC, RST, DELAY> {
     /// SPI
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
@@ -79,6 +82,8 @@ pub struct Epd2in9<SPI, CS, BUSY, DC, RST, DELAY> {
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Gate lines and scan direction
+    driver_output: DriverOutputControl,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
@@ -90,7 +95,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.reset(delay, 10);
 
         self.wait_until_idle();
@@ -99,9 +104,10 @@ where
         // A[7:0]
         // 0.. A[8]
         // 0.. B[2:0]
-        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
+        // Default Values: A = Height of Screen - 1 (0x127), B = 0x00 (GD, SM and TB=0?)
+        let driver_output = self.driver_output.to_bytes();
         self.interface
-            .cmd_with_data(spi, Command::DriverOutputControl, &[0x27, 0x01, 0x00])?;
+            .cmd_with_data(spi, Command::DriverOutputControl, &driver_output)?;
 
         // 3 Databytes: (and default values from datasheet and arduino)
         // 1 .. A[6:0]  = 0xCF | 0xD7
@@ -128,7 +134,9 @@ where
         self.interface
             .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])?;
 
-        self.set_lut(spi, None)
+        self.set_lut(spi, None)?;
+        self.interface.set_initialized();
+        Ok(())
     }
 }
 
@@ -158,30 +166,94 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
 
-        let mut epd = Epd2in9 {
+        Epd2in9 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+            driver_output: DriverOutputControl::new(HEIGHT as u16),
+        }
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         //TODO: is 0x00 needed here? (see also epd1in54)
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x00])?;
+        self.interface.set_sleeping();
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn can_partial_refresh(&self) -> bool {
+        self.interface.has_base_image()
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::SwReset, 10, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         self.init(spi, delay)?;
         Ok(())
@@ -192,12 +264,13 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.use_full_frame(spi)?;
 
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)?;
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
         Ok(())
     }
 
@@ -210,7 +283,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.set_ram_area(spi, x, y, x + width, y + height)?;
         self.set_ram_counter(spi, x, y)?;
@@ -220,7 +294,13 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.wait_until_idle();
         // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
         //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
@@ -231,6 +311,7 @@ where
         // MASTER Activation should not be interupted to avoid currption of panel images
         // therefore a terminate command is send
         self.interface.cmd(spi, Command::Nop)?;
+        self.interface.refreshed(self.refresh);
         Ok(())
     }
 
@@ -239,18 +320,23 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.use_full_frame(spi)?;
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self.background_color.panel_byte(BLACK_BIT);
 
         self.interface.cmd(spi, Command::WriteRam)?;
         self.interface
@@ -270,7 +356,7 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
@@ -280,6 +366,16 @@ where
         }
     }
 
+    fn refresh_lut(&self) -> RefreshLut {
+        self.refresh
+    }
+
+    fn max_partial_refresh_hz(&self) -> u32 {
+        // the specification of the 2.9inch e-Paper Module on the Waveshare wiki lists 0.3s
+        // for a partial refresh, rounded down
+        3
+    }
+
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
@@ -294,11 +390,25 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
+    /// Sets the number of gate lines and the scan direction, for panels with another height
+    ///
+    /// The setting is kept for the next [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_driver_output_control(
+        &mut self,
+        spi: &mut SPI,
+        control: DriverOutputControl,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.driver_output = control;
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::DriverOutputControl, &control.to_bytes())
+    }
+
     fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -313,7 +423,7 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         assert!(start_x < end_x);
         assert!(start_y < end_y);
 
@@ -338,7 +448,12 @@ where
         )
     }
 
-    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), SPI::Error> {
+    fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -355,7 +470,11 @@ where
     }
 
     /// Set your own LUT, this function is also used internally for set_lut
-    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
+    fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         assert!(buffer.len() == 30);
         self.interface
@@ -364,14 +483,314 @@ where
     }
 }
 
+impl<SPI, CS, BUSY, DC, RST, DELAY> SplitRefresh<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn start_refresh(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC4])?;
+        self.interface.cmd(spi, Command::MasterActivation)?;
+        self.interface.cmd(spi, Command::Nop)?;
+        self.interface.refreshed(self.refresh);
+        Ok(())
+    }
+
+    fn finish_refresh(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_for_idle(delay);
+        Ok(())
+    }
+
+    fn wait_for_idle(&mut self, delay: &mut DELAY) {
+        self.interface
+            .wait_until_idle_with_delay(IS_BUSY_LOW, delay);
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> DirectRam<SPI> for Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    const RAM_WIDTH: u32 = WIDTH;
+    const RAM_HEIGHT: u32 = HEIGHT;
+
+    fn write_ram(
+        &mut self,
+        spi: &mut SPI,
+        x_byte: u32,
+        y: u32,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        // the window has to span the whole ram, the counter only wraps inside of it
+        self.use_full_frame(spi)?;
+        self.set_ram_counter(spi, x_byte * 8, y)?;
+
+        self.interface.cmd_with_data(spi, Command::WriteRam, data)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn new_with_config_runs_the_init_of_new() {
+        use crate::mock::{MockDelay, MockPin, MockSpi, Recorder};
+
+        let events = |with_config: bool| {
+            let mock = Recorder::new(IS_BUSY_LOW);
+            mock.log_busy_reads();
+            let (mut spi, mut delay) = (mock.spi(), mock.delay());
+            let (cs, busy, dc, rst) = (mock.cs(), mock.busy(), mock.dc(), mock.rst());
+            let _epd: Epd2in9<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> =
+                if with_config {
+                    let config = InterfaceConfig::default();
+                    Epd2in9::new_with_config(&mut spi, cs, busy, dc, rst, &mut delay, config)
+                } else {
+                    Epd2in9::new(&mut spi, cs, busy, dc, rst, &mut delay)
+                }
+                .unwrap();
+            mock.events()
+        };
+        assert_eq!(events(true), events(false));
+    }
+
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 128);
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn max_partial_refresh_hz() {
+        use crate::mock::{MockDelay, MockPin, MockSpi, Recorder};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let epd: Epd2in9<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(epd.max_partial_refresh_hz(), 3);
+    }
+
+    #[test]
+    fn filtered_busy_ignores_glitches() {
+        use crate::mock::{MockDelay, MockPin, MockSpi, Recorder};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let epd: Epd2in9<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        // a single idle glitch while the display is busy
+        mock.idle_for(1);
+        mock.busy_for(1);
+        mock.idle_for(1);
+        assert!(epd.is_busy_filtered(3));
+        // the plain read takes the glitch for idle
+        assert!(!epd.is_busy());
+
+        // idle for good
+        assert!(!epd.is_busy_filtered(3));
+        assert!(!epd.is_busy_filtered(0));
+    }
+
+    #[test]
+    fn init_retries_after_a_failure() {
+        use crate::mock::{Event, MockDelay, Recorder, SpiFault};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in9<_, _, _, _, _, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        let resets = |mock: &Recorder| {
+            mock.events()
+                .iter()
+                .filter(|event| **event == Event::Reset(false))
+                .count()
+        };
+
+        // the first command of the first attempt fails
+        mock.fail_writes(1);
+        epd.init_with_retries(&mut mock.spi(), &mut mock.delay(), 2)
+            .unwrap();
+        assert_eq!(resets(&mock), 2);
+        epd.update_frame(&mut mock.spi(), &[0xFF; 10], &mut mock.delay())
+            .unwrap();
+
+        // gives up after the retries
+        mock.clear();
+        mock.fail_writes(3);
+        assert_eq!(
+            epd.init_with_retries(&mut mock.spi(), &mut mock.delay(), 2),
+            Err(DisplayError::Spi(SpiFault))
+        );
+        assert_eq!(resets(&mock), 3);
+    }
+
+    #[test]
+    fn min_refresh_interval_blocks_early_refresh() {
+        use crate::mock::{MockDelay, Recorder};
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static NOW_MS: AtomicU32 = AtomicU32::new(u32::MAX - 500);
+        fn now_ms() -> u32 {
+            NOW_MS.load(Ordering::SeqCst)
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in9<_, _, _, _, _, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+
+        // too soon, nothing is sent
+        mock.clear();
+        NOW_MS.store(u32::MAX, Ordering::SeqCst);
+        assert_eq!(
+            epd.display_frame(&mut mock.spi(), &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+        assert!(mock.events().is_empty());
+
+        // allowed again after the interval, even though the clock wrapped around
+        NOW_MS.store(500, Ordering::SeqCst);
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert!(!mock.commands().is_empty());
+
+        // disabled
+        NOW_MS.store(501, Ordering::SeqCst);
+        epd.set_min_refresh_interval_ms(0, now_ms);
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+    }
+
+    #[test]
+    fn clear_and_display_refreshes() {
+        use crate::mock::{MockDelay, Recorder};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in9<_, _, _, _, _, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+        let clear = mock.commands();
+        assert_eq!(clear.last(), Some(&0x24));
+
+        mock.clear();
+        epd.clear_and_display(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        let commands = mock.commands();
+        assert_eq!(commands[..clear.len()], clear[..]);
+        assert_eq!(commands[clear.len()..], [0x22, 0x20, 0xFF]);
+    }
+
+    #[test]
+    fn vertical_flip_sends_rows_bottom_first() {
+        use crate::mock::{MockDelay, Recorder};
+
+        const ROW_LEN: usize = WIDTH as usize / 8;
+        let mut buffer = [0u8; ROW_LEN * HEIGHT as usize];
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = (i / ROW_LEN) as u8;
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in9<_, _, _, _, _, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        let normal = mock.data_after(0x24).unwrap();
+        assert_eq!(normal, buffer);
+
+        mock.clear();
+        epd.set_interface_config(InterfaceConfig::new().vertical_flip(true));
+        epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        let flipped = mock.data_after(0x24).unwrap();
+        assert_eq!(flipped.len(), normal.len());
+        for (flipped_row, row) in flipped.chunks(ROW_LEN).zip(normal.chunks(ROW_LEN).rev()) {
+            assert_eq!(flipped_row, row);
+        }
+        assert_eq!(flipped[0], (HEIGHT - 1) as u8);
+    }
+
+    #[test]
+    fn custom_driver_output_control_is_kept() {
+        use crate::mock::{MockDelay, Recorder};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in9<_, _, _, _, _, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x01).unwrap(), [0x27, 0x01, 0x00]);
+
+        // a 250 rows panel, scanned from the last gate
+        let control = DriverOutputControl::new(250).scan(0x04);
+        mock.clear();
+        epd.set_driver_output_control(&mut mock.spi(), control)
+            .unwrap();
+        assert_eq!(mock.data_after(0x01).unwrap(), [0xF9, 0x00, 0x04]);
+
+        mock.clear();
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x01).unwrap(), [0xF9, 0x00, 0x04]);
+    }
+
+    #[test]
+    fn state_follows_the_lifecycle() {
+        use crate::mock::{MockDelay, Recorder};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in9<_, _, _, _, _, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(epd.state(), DisplayState::Uninitialized);
+
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(epd.state(), DisplayState::Ready);
+
+        // the refresh runs while the busy line reports it
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        mock.busy_for(2);
+        assert_eq!(epd.state(), DisplayState::Refreshing);
+        epd.wait_until_idle();
+        assert_eq!(epd.state(), DisplayState::Ready);
+
+        // a refresh which already ended
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(epd.state(), DisplayState::Ready);
+
+        epd.sleep(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(epd.state(), DisplayState::Sleeping);
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(epd.state(), DisplayState::Ready);
+
+        // a panel which doesn't answer the probe needs the init sequence again
+        assert!(!epd.probe(&mut mock.spi(), &mut mock.delay()).unwrap());
+        assert_eq!(epd.state(), DisplayState::Uninitialized);
+    }
 }
@@ -6,7 +6,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -61,6 +61,8 @@ pub const HEIGHT: u32 = 296;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = false;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
 
 const LUT_PARTIAL_2IN9: [u8; 153] = [
     0x0, 0x40, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x80, 0x80, 0x0, 0x0, 0x0, 0x0,
@@ -85,7 +87,7 @@ use crate::color::Color;
 
 use crate::traits::*;
 
-use crate::interface::DisplayInterface;
+use crate::interface::{DisplayInterface, InterfaceConfig};
 use crate::traits::QuickRefresh;
 
 #[cfg(feature = "graphics")]
@@ -95,6 +97,7 @@ pub use crate::epd2in9_v2::graphics::Display2in9;
 
 /// Epd2in9 driver
 ///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd2in9<SPI, CS, BUSY, DC, RST, DELAY> {
     /// SPI
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
@@ -102,6 +105,8 @@ pub struct Epd2in9<SPI, CS, BUSY, DC, RST, DELAY> {
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Gate lines and scan direction
+    driver_output: DriverOutputControl,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> Epd2in9<SPI, CS, BUSY, DC, RST, DELAY>
@@ -113,7 +118,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.reset(delay, 2);
 
         self.wait_until_idle();
@@ -124,9 +129,10 @@ where
         // A[7:0]
         // 0.. A[8]
         // 0.. B[2:0]
-        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
+        // Default Values: A = Height of Screen - 1 (0x127), B = 0x00 (GD, SM and TB=0?)
+        let driver_output = self.driver_output.to_bytes();
         self.interface
-            .cmd_with_data(spi, Command::DriverOutputControl, &[0x27, 0x01, 0x00])?;
+            .cmd_with_data(spi, Command::DriverOutputControl, &driver_output)?;
 
         // One Databyte with default value 0x03
         //  -> address: x increment, y increment, address counter is updated in x direction
@@ -141,6 +147,7 @@ where
         self.set_ram_counter(spi, 0, 0)?;
 
         self.wait_until_idle();
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -171,29 +178,93 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
 
-        let mut epd = Epd2in9 {
+        Epd2in9 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
-        };
-
-        epd.init(spi, delay)?;
-
-        Ok(epd)
+            driver_output: DriverOutputControl::new(HEIGHT as u16),
+        }
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])?;
+        self.interface.set_sleeping();
         Ok(())
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn can_partial_refresh(&self) -> bool {
+        self.interface.has_base_image()
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::SwReset, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)?;
         Ok(())
     }
@@ -203,9 +274,11 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
-        self.interface.cmd_with_data(spi, Command::WriteRam, buffer)
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.frame(spi, buffer, HEIGHT)
     }
 
     fn update_partial_frame(
@@ -216,7 +289,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         //TODO This is copied from epd2in9 but it seems not working. Partial refresh supported by version 2?
         self.wait_until_idle();
         self.set_ram_area(spi, x, y, x + width, y + height)?;
@@ -227,13 +301,20 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.wait_until_idle();
         // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
         self.interface.cmd(spi, Command::MasterActivation)?;
         self.wait_until_idle();
+        self.interface.refreshed(RefreshLut::Full);
         Ok(())
     }
 
@@ -242,17 +323,22 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
 
         // clear the ram with the background color
-        let color = self.background_color.get_byte_value();
+        let color = self.background_color.panel_byte(BLACK_BIT);
 
         self.interface.cmd(spi, Command::WriteRam)?;
         self.interface.data_x_times(spi, color, WIDTH / 8 * HEIGHT)
@@ -270,13 +356,23 @@ where
         &mut self,
         _spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
         Ok(())
     }
 
+    fn refresh_lut(&self) -> RefreshLut {
+        self.refresh
+    }
+
+    fn max_partial_refresh_hz(&self) -> u32 {
+        // Waveshare wiki, 2.9inch e-Paper Module (V2) specification: 3s for a full and 0.3s
+        // for a partial refresh, rounded down
+        3
+    }
+
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
@@ -291,11 +387,44 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
+    /// Sets the number of gate lines and the scan direction, for panels with another height
+    ///
+    /// The setting is kept for the next [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_driver_output_control(
+        &mut self,
+        spi: &mut SPI,
+        control: DriverOutputControl,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.driver_output = control;
+        self.wait_until_idle();
+        self.interface
+            .cmd_with_data(spi, Command::DriverOutputControl, &control.to_bytes())
+    }
+
+    /// Writes `buffer` into both the new and the base (old) RAM
+    ///
+    /// Use it after a full refresh, so the first quick refresh compares with the image
+    /// which is on the panel and doesn't leave ghosts of the previous frame.
+    pub fn sync_base_image(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle();
+        self.use_full_frame(spi)?;
+
+        self.interface.cmd(spi, Command::WriteRam)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
+        self.interface.cmd(spi, Command::WriteRam2)?;
+        self.interface.frame(spi, buffer, HEIGHT)
+    }
+
     fn wait_until_idle(&mut self) {
         self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn use_full_frame(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
         // choose full frame/ram
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1)?;
 
@@ -310,7 +439,7 @@ where
         start_y: u32,
         end_x: u32,
         end_y: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         assert!(start_x < end_x);
         assert!(start_y < end_y);
 
@@ -335,7 +464,12 @@ where
         )
     }
 
-    fn set_ram_counter(&mut self, spi: &mut SPI, x: u32, y: u32) -> Result<(), SPI::Error> {
+    fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -352,7 +486,11 @@ where
     }
 
     /// Set your own LUT, this function is also used internally for set_lut
-    fn set_lut_helper(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), SPI::Error> {
+    fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)?;
@@ -377,7 +515,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.interface
             .cmd_with_data(spi, Command::WriteRam, buffer)?;
@@ -391,7 +530,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.interface.reset(delay, 2);
 
@@ -403,6 +543,7 @@ where
         )?;
         self.interface
             .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])?;
+        // only enables the clock and analog for the partial LUT, nothing is displayed
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC0])?;
         self.interface.cmd(spi, Command::MasterActivation)?;
@@ -417,7 +558,13 @@ where
     }
 
     /// For a quick refresh of the new updated frame. To be used immediately after `update_new_frame`
-    fn display_new_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.wait_until_idle();
         self.interface
             .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x0F])?;
@@ -432,7 +579,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_new_frame(spi, buffer, delay)?;
         self.display_new_frame(spi, delay)?;
         Ok(())
@@ -448,9 +595,10 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         //TODO supported by display?
-        unimplemented!()
+        Err(DisplayError::Unsupported)
     }
 
     /// Partial quick refresh not supported yet
@@ -463,9 +611,10 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         //TODO supported by display?
-        unimplemented!()
+        Err(DisplayError::Unsupported)
     }
 
     /// Partial quick refresh not supported yet
@@ -477,9 +626,10 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         //TODO supported by display?
-        unimplemented!()
+        Err(DisplayError::Unsupported)
     }
 }
 
@@ -493,4 +643,87 @@ mod tests {
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn sync_base_image_writes_both_rams() {
+        use crate::mock::{MockDelay, Recorder};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in9<_, _, _, _, _, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        let buffer = [0x5A; WIDTH as usize / 8 * HEIGHT as usize];
+        epd.sync_base_image(&mut mock.spi(), &buffer).unwrap();
+        assert_eq!(mock.data_after(0x24), Some(buffer.to_vec()));
+        assert_eq!(mock.data_after(0x26), Some(buffer.to_vec()));
+    }
+
+    #[test]
+    fn max_partial_refresh_hz() {
+        use crate::mock::{MockDelay, MockPin, MockSpi, Recorder};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let epd: Epd2in9<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(epd.max_partial_refresh_hz(), 3);
+    }
+
+    #[test]
+    fn display_new_frame_keeps_the_refresh_interval() {
+        use crate::mock::{MockDelay, Recorder};
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static NOW_MS: AtomicU32 = AtomicU32::new(0);
+        fn now_ms() -> u32 {
+            NOW_MS.load(Ordering::SeqCst)
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in9<_, _, _, _, _, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+
+        epd.display_new_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert!(mock.commands().contains(&0x20));
+
+        mock.clear();
+        NOW_MS.store(999, Ordering::SeqCst);
+        assert_eq!(
+            epd.display_new_frame(&mut mock.spi(), &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+        assert!(!mock.commands().contains(&0x20));
+    }
+
+    #[test]
+    fn update_and_display_new_frame_keeps_the_refresh_interval() {
+        use crate::mock::{MockDelay, Recorder};
+
+        fn now_ms() -> u32 {
+            0
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd2in9<_, _, _, _, _, MockDelay> =
+            Epd2in9::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+        let buffer = [0xFF; WIDTH as usize / 8 * HEIGHT as usize];
+
+        // the upload enables the clock and analog, that doesn't count as a refresh
+        epd.update_and_display_new_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        let activations = |mock: &Recorder| mock.commands().iter().filter(|&&c| c == 0x20).count();
+        assert_eq!(activations(&mock), 2);
+
+        mock.clear();
+        assert_eq!(
+            epd.update_and_display_new_frame(&mut mock.spi(), &buffer, &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+        assert_eq!(activations(&mock), 1);
+    }
 }
@@ -0,0 +1,120 @@
+//! A simple Driver for the Waveshare 2.9" D flexible E-Ink Display via SPI
+//!
+//! The 2.9" D is a 128x296 black/white panel on a flexible substrate, which makes it a
+//! common choice for wearables. It supports partial refreshes with the
+//! [quick LUT](crate::traits::RefreshLut::Quick).
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/2.9inch_e-Paper_HAT_(D))
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/702def0/RaspberryPi%26JetsonNano/c/lib/e-Paper/EPD_2in9d.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/702def0/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd2in9d.py)
+//!
+//! # Examples
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+//!use embedded_graphics::{
+//!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{epd2in9d::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd2in9d::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display2in9d::default();
+//!
+//!// Use embedded graphics for drawing a line
+//!let _ = Line::new(Point::new(0, 0), Point::new(127, 295))
+//!    .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_frame(&mut spi, &display.buffer(), &mut delay)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use crate::type_d::EpdTypeD;
+
+pub use crate::type_d::DEFAULT_BACKGROUND_COLOR;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display2in9d;
+
+/// Width of the display
+pub const WIDTH: u32 = 128;
+/// Height of the display
+pub const HEIGHT: u32 = 296;
+
+/// Epd2in9d driver
+///
+pub type Epd2in9d<SPI, CS, BUSY, DC, RST, DELAY> =
+    EpdTypeD<SPI, CS, BUSY, DC, RST, DELAY, WIDTH, HEIGHT>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::mock::Recorder;
+    use crate::traits::{RefreshLut, WaveshareDisplay};
+    use crate::type_d::{constants::*, IS_BUSY_LOW};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 128);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_commands() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd2in9d::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            mock.commands(),
+            [0x01, 0x06, 0x04, 0x00, 0x30, 0x61, 0x82, 0x50, 0x20, 0x21, 0x22, 0x23, 0x24]
+        );
+        assert_eq!(
+            mock.data_after(0x01).unwrap(),
+            [0x03, 0x00, 0x2B, 0x2B, 0x03]
+        );
+        assert_eq!(mock.data_after(0x00).unwrap(), [0xBF, 0x0D]);
+        assert_eq!(mock.data_after(0x30).unwrap(), [0x3A]);
+        assert_eq!(mock.data_after(0x61).unwrap(), [128, 0x01, 0x28]);
+        assert_eq!(mock.data_after(0x82).unwrap(), [0x28]);
+        assert_eq!(mock.data_after(0x20).unwrap(), LUT_VCOM_FULL);
+
+        mock.clear();
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Quick))
+            .unwrap();
+        assert_eq!(mock.data_after(0x82).unwrap(), [0x00]);
+        assert_eq!(mock.data_after(0x22).unwrap(), LUT_BW_QUICK);
+    }
+}
@@ -0,0 +1,98 @@
+//! SPI Commands for the Waveshare 3.52" E-Ink Display
+
+use crate::traits;
+
+/// Epd3in52 commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift
+    /// direction, booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0".
+    PowerOff = 0x02,
+
+    /// Turning On the Power
+    ///
+    /// After the Power ON command, the driver will power on following the Power ON
+    /// sequence. Once complete, the BUSY signal will become "1".
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The deep sleep mode would return to stand-by by hardware reset.
+    ///
+    /// The only one parameter is a check code, the command would be excuted if check code = 0xA5.
+    DeepSleep = 0x07,
+
+    /// This command starts transmitting data and write them into SRAM.
+    ///
+    /// OLD_DATA
+    DataStartTransmission1 = 0x10,
+
+    /// After this command is issued, driver will refresh display (data/VCOM) according to
+    /// SRAM data and LUT.
+    DisplayRefresh = 0x12,
+
+    /// This command starts transmitting data and write them into SRAM.
+    ///
+    /// NEW_DATA
+    DataStartTransmission2 = 0x13,
+
+    /// Runs power on, display refresh and power off in one go.
+    ///
+    /// The only parameter is a check code, the sequence is only run if it is 0xA5.
+    AutoSequence = 0x17,
+
+    /// The command controls the PLL clock frequency.
+    PllControl = 0x30,
+
+    /// This command indicates the interval of Vcom and data output. When setting the
+    /// vertical back porch, the total blanking will be kept (20 Hsync).
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// This command defines the non-overlap period of Gate and Source.
+    TconSetting = 0x60,
+
+    /// This command defines the resolution setting.
+    TconResolution = 0x61,
+
+    /// This command sets VCOM_DC value.
+    VcmDcSetting = 0x82,
+
+    /// Sets the power saving intervals of the source and gate drivers.
+    PowerSaving = 0xE3,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::AutoSequence.address(), 0x17);
+        assert_eq!(Command::TconResolution.address(), 0x61);
+        assert_eq!(Command::PowerSaving.address(), 0xE3);
+    }
+}
@@ -0,0 +1,447 @@
+//! A simple Driver for the Waveshare 4.01 inch (F) 7-Color E-Ink Display via SPI
+//!
+//! The pixels are packed like on the [5.65 inch panel](crate::epd5in65f), two [OctColor]s per byte.
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/4.01inch_e-Paper_HAT_(F))
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_4in01f.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd4in01f.py)
+//!
+//! # Examples
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+//!use epd_waveshare::{epd4in01f::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd4in01f::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display4in01f::default();
+//!
+//!// Use embedded graphics for drawing a line
+//!let _ = Line::new(Point::new(0, 0), Point::new(639, 399))
+//!    .into_styled(PrimitiveStyle::with_stroke(OctColor::Orange, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_and_display_frame(&mut spi, display.buffer(), &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::color::OctColor;
+use crate::interface::{DisplayInterface, InterfaceConfig};
+use crate::traits::{
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display4in01f;
+
+/// Width of the display
+pub const WIDTH: u32 = 640;
+/// Height of the display
+pub const HEIGHT: u32 = 400;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
+const IS_BUSY_LOW: bool = true;
+// No `BLACK_BIT` here: the frame memory takes a 4 bit color index per pixel,
+// with 0x0 for black, see [OctColor]
+
+/// Epd4in01f driver
+///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
+pub struct Epd4in01f<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: OctColor,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in01f<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        // Reset the device
+        self.interface.reset(delay, 2);
+        self.wait_busy_high();
+
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x2F, 0x00])?;
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x37, 0x00, 0x05, 0x05])?;
+        self.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x00])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0xC7, 0xC7, 0x1D])?;
+        self.cmd_with_data(spi, Command::TemperatureCalibration, &[0x00])?;
+        self.update_vcom(spi)?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
+        self.send_resolution(spi)?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0xAA])?;
+
+        self.interface.set_initialized();
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in01f<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = OctColor;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        Epd4in01f { interface, color }
+    }
+
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        delay.delay_ms(100);
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_busy_high();
+        self.update_vcom(spi)?;
+        self.send_resolution(spi)?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
+        Ok(())
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.command(spi, Command::PowerOn)?;
+        self.wait_busy_high();
+        self.command(spi, Command::DisplayRefresh)?;
+        self.wait_busy_high();
+        self.command(spi, Command::PowerOff)?;
+        self.wait_busy_low();
+        delay.delay_ms(200);
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        let bg = OctColor::colors_byte(self.color, self.color);
+        self.wait_busy_high();
+        self.update_vcom(spi)?;
+        self.send_resolution(spi)?;
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface.data_x_times(spi, bg, WIDTH * HEIGHT / 2)?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: OctColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &OctColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        Err(DisplayError::Unsupported)
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd4in01f<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_busy_high(&mut self) {
+        self.interface.wait_until_idle(true);
+    }
+
+    fn wait_busy_low(&mut self) {
+        self.interface.wait_until_idle(false);
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        let w = self.width();
+        let h = self.height();
+
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[(w >> 8) as u8, w as u8, (h >> 8) as u8, h as u8],
+        )
+    }
+
+    fn update_vcom(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        let bg_color = (self.color.get_nibble() & 0b111) << 5;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17 | bg_color])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Event, MockDelay, Recorder};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 640);
+        assert_eq!(HEIGHT, 400);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
+    }
+
+    #[test]
+    fn init_commands() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let _epd = Epd4in01f::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            mock.commands(),
+            [0x00, 0x01, 0x03, 0x06, 0x41, 0x50, 0x60, 0x61, 0xE3]
+        );
+        assert_eq!(mock.data_after(0x50).unwrap(), [0x37]);
+        assert_eq!(mock.data_after(0x61).unwrap(), [0x02, 0x80, 0x01, 0x90]);
+    }
+
+    #[test]
+    fn display_frame_powers_on_refreshes_and_powers_off() {
+        // the busy line goes high once powered on and low again after the power off
+        let mock = Recorder::new(!IS_BUSY_LOW);
+        mock.busy_for(2);
+        let mut epd: Epd4in01f<_, _, _, _, _, MockDelay> =
+            Epd4in01f::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+
+        assert_eq!(
+            mock.events(),
+            [
+                Event::Command(0x04),
+                Event::Command(0x12),
+                Event::Command(0x02),
+                Event::Delay(200),
+            ]
+        );
+    }
+
+    #[test]
+    fn clear_frame_only_fills_ram() {
+        // every wait for the powered on controller reads the busy line high once
+        let mock = Recorder::new(!IS_BUSY_LOW);
+        let mut epd: Epd4in01f<_, _, _, _, _, MockDelay> =
+            Epd4in01f::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        mock.busy_for(1);
+        epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.commands(), [0x50, 0x61, 0x10]);
+
+        mock.clear();
+        mock.busy_for(3);
+        epd.clear_and_display(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.commands(), [0x50, 0x61, 0x10, 0x04, 0x12, 0x02]);
+    }
+
+    #[test]
+    fn clear_frame_palette() {
+        // four bits per pixel, two pixels of the same color in every byte
+        let expected = [
+            (OctColor::Black, 0x00),
+            (OctColor::White, 0x11),
+            (OctColor::Red, 0x44),
+        ];
+        for (color, byte) in expected {
+            let mock = Recorder::new(!IS_BUSY_LOW);
+            let mut epd: Epd4in01f<_, _, _, _, _, MockDelay> =
+                Epd4in01f::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+            epd.assume_initialized();
+            epd.set_background_color(color);
+
+            mock.busy_for(1);
+            epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+
+            let data = mock.data_after(0x10).unwrap();
+            assert_eq!(data.len(), (WIDTH * HEIGHT / 2) as usize);
+            assert!(data.iter().all(|&b| b == byte));
+        }
+    }
+}
@@ -174,4 +174,9 @@ mod tests {
 
         assert_eq!(Command::DisplayRefresh.address(), 0x12);
     }
+
+    #[test]
+    fn command_is_a_single_byte() {
+        assert_eq!(core::mem::size_of::<Command>(), 1);
+    }
 }
@@ -1,6 +1,6 @@
-use crate::epd4in2::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
-use crate::graphics::{Display, DisplayRotation};
-use embedded_graphics_core::pixelcolor::BinaryColor;
+use crate::epd4in2::{DEFAULT_BACKGROUND_COLOR, GRAY_BUFFER_LEN, HEIGHT, WIDTH};
+use crate::graphics::{rotated_size, Display, DisplayRotation, GrayDisplay};
+use embedded_graphics_core::pixelcolor::{BinaryColor, Gray4};
 use embedded_graphics_core::prelude::*;
 
 /// Full size buffer for use with the 4in2 EPD
@@ -38,7 +38,7 @@ impl DrawTarget for Display4in2 {
 
 impl OriginDimensions for Display4in2 {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        rotated_size(WIDTH, HEIGHT, self.rotation)
     }
 }
 
@@ -60,16 +60,71 @@ impl Display for Display4in2 {
     }
 }
 
+/// Full size buffer for the 4 level grayscale mode of the 4in2 EPD
+///
+/// Send it with [Epd4in2::update_and_display_gray_frame](crate::epd4in2::Epd4in2::update_and_display_gray_frame).
+pub struct Display4in2Gray {
+    buffer: [u8; GRAY_BUFFER_LEN],
+    rotation: DisplayRotation,
+}
+
+impl Default for Display4in2Gray {
+    fn default() -> Self {
+        Display4in2Gray {
+            buffer: [0xFF; GRAY_BUFFER_LEN],
+            rotation: DisplayRotation::default(),
+        }
+    }
+}
+
+impl DrawTarget for Display4in2Gray {
+    type Color = Gray4;
+    type Error = core::convert::Infallible;
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(WIDTH, HEIGHT, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Display4in2Gray {
+    fn size(&self) -> Size {
+        rotated_size(WIDTH, HEIGHT, self.rotation)
+    }
+}
+
+impl GrayDisplay for Display4in2Gray {
+    fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        &mut self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::color::Black;
     use crate::color::Color;
     use crate::epd4in2;
-    use crate::graphics::{Display, DisplayRotation};
+    use crate::graphics::{Display, DisplayRotation, GrayDisplay};
     use embedded_graphics::{
         prelude::*,
-        primitives::{Line, PrimitiveStyle},
+        primitives::{Line, PrimitiveStyle, Rectangle},
     };
 
     // test buffer length
@@ -161,4 +216,117 @@ mod tests {
             assert_eq!(byte, epd4in2::DEFAULT_BACKGROUND_COLOR.get_byte_value());
         }
     }
+
+    #[test]
+    fn graphics_bounding_box_follows_rotation() {
+        let mut display = Display4in2::default();
+        assert_eq!(display.bounding_box().size, Size::new(WIDTH, HEIGHT));
+
+        display.set_rotation(DisplayRotation::Rotate90);
+        assert_eq!(display.bounding_box().size, Size::new(HEIGHT, WIDTH));
+
+        display.set_rotation(DisplayRotation::Rotate180);
+        assert_eq!(display.bounding_box().size, Size::new(WIDTH, HEIGHT));
+
+        display.set_rotation(DisplayRotation::Rotate270);
+        assert_eq!(display.bounding_box().size, Size::new(HEIGHT, WIDTH));
+    }
+
+    #[test]
+    fn graphics_clipped_draw_stays_inside() {
+        let mut display = Display4in2::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+
+        let area = Rectangle::new(Point::new(0, 0), Size::new(1, 8));
+        let _ = Line::new(Point::new(0, 0), Point::new(0, 399))
+            .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+            .draw(&mut display.clipped(&area));
+
+        let buffer = display.buffer();
+        // the first eight pixels of the rotated column end up in the last byte of the first row
+        assert_eq!(
+            buffer[WIDTH as usize / 8 - 1],
+            Color::Black.get_byte_value()
+        );
+        for (index, &byte) in buffer.iter().enumerate() {
+            if index != WIDTH as usize / 8 - 1 {
+                assert_eq!(byte, epd4in2::DEFAULT_BACKGROUND_COLOR.get_byte_value());
+            }
+        }
+    }
+
+    fn gray_level(display: &Display4in2Gray, x: u32, y: u32) -> u8 {
+        let pixel = (y * WIDTH + x) as usize;
+        (display.buffer()[pixel / 4] >> (6 - 2 * (pixel % 4))) & 0x03
+    }
+
+    #[test]
+    fn gray_antialiased_line_has_intermediate_levels() {
+        use crate::graphics::AntialiasedLine;
+
+        let mut display = Display4in2Gray::default();
+        let _ = AntialiasedLine::new(Point::new(10, 10), Point::new(60, 35), 3, Gray4::BLACK)
+            .draw(&mut display);
+
+        let mut levels = [0usize; 4];
+        for y in 0..50 {
+            for x in 0..80 {
+                levels[gray_level(&display, x, y) as usize] += 1;
+            }
+        }
+        assert!(levels[0] > 0);
+        assert!(levels[1] + levels[2] > 0);
+        // the center of the line is black, far away from it stays white
+        assert_eq!(gray_level(&display, 34, 22), 0);
+        assert_eq!(gray_level(&display, 60, 10), 3);
+    }
+
+    #[test]
+    fn gray_dithers_between_levels() {
+        let mut display = Display4in2Gray::default();
+        let _ = Rectangle::new(Point::new(0, 0), Size::new(2, 2))
+            .into_styled(PrimitiveStyle::with_fill(Gray4::new(7)))
+            .draw(&mut display);
+        let _ = Rectangle::new(Point::new(2, 0), Size::new(2, 2))
+            .into_styled(PrimitiveStyle::with_fill(Gray4::new(5)))
+            .draw(&mut display);
+
+        assert_eq!(gray_level(&display, 0, 0), 1);
+        assert_eq!(gray_level(&display, 1, 0), 2);
+        assert_eq!(gray_level(&display, 0, 1), 2);
+        assert_eq!(gray_level(&display, 1, 1), 1);
+        // a color matching one of the levels isn't dithered
+        for (x, y) in [(2, 0), (3, 0), (2, 1), (3, 1)] {
+            assert_eq!(gray_level(&display, x, y), 1);
+        }
+        assert_eq!(gray_level(&display, 4, 0), 3);
+    }
+
+    // pixel (0, 0) is the most significant bit of the first byte, black clears the bit
+    #[test]
+    fn graphics_origin_bit() {
+        use crate::color::Black;
+        use crate::graphics::Display;
+        use embedded_graphics::prelude::{Drawable, Pixel, Point};
+
+        let mut display = Display4in2::default();
+        let _ = Pixel(Point::new(0, 0), Black).draw(&mut display);
+        assert_eq!(display.buffer()[0], 0b0111_1111);
+        let _ = Pixel(Point::new(1, 0), Black).draw(&mut display);
+        assert_eq!(display.buffer()[0], 0b0011_1111);
+    }
+
+    // pixel (0, 0) is in the two most significant bits of the first byte, 0b00 is black
+    #[test]
+    fn gray_graphics_origin_bits() {
+        use crate::graphics::GrayDisplay;
+        use embedded_graphics::pixelcolor::Gray4;
+        use embedded_graphics::prelude::{Drawable, GrayColor, Pixel, Point};
+
+        let mut display = Display4in2Gray::default();
+        let _ = Pixel(Point::new(0, 0), Gray4::BLACK).draw(&mut display);
+        assert_eq!(display.buffer()[0], 0b0011_1111);
+        let _ = Pixel(Point::new(3, 0), Gray4::BLACK).draw(&mut display);
+        assert_eq!(display.buffer()[0], 0b0011_1100);
+    }
 }
@@ -9,7 +9,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -50,12 +50,19 @@
 //! BE CAREFUL! The screen can get ghosting/burn-ins through the Partial Fast Update Drawing.
 
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::*,
 };
 
-use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::interface::{DisplayInterface, InterfaceConfig, LutLoadOrder};
+use crate::panel_setting::PanelSettingBuilder;
+use crate::traits::{
+    DisplayError, DisplayState, InternalWiAdditions, QuickRefresh, RefreshLut, SplitRefresh,
+    WaveshareDisplay,
+};
 
 //The Lookup Tables for the Display
 mod constants;
@@ -68,6 +75,192 @@ pub const HEIGHT: u32 = 300;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
+/// Panel setting with the LUT loaded from OTP, black/white mode, scanning up and shifting right
+const PANEL_SETTING: PanelSettingBuilder = PanelSettingBuilder::new().black_white(true);
+/// Soft start of the booster phases A, B and C: 10ms each, strength 3 and 6.58us off time
+const BOOSTER_SOFT_START: [u8; 3] = [0x17, 0x17, 0x17];
+
+/// Buffer length of a full frame in the 4 level grayscale mode (2 bits per pixel)
+pub const GRAY_BUFFER_LEN: usize = WIDTH as usize / 4 * HEIGHT as usize;
+
+/// Waveform tables used by [Epd4in2::update_and_display_gray_frame]
+///
+/// The four level tables are named after the bits the two RAM planes hold for a
+/// pixel, e.g. `wb` drives pixels which are white in the old and black in the new
+/// plane. Overriding them allows tuning the contrast between the gray levels, see
+/// [waveform_table] to build them phase by phase.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrayLuts {
+    /// VCOM table
+    pub vcom: [u8; 42],
+    /// Table for white pixels
+    pub ww: [u8; 42],
+    /// Table for light gray pixels
+    pub bw: [u8; 42],
+    /// Table for dark gray pixels
+    pub wb: [u8; 42],
+    /// Table for black pixels
+    pub bb: [u8; 42],
+}
+
+impl Default for GrayLuts {
+    /// The tables used by the Waveshare reference code
+    fn default() -> Self {
+        GrayLuts {
+            vcom: LUT_VCOM_GRAY,
+            ww: LUT_WW_GRAY,
+            bw: LUT_BW_GRAY,
+            wb: LUT_WB_GRAY,
+            bb: LUT_BB_GRAY,
+        }
+    }
+}
+
+/// Number of groups in a waveform table of [GrayLuts]
+pub const WAVEFORM_PHASES: usize = 7;
+
+/// Voltage driven during a phase of a [WaveformPhase]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum WaveformLevel {
+    /// Ground, VCOM_DC in the VCOM table
+    #[default]
+    Ground = 0b00,
+    /// VDH, VCOM_DC + VDH in the VCOM table
+    High = 0b01,
+    /// VDL, VCOM_DC + VDL in the VCOM table
+    Low = 0b10,
+    /// Floating in the VCOM table, not used in the pixel tables
+    Floating = 0b11,
+}
+
+impl WaveformLevel {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => WaveformLevel::Ground,
+            0b01 => WaveformLevel::High,
+            0b10 => WaveformLevel::Low,
+            _ => WaveformLevel::Floating,
+        }
+    }
+}
+
+/// One group of a waveform table: four phases A-D, repeated `repeat` times
+///
+/// A group takes 6 bytes of the table: the levels of the phases with A in the highest
+/// bits, the number of frames each phase lasts (TP A-D) and the repeat count (RP).
+///
+/// ```
+/// use epd_waveshare::epd4in2::{waveform_table, WaveformLevel, WaveformPhase};
+///
+/// let push_black = WaveformPhase {
+///     levels: [WaveformLevel::High, WaveformLevel::Low, WaveformLevel::Ground, WaveformLevel::Ground],
+///     frames: [0x14, 0x14, 0x00, 0x00],
+///     repeat: 1,
+/// };
+/// let table = waveform_table(&[push_black]);
+/// assert_eq!(table[..6], [0x60, 0x14, 0x14, 0x00, 0x00, 0x01]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct WaveformPhase {
+    /// Levels of the phases A to D
+    pub levels: [WaveformLevel; 4],
+    /// Frames of the phases A to D (TP)
+    pub frames: [u8; 4],
+    /// How often the four phases are repeated (RP), 0 skips the group
+    pub repeat: u8,
+}
+
+impl WaveformPhase {
+    /// The 6 bytes of the group in the table
+    pub fn to_bytes(self) -> [u8; 6] {
+        let levels = self
+            .levels
+            .iter()
+            .fold(0, |byte, &level| byte << 2 | level as u8);
+        let [a, b, c, d] = self.frames;
+        [levels, a, b, c, d, self.repeat]
+    }
+
+    /// Reads a group from its 6 bytes in the table
+    pub fn from_bytes(bytes: [u8; 6]) -> Self {
+        let [levels, a, b, c, d, repeat] = bytes;
+        WaveformPhase {
+            levels: [6, 4, 2, 0].map(|shift| WaveformLevel::from_bits(levels >> shift)),
+            frames: [a, b, c, d],
+            repeat,
+        }
+    }
+}
+
+/// Assembles a waveform table for [GrayLuts] from up to [WAVEFORM_PHASES] groups
+///
+/// The groups are run in order, the remaining groups of the table are left empty.
+///
+/// # Panics
+///
+/// If there are more than [WAVEFORM_PHASES] groups.
+pub fn waveform_table(phases: &[WaveformPhase]) -> [u8; 42] {
+    assert!(phases.len() <= WAVEFORM_PHASES);
+    let mut table = [0u8; 42];
+    for (group, phase) in table.chunks_mut(6).zip(phases) {
+        group.copy_from_slice(&phase.to_bytes());
+    }
+    table
+}
+
+/// Splits a waveform table into its groups, e.g. to change a single phase of the defaults
+pub fn waveform_phases(table: &[u8; 42]) -> [WaveformPhase; WAVEFORM_PHASES] {
+    let mut phases = [WaveformPhase::default(); WAVEFORM_PHASES];
+    for (phase, group) in phases.iter_mut().zip(table.chunks(6)) {
+        let mut bytes = [0u8; 6];
+        bytes.copy_from_slice(group);
+        *phase = WaveformPhase::from_bytes(bytes);
+    }
+    phases
+}
+
+/// Frame rates of the PLL control register
+///
+/// A higher frame rate makes the refresh faster, but might reduce the quality
+/// of the image depending on the LUT and the temperature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FrameRate {
+    /// 50Hz, the default of the controller
+    Hz50 = 0x3C,
+    /// 100Hz, used by this driver
+    #[default]
+    Hz100 = 0x3A,
+    /// 150Hz, not tested yet
+    Hz150 = 0x29,
+    /// 171Hz, not tested yet
+    Hz171 = 0x31,
+    /// 200Hz, didn't work on all boards
+    Hz200 = 0x39,
+}
+
+/// Level of the border during a refresh, bits 7-6 (VBD) of the VCOM and data interval setting
+///
+/// A floating border doesn't flash white with every refresh, but can slowly fade.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum BorderLevel {
+    /// Border isn't driven
+    Floating = 0b00,
+    /// Black border
+    Black = 0b01,
+    /// White border, used by this driver
+    #[default]
+    White = 0b10,
+}
+
+/// VCOM and data interval setting of the init sequence: white border, b/w mode, 10 hsync
+const VCOM_DATA_INTERVAL: u8 = 0x97;
+
+/// Composes the VCOM and data interval setting from its three fields
+fn vcom_data_interval_byte(vbd: BorderLevel, ddx: u8, cdi: u8) -> u8 {
+    (vbd as u8) << 6 | (ddx & 0x03) << 4 | (cdi & 0x0F)
+}
 
 use crate::color::Color;
 
@@ -77,10 +270,11 @@ use self::command::Command;
 #[cfg(feature = "graphics")]
 mod graphics;
 #[cfg(feature = "graphics")]
-pub use self::graphics::Display4in2;
+pub use self::graphics::{Display4in2, Display4in2Gray};
 
 /// Epd4in2 driver
 ///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd4in2<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
@@ -88,6 +282,14 @@ pub struct Epd4in2<SPI, CS, BUSY, DC, RST, DELAY> {
     color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Frame rate of the PLL
+    frame_rate: FrameRate,
+    /// Whether the factory LUT from OTP is used instead of the one in the registers
+    otp_lut: bool,
+    /// BoosterSoftStart bytes of the phases A, B and C
+    booster: [u8; 3],
+    /// VCOM and data interval setting sent by the init sequences
+    vcom_data_interval: u8,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -100,7 +302,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         // reset the device
         self.interface.reset(delay, 10);
 
@@ -113,21 +315,29 @@ where
 
         // start the booster
         self.interface
-            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
+            .cmd_with_data(spi, Command::BoosterSoftStart, &self.booster)?;
+
+        let lut_first = self
+            .interface
+            .lut_before_power_on(LutLoadOrder::AfterPowerOn);
+        if lut_first {
+            self.set_lut(spi, None)?;
+        }
 
         // power on
-        self.command(spi, Command::PowerOn)?;
-        delay.delay_ms(5);
+        self.interface
+            .cmd_then_delay(spi, delay, Command::PowerOn, 5)?;
         self.wait_until_idle();
+        self.interface.power_on_settle(delay);
 
         // set the panel settings
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x3F])?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[self.panel_setting()])?;
 
         // Set Frequency, 200 Hz didn't work on my board
         // 150Hz and 171Hz wasn't tested yet
         // TODO: Test these other frequencies
         // 3A 100HZ   29 150Hz 39 200HZ  31 171HZ DEFAULT: 3c 50Hz
-        self.cmd_with_data(spi, Command::PllControl, &[0x3A])?;
+        self.cmd_with_data(spi, Command::PllControl, &[self.frame_rate as u8])?;
 
         self.send_resolution(spi)?;
 
@@ -135,12 +345,19 @@ where
             .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
 
         //VBDF 17|D7 VBDW 97  VBDB 57  VBDF F7  VBDW 77  VBDB 37  VBDR B7
-        self.interface
-            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x97])?;
+        let vcom_data_interval = self.vcom_data_interval;
+        self.interface.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[vcom_data_interval],
+        )?;
 
-        self.set_lut(spi, None)?;
+        if !lut_first {
+            self.set_lut(spi, None)?;
+        }
 
         self.wait_until_idle();
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -163,26 +380,92 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd4in2 {
+        Epd4in2 {
             interface,
             color,
             refresh: RefreshLut::Full,
-        };
+            frame_rate: FrameRate::default(),
+            otp_lut: false,
+            booster: BOOSTER_SOFT_START,
+            vcom_data_interval: VCOM_DATA_INTERVAL,
+        }
+    }
 
-        epd.init(spi, delay)?;
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
 
-        Ok(epd)
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn can_partial_refresh(&self) -> bool {
+        self.interface.has_base_image()
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 10, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         self.interface
             .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17])?; //border floating
@@ -198,6 +481,7 @@ where
         self.wait_until_idle();
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
         Ok(())
     }
 
@@ -206,16 +490,17 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
-        let color_value = self.color.get_byte_value();
+        let color_value = self.color.panel_byte(BLACK_BIT);
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface
             .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
 
-        self.interface
-            .cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
         Ok(())
     }
 
@@ -227,7 +512,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         if buffer.len() as u32 != width / 8 * height {
             //TODO: panic!! or sth like that
@@ -265,9 +551,16 @@ where
         Ok(())
     }
 
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.wait_until_idle();
         self.command(spi, Command::DisplayRefresh)?;
+        self.interface.refreshed(self.refresh);
         Ok(())
     }
 
@@ -276,17 +569,22 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
+        self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.send_resolution(spi)?;
 
-        let color_value = self.color.get_byte_value();
+        let color_value = self.color.panel_byte(BLACK_BIT);
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
         self.interface
@@ -318,7 +616,7 @@ where
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
@@ -337,6 +635,15 @@ where
         }
     }
 
+    fn refresh_lut(&self) -> RefreshLut {
+        self.refresh
+    }
+
+    fn max_partial_refresh_hz(&self) -> u32 {
+        // a refresh with the quick LUT takes about 0.5s
+        2
+    }
+
     fn is_busy(&self) -> bool {
         self.interface.is_busy(IS_BUSY_LOW)
     }
@@ -351,11 +658,11 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -364,15 +671,15 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
     fn wait_until_idle(&mut self) {
-        let _ = self.interface.wait_until_idle(IS_BUSY_LOW);
+        self.interface.wait_until_idle(IS_BUSY_LOW);
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -391,7 +698,7 @@ where
         lut_bw: &[u8],
         lut_wb: &[u8],
         lut_bb: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         // LUT VCOM
         self.cmd_with_data(spi, Command::LutForVcom, lut_vcom)?;
@@ -410,6 +717,165 @@ where
         Ok(())
     }
 
+    fn panel_setting(&self) -> u8 {
+        PANEL_SETTING.register_lut(!self.otp_lut).build()
+    }
+
+    /// Switches to the factory LUT stored in the OTP of the controller
+    ///
+    /// A fallback if a custom LUT misbehaves. The LUTs set with
+    /// [set_lut](WaveshareDisplay::set_lut) are still written but aren't used until
+    /// [use_register_lut](Epd4in2::use_register_lut) is called. The choice is kept for the
+    /// next [wake_up](WaveshareDisplay::wake_up). Both fail with
+    /// [DisplayError::NotInitialized] until the display was initialised.
+    pub fn use_otp_lut(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.otp_lut = true;
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::PanelSetting, &[self.panel_setting()])
+    }
+
+    /// Switches back to the LUT in the registers, the default
+    ///
+    /// The LUT of the last [set_lut](WaveshareDisplay::set_lut) is written again.
+    pub fn use_register_lut(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.otp_lut = false;
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::PanelSetting, &[self.panel_setting()])?;
+        self.set_lut(spi, None)
+    }
+
+    /// Sets the frame rate of the PLL, independently of the LUT
+    ///
+    /// The frame rate is kept for the next [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_frame_rate(
+        &mut self,
+        spi: &mut SPI,
+        frame_rate: FrameRate,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.frame_rate = frame_rate;
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::PllControl, &[frame_rate as u8])
+    }
+
+    /// Sets the soft start of the booster phases A, B and C
+    ///
+    /// Each byte holds the soft start period in bits 7-6, the driving strength in bits 5-3 and
+    /// the minimum off time in bits 2-0, see the datasheet. Faster and stronger settings
+    /// shorten the power on but stress the panel more. The default `0x17` for all phases is
+    /// kept for the next [wake_up](WaveshareDisplay::wake_up), which is when the booster is
+    /// started with the new setting.
+    pub fn set_booster_soft_start(
+        &mut self,
+        spi: &mut SPI,
+        a: u8,
+        b: u8,
+        c: u8,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.booster = [a, b, c];
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[a, b, c])
+    }
+
+    /// Sets the VCOM and data interval setting
+    ///
+    /// `vbd` is the level of the border, `ddx` (2 bits) the data polarity with `0b01` for the
+    /// b/w mode of the driver and `cdi` (4 bits) the interval between VCOM and data, from
+    /// 17 hsync at `0` down to 2 hsync at `0x0F`. A floating border and a shorter interval
+    /// reduce the flash of the white border. The default is
+    /// `(BorderLevel::White, 0b01, 0b0111)`, it is kept for the next
+    /// [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_vcom_data_interval(
+        &mut self,
+        spi: &mut SPI,
+        vbd: BorderLevel,
+        ddx: u8,
+        cdi: u8,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.vcom_data_interval = vcom_data_interval_byte(vbd, ddx, cdi);
+        self.wait_until_idle();
+        let vcom_data_interval = self.vcom_data_interval;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[vcom_data_interval],
+        )
+    }
+
+    /// Initialises the display for 4 level grayscale refreshes
+    ///
+    /// Use [Epd4in2::update_and_display_gray_frame] afterwards. Calling `wake_up`
+    /// switches back to the black/white mode.
+    pub fn init_gray(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.reset(delay, 10);
+
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2b, 0x2b, 0x13])?;
+        let booster = self.booster;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &booster)?;
+
+        self.interface
+            .cmd_then_delay(spi, delay, Command::PowerOn, 5)?;
+        self.wait_until_idle();
+        self.interface.power_on_settle(delay);
+
+        let setting = PANEL_SETTING.register_lut(true).build();
+        self.cmd_with_data(spi, Command::PanelSetting, &[setting])?;
+        // 50Hz, as in the reference code
+        self.cmd_with_data(spi, Command::PllControl, &[FrameRate::Hz50 as u8])?;
+        self.send_resolution(spi)?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])?;
+        let vcom_data_interval = self.vcom_data_interval;
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[vcom_data_interval],
+        )?;
+        self.interface.set_initialized();
+        Ok(())
+    }
+
+    /// Transmits a 4 level grayscale frame, writes the gray `luts` and refreshes the display
+    ///
+    /// `buffer` holds 2 bits per pixel with the first pixel in the highest bits:
+    /// `0b11` is white, `0b10` light gray, `0b01` dark gray and `0b00` black.
+    /// Its length has to be [GRAY_BUFFER_LEN], otherwise [DisplayError::InvalidData] is returned.
+    ///
+    /// The display needs to be initialised with [Epd4in2::init_gray] first.
+    pub fn update_and_display_gray_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        luts: &GrayLuts,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        if buffer.len() != GRAY_BUFFER_LEN {
+            return Err(DisplayError::InvalidData);
+        }
+        self.wait_until_idle();
+
+        // the high bit of every pixel goes into the old plane, the low bit into the new one
+        self.command(spi, Command::DataStartTransmission1)?;
+        for pixels in buffer.chunks(2) {
+            self.send_data(spi, &[gray_plane_byte(pixels, 1)])?;
+        }
+        self.command(spi, Command::DataStartTransmission2)?;
+        for pixels in buffer.chunks(2) {
+            self.send_data(spi, &[gray_plane_byte(pixels, 0)])?;
+        }
+
+        self.set_lut_helper(spi, &luts.vcom, &luts.ww, &luts.bw, &luts.wb, &luts.bb)?;
+        self.interface.check_refresh_interval()?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.wait_until_idle();
+        Ok(())
+    }
+
     /// Helper function. Sets up the display to send pixel data to a custom
     /// starting point.
     pub fn shift_display(
@@ -419,7 +885,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.send_data(spi, &[(x >> 8) as u8])?;
         let tmp = x & 0xf8;
         self.send_data(spi, &[tmp as u8])?; // x should be the multiple of 8, the last 3 bit will always be ignored
@@ -439,6 +905,17 @@ where
     }
 }
 
+/// Collects the given bit of the eight 2 bit pixels in `pixels` into one plane byte
+fn gray_plane_byte(pixels: &[u8], bit: u8) -> u8 {
+    let mut byte = 0;
+    for &value in pixels {
+        for shift in (0..4).rev() {
+            byte = (byte << 1) | ((value >> (shift * 2 + bit)) & 0x01);
+        }
+    }
+    byte
+}
+
 impl<SPI, CS, BUSY, DC, RST, DELAY> QuickRefresh<SPI, CS, BUSY, DC, RST, DELAY>
     for Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>
 where
@@ -455,7 +932,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
 
         self.interface.cmd(spi, Command::DataStartTransmission1)?;
@@ -471,7 +949,8 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         // self.send_resolution(spi)?;
 
@@ -484,7 +963,12 @@ where
 
     /// This is a wrapper around `display_frame` for using this device as a true
     /// `QuickRefresh` device.
-    fn display_new_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.display_frame(spi, delay)
     }
 
@@ -497,7 +981,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_new_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)
     }
@@ -510,7 +994,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
 
         if buffer.len() as u32 != width / 8 * height {
@@ -540,7 +1025,8 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         if buffer.len() as u32 != width / 8 * height {
             //TODO: panic!! or sth like that
@@ -564,11 +1050,12 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.send_resolution(spi)?;
 
-        let color_value = self.color.get_byte_value();
+        let color_value = self.color.panel_byte(BLACK_BIT);
 
         self.interface.cmd(spi, Command::PartialIn)?;
         self.interface.cmd(spi, Command::PartialWindow)?;
@@ -588,9 +1075,146 @@ where
     }
 }
 
+/// Waveform and chip revision of the controller, see [Epd4in2::read_waveform_version]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Revision {
+    /// Version of the waveform (LUT) stored in the OTP, differs between panel batches
+    pub lut_version: u32,
+    /// Revision of the controller
+    pub chip_revision: u8,
+}
+
+impl Revision {
+    fn from_bytes(bytes: [u8; 4]) -> Self {
+        let [lut_high, lut_mid, lut_low, chip_revision] = bytes;
+        Revision {
+            lut_version: u32::from_be_bytes([0, lut_high, lut_mid, lut_low]),
+            chip_revision,
+        }
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> SplitRefresh<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn start_refresh(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.command(spi, Command::DisplayRefresh)?;
+        self.interface.refreshed(self.refresh);
+        Ok(())
+    }
+
+    fn finish_refresh(
+        &mut self,
+        _spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_for_idle(delay);
+        Ok(())
+    }
+
+    fn wait_for_idle(&mut self, delay: &mut DELAY) {
+        self.interface
+            .wait_until_idle_with_delay(IS_BUSY_LOW, delay);
+    }
+}
+
+/// Flags of the controller, see [Epd4in2::read_faults]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Faults {
+    /// The supply is below the low power detection level, e.g. an empty battery
+    pub low_power: bool,
+    /// Talking to the temperature sensor over I2C failed
+    pub i2c_error: bool,
+    /// The charge pump is running
+    pub power_on: bool,
+    /// The controller is still busy
+    pub busy: bool,
+}
+
+impl Faults {
+    fn from_bytes(status: u8, power: u8) -> Self {
+        Faults {
+            low_power: power & 0x01 == 0,
+            i2c_error: status & 0x20 != 0,
+            power_on: status & 0x04 != 0,
+            busy: status & 0x01 == 0,
+        }
+    }
+
+    /// Returns true if a flag points at a problem
+    pub fn any(&self) -> bool {
+        self.low_power || self.i2c_error
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd4in2<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Reads the version of the waveform in the OTP and the chip revision
+    ///
+    /// Helps to tell panel batches apart which need a different tuning. Needs an SPI which
+    /// can read and the data line of the controller connected to MISO, Waveshare boards
+    /// don't connect it by default.
+    pub fn read_waveform_version(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<Revision, DisplayError<<SPI as Write<u8>>::Error>> {
+        self.wait_until_idle();
+        self.interface.cmd(spi, Command::Revision)?;
+        let mut bytes = [0; 4];
+        self.interface.read(spi, &mut bytes)?;
+        Ok(Revision::from_bytes(bytes))
+    }
+
+    /// Reads the status and the low power detection flags of the controller
+    ///
+    /// Helps to tell a weak supply or a missing temperature sensor apart from a wiring problem
+    /// when refreshes keep failing. Has the same wiring needs as [Self::read_waveform_version].
+    pub fn read_faults(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<Faults, DisplayError<<SPI as Write<u8>>::Error>> {
+        let mut status = [0];
+        self.interface.cmd(spi, Command::GetStatus)?;
+        self.interface.read(spi, &mut status)?;
+        let mut power = [0];
+        self.interface.cmd(spi, Command::LowPowerDetection)?;
+        self.interface.read(spi, &mut power)?;
+        Ok(Faults::from_bytes(status[0], power[0]))
+    }
+
+    /// Clears the flags of the controller
+    ///
+    /// The controller has no command for this, its flags are only cleared by a reset, so this
+    /// resets and initialises the display again.
+    pub fn clear_faults(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<<SPI as Write<u8>>::Error>> {
+        self.init(spi, delay)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Event, MockDelay, Recorder};
 
     #[test]
     fn epd_size() {
@@ -598,4 +1222,676 @@ mod tests {
         assert_eq!(HEIGHT, 300);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn new_without_init_sends_nothing() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(mock.events(), []);
+
+        // an already initialised panel is usable straight away
+        epd.assume_initialized();
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.events(), [Event::Command(0x12)]);
+    }
+
+    #[test]
+    fn frames_need_init() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        let buffer = [0u8; WIDTH as usize / 8 * HEIGHT as usize];
+
+        assert_eq!(
+            epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay()),
+            Err(DisplayError::NotInitialized)
+        );
+        assert_eq!(
+            epd.display_frame(&mut mock.spi(), &mut mock.delay()),
+            Err(DisplayError::NotInitialized)
+        );
+        assert_eq!(mock.events(), []);
+
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(
+            epd.update_and_display_frame(&mut mock.spi(), &buffer, &mut mock.delay()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn configured_reset_pulses() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let config = InterfaceConfig::new()
+            .reset_pulses(3)
+            .reset_high_ms(5)
+            .reset_low_ms(20);
+        let _epd = Epd4in2::new_with_config(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+            config,
+        )
+        .unwrap();
+
+        assert_eq!(
+            mock.events()[..14],
+            [
+                Event::Reset(true),
+                Event::Delay(5),
+                Event::Reset(false),
+                Event::Delay(20),
+                Event::Reset(true),
+                Event::Delay(5),
+                Event::Reset(false),
+                Event::Delay(20),
+                Event::Reset(true),
+                Event::Delay(5),
+                Event::Reset(false),
+                Event::Delay(20),
+                Event::Reset(true),
+                Event::Delay(200),
+            ]
+        );
+    }
+
+    #[test]
+    fn default_reset_shape() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let _epd = Epd4in2::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            mock.events()[..6],
+            [
+                Event::Reset(true),
+                Event::Delay(10),
+                Event::Reset(false),
+                Event::Delay(10),
+                Event::Reset(true),
+                Event::Delay(200),
+            ]
+        );
+    }
+
+    #[test]
+    fn power_on_is_followed_by_delay() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let _epd = Epd4in2::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        let events = mock.events();
+        let power_on = events
+            .iter()
+            .position(|event| *event == Event::Command(0x04))
+            .unwrap();
+        assert_eq!(events[power_on + 1], Event::Delay(5));
+    }
+
+    #[test]
+    fn frame_rate_register() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let mut spi = mock.spi();
+
+        for (frame_rate, code) in [
+            (FrameRate::Hz50, 0x3C),
+            (FrameRate::Hz100, 0x3A),
+            (FrameRate::Hz150, 0x29),
+            (FrameRate::Hz171, 0x31),
+            (FrameRate::Hz200, 0x39),
+        ] {
+            mock.clear();
+            epd.set_frame_rate(&mut spi, frame_rate).unwrap();
+            assert_eq!(mock.events(), [Event::Command(0x30), Event::data(&[code])]);
+        }
+
+        // init keeps the chosen frame rate
+        mock.clear();
+        epd.wake_up(&mut spi, &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x30).unwrap(), [0x39]);
+    }
+
+    #[test]
+    fn booster_soft_start_register() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        let mut spi = mock.spi();
+
+        epd.wake_up(&mut spi, &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x06).unwrap(), [0x17, 0x17, 0x17]);
+
+        mock.clear();
+        epd.set_booster_soft_start(&mut spi, 0x07, 0x0F, 0x27)
+            .unwrap();
+        assert_eq!(
+            mock.events(),
+            [Event::Command(0x06), Event::data(&[0x07, 0x0F, 0x27])]
+        );
+
+        // the next init starts the booster with the new setting
+        mock.clear();
+        epd.wake_up(&mut spi, &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x06).unwrap(), [0x07, 0x0F, 0x27]);
+    }
+
+    #[test]
+    fn warm_up_refreshes_cycles_times() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.warm_up(&mut mock.spi(), &mut mock.delay(), 3).unwrap();
+
+        let refreshes = mock
+            .commands()
+            .into_iter()
+            .filter(|&command| command == 0x12)
+            .count();
+        assert_eq!(refreshes, 3);
+    }
+
+    #[test]
+    fn flush_and_sleep_refreshes_first() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let buffer = [0x00; WIDTH as usize / 8 * HEIGHT as usize];
+
+        epd.flush_and_sleep(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+
+        assert_eq!(
+            mock.commands(),
+            [0x10, 0x13, 0x12, 0x50, 0x82, 0x00, 0x01, 0x02, 0x07]
+        );
+        assert_eq!(mock.data_after(0x07).unwrap(), [0xA5]);
+    }
+
+    #[test]
+    fn gray_plane_split() {
+        // white, light gray, dark gray, black, then the same again
+        let pixels = [0b11_10_01_00, 0b11_10_01_00];
+        assert_eq!(gray_plane_byte(&pixels, 1), 0b1100_1100);
+        assert_eq!(gray_plane_byte(&pixels, 0), 0b1010_1010);
+    }
+
+    #[test]
+    fn gray_frame_sends_custom_luts() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let luts = GrayLuts {
+            vcom: [0x01; 42],
+            ww: [0x02; 42],
+            bw: [0x03; 42],
+            wb: [0x04; 42],
+            bb: [0x05; 42],
+        };
+        let buffer = [0b10_10_10_10; GRAY_BUFFER_LEN];
+
+        epd.update_and_display_gray_frame(&mut mock.spi(), &buffer, &luts, &mut mock.delay())
+            .unwrap();
+
+        let events = mock.events();
+        assert_eq!(events[0], Event::Command(0x10));
+        assert_eq!(events[1], Event::data(&[0xFF; GRAY_BUFFER_LEN / 2]));
+        assert_eq!(events[2], Event::Command(0x13));
+        assert_eq!(events[3], Event::data(&[0x00; GRAY_BUFFER_LEN / 2]));
+        assert_eq!(
+            events[4..],
+            [
+                Event::Command(0x20),
+                Event::data(&[0x01; 42]),
+                Event::Command(0x21),
+                Event::data(&[0x02; 42]),
+                Event::Command(0x22),
+                Event::data(&[0x03; 42]),
+                Event::Command(0x23),
+                Event::data(&[0x04; 42]),
+                Event::Command(0x24),
+                Event::data(&[0x05; 42]),
+                Event::Command(0x12),
+            ]
+        );
+    }
+
+    #[test]
+    fn gray_frame_of_the_wrong_length_is_refused() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let buffer = [0xFF; GRAY_BUFFER_LEN - 1];
+
+        assert_eq!(
+            epd.update_and_display_gray_frame(
+                &mut mock.spi(),
+                &buffer,
+                &GrayLuts::default(),
+                &mut mock.delay()
+            ),
+            Err(DisplayError::InvalidData)
+        );
+        assert_eq!(mock.events(), []);
+    }
+
+    #[test]
+    fn gray_frame_keeps_the_refresh_interval() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static NOW_MS: AtomicU32 = AtomicU32::new(0);
+        fn now_ms() -> u32 {
+            NOW_MS.load(Ordering::SeqCst)
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+        let buffer = [0xFF; GRAY_BUFFER_LEN];
+        let luts = GrayLuts::default();
+
+        epd.update_and_display_gray_frame(&mut mock.spi(), &buffer, &luts, &mut mock.delay())
+            .unwrap();
+        assert!(mock.commands().contains(&0x12));
+
+        mock.clear();
+        NOW_MS.store(999, Ordering::SeqCst);
+        assert_eq!(
+            epd.update_and_display_gray_frame(&mut mock.spi(), &buffer, &luts, &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+        assert!(!mock.commands().contains(&0x12));
+    }
+
+    #[test]
+    fn gray_init_runs_at_50hz() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.init_gray(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x30).unwrap(), [0x3C]);
+    }
+
+    #[test]
+    fn otp_and_register_lut() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        // refused before the init, like the frames
+        assert_eq!(
+            epd.use_otp_lut(&mut mock.spi()),
+            Err(DisplayError::NotInitialized)
+        );
+        assert_eq!(
+            epd.use_register_lut(&mut mock.spi()),
+            Err(DisplayError::NotInitialized)
+        );
+        assert!(mock.events().is_empty());
+
+        epd.assume_initialized();
+        epd.use_otp_lut(&mut mock.spi()).unwrap();
+        assert_eq!(mock.data_after(0x00).unwrap(), [0x1F]);
+
+        // kept when waking up
+        mock.clear();
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x00).unwrap()[0] & 0x20, 0);
+
+        mock.clear();
+        epd.use_register_lut(&mut mock.spi()).unwrap();
+        assert_eq!(mock.data_after(0x00).unwrap(), [0x3F]);
+        assert_eq!(mock.data_after(0x20).unwrap(), LUT_VCOM0);
+    }
+
+    #[test]
+    fn vcom_data_interval() {
+        assert_eq!(
+            vcom_data_interval_byte(BorderLevel::default(), 0b01, 0b0111),
+            VCOM_DATA_INTERVAL
+        );
+        assert_eq!(
+            vcom_data_interval_byte(BorderLevel::Floating, 0b01, 0x07),
+            0x17
+        );
+        assert_eq!(
+            vcom_data_interval_byte(BorderLevel::Black, 0b11, 0xFF),
+            0x7F
+        );
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd4in2::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+        assert_eq!(mock.data_after(0x50).unwrap(), [0x97]);
+
+        mock.clear();
+        epd.set_vcom_data_interval(&mut mock.spi(), BorderLevel::Floating, 0b01, 0x0A)
+            .unwrap();
+        assert_eq!(mock.events(), [Event::Command(0x50), Event::data(&[0x1A])]);
+
+        // kept for the next wake up
+        mock.clear();
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x50).unwrap(), [0x1A]);
+    }
+
+    #[test]
+    fn pair_refreshes_before_waiting() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut first: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        let mut second: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        first.assume_initialized();
+        second.assume_initialized();
+
+        let buffer = [0xFF; WIDTH as usize / 8 * HEIGHT as usize];
+        mock.log_busy_reads();
+        crate::update_and_display_pair(
+            &mut mock.spi(),
+            &mut mock.delay(),
+            &mut first,
+            &buffer,
+            &mut second,
+            &buffer,
+        )
+        .unwrap();
+
+        // both uploads come first, then the two refreshes back to back
+        let events = mock.events();
+        let refreshes: std::vec::Vec<_> = events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| **event == Event::Command(0x12))
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(refreshes.len(), 2);
+        assert_eq!(refreshes[1], refreshes[0] + 1);
+        assert_eq!(
+            events[..refreshes[0]]
+                .iter()
+                .filter(|event| **event == Event::Command(0x13))
+                .count(),
+            2
+        );
+        // the waits for the end of the refreshes follow
+        assert!(events[refreshes[1] + 1..]
+            .iter()
+            .all(|event| *event == Event::BusyRead));
+        assert!(events.len() > refreshes[1] + 1);
+    }
+
+    #[test]
+    fn pair_sleeps_between_busy_polls() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let other = Recorder::new(IS_BUSY_LOW);
+        let mut first: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        let mut second: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(other.cs(), other.busy(), other.dc(), other.rst());
+        first.assume_initialized();
+        second.assume_initialized();
+
+        // idle for the upload of the second frame, busy once both are sent
+        other.idle_for(1);
+        other.busy_for(3);
+        let buffer = [0xFF; WIDTH as usize / 8 * HEIGHT as usize];
+        crate::update_and_display_pair(
+            &mut mock.spi(),
+            &mut mock.delay(),
+            &mut first,
+            &buffer,
+            &mut second,
+            &buffer,
+        )
+        .unwrap();
+
+        let events = mock.events();
+        let refresh = events
+            .iter()
+            .position(|event| *event == Event::Command(0x12))
+            .unwrap();
+        let sleeps = events[..refresh]
+            .iter()
+            .filter(|event| **event == Event::Delay(20))
+            .count();
+        assert_eq!(sleeps, 3);
+    }
+
+    #[test]
+    fn power_on_settle_follows_busy_wait() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        mock.log_busy_reads();
+        mock.busy_for(2);
+        let _epd = Epd4in2::new_with_config(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+            InterfaceConfig::new().power_on_settle_ms(50),
+        )
+        .unwrap();
+
+        let events = mock.events();
+        let power_on = events
+            .iter()
+            .position(|event| *event == Event::Command(0x04))
+            .unwrap();
+        assert_eq!(
+            events[power_on..power_on + 7],
+            [
+                Event::Command(0x04),
+                Event::Delay(5),
+                Event::BusyRead,
+                Event::BusyRead,
+                Event::BusyRead,
+                Event::Delay(50),
+                Event::Command(0x00),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_waveform_version() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        mock.reply(&[0x01, 0x02, 0x0A, 0x1F]);
+        let revision = epd.read_waveform_version(&mut mock.spi()).unwrap();
+        assert_eq!(
+            revision,
+            Revision {
+                lut_version: 0x01020A,
+                chip_revision: 0x1F,
+            }
+        );
+        assert_eq!(mock.commands(), [0x70]);
+    }
+
+    #[test]
+    fn read_faults() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        // I2C error and power on, but idle, and a supply below the detection level
+        mock.reply(&[0x25, 0x00]);
+        let faults = epd.read_faults(&mut mock.spi()).unwrap();
+        assert_eq!(
+            faults,
+            Faults {
+                low_power: true,
+                i2c_error: true,
+                power_on: true,
+                busy: false,
+            }
+        );
+        assert!(faults.any());
+        assert_eq!(mock.commands(), [0x71, 0x51]);
+
+        mock.clear();
+        mock.reply(&[0x02, 0x01]);
+        let faults = epd.read_faults(&mut mock.spi()).unwrap();
+        assert!(!faults.any());
+        assert!(faults.busy);
+
+        mock.clear();
+        epd.clear_faults(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.commands()[..2], [0x01, 0x06]);
+    }
+
+    #[test]
+    fn max_partial_refresh_hz() {
+        use crate::mock::{MockPin, MockSpi};
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let epd: Epd4in2<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(epd.max_partial_refresh_hz(), 2);
+    }
+
+    #[test]
+    fn waveform_phases_assemble_the_table() {
+        use WaveformLevel::{Ground, High, Low};
+
+        // the light gray table of the reference code, group by group
+        let phases = [
+            WaveformPhase {
+                levels: [High, Ground, Ground, Ground],
+                frames: [0x0A, 0x00, 0x00, 0x00],
+                repeat: 1,
+            },
+            WaveformPhase {
+                levels: [Low, High, Ground, Ground],
+                frames: [0x14, 0x14, 0x00, 0x00],
+                repeat: 1,
+            },
+            WaveformPhase {
+                levels: [Ground, Ground, Ground, Ground],
+                frames: [0x14, 0x0A, 0x00, 0x00],
+                repeat: 1,
+            },
+            WaveformPhase {
+                levels: [Low, High, Low, High],
+                frames: [0x0C, 0x01, 0x03, 0x04],
+                repeat: 1,
+            },
+            WaveformPhase {
+                levels: [Ground, Ground, Ground, Low],
+                frames: [0x04, 0x01, 0x00, 0x00],
+                repeat: 1,
+            },
+        ];
+        assert_eq!(waveform_table(&phases), LUT_BW_GRAY);
+        assert_eq!(waveform_phases(&LUT_BW_GRAY)[..5], phases);
+        assert_eq!(waveform_phases(&LUT_BW_GRAY)[5], WaveformPhase::default());
+
+        let floating = WaveformPhase {
+            levels: [WaveformLevel::Floating; 4],
+            frames: [1, 2, 3, 4],
+            repeat: 5,
+        };
+        assert_eq!(floating.to_bytes(), [0xFF, 1, 2, 3, 4, 5]);
+        assert_eq!(WaveformPhase::from_bytes(floating.to_bytes()), floating);
+    }
+
+    #[test]
+    fn lut_load_order_around_the_power_on() {
+        let lut_after_power_on = |config: InterfaceConfig| {
+            let mock = Recorder::new(IS_BUSY_LOW);
+            let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+                Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+            epd.set_interface_config(config);
+            epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+
+            let commands = mock.commands();
+            let power_on = commands.iter().position(|&c| c == 0x04).unwrap();
+            let lut = commands.iter().position(|&c| c == 0x20).unwrap();
+            assert_eq!(commands.iter().filter(|&&c| c == 0x20).count(), 1);
+            power_on < lut
+        };
+
+        // the reference code writes the LUT at the end of the init
+        assert!(lut_after_power_on(InterfaceConfig::new()));
+        assert!(!lut_after_power_on(
+            InterfaceConfig::new().lut_load_order(LutLoadOrder::BeforePowerOn)
+        ));
+    }
+
+    #[test]
+    fn update_and_display_frame_keeps_the_refresh_interval() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static NOW_MS: AtomicU32 = AtomicU32::new(0);
+        fn now_ms() -> u32 {
+            NOW_MS.load(Ordering::SeqCst)
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+        let buffer = std::vec![0xFF; crate::buffer_len(WIDTH as usize, HEIGHT as usize)];
+
+        epd.update_and_display_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        assert!(mock.commands().contains(&0x12));
+
+        // the frame is still sent, but the refresh is refused
+        mock.clear();
+        NOW_MS.store(999, Ordering::SeqCst);
+        assert_eq!(
+            epd.update_and_display_frame(&mut mock.spi(), &buffer, &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+        assert!(!mock.commands().contains(&0x12));
+    }
+
+    #[test]
+    fn state_after_a_waiting_refresh() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        // display_frame waits for the end of the refresh itself
+        mock.busy_for(3);
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(epd.state(), DisplayState::Ready);
+        epd.sleep(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(epd.state(), DisplayState::Sleeping);
+    }
 }
@@ -1,6 +1,6 @@
 use crate::color::OctColor;
 use crate::epd5in65f::{DEFAULT_BACKGROUND_COLOR, HEIGHT, WIDTH};
-use crate::graphics::{DisplayRotation, OctDisplay};
+use crate::graphics::{rotated_size, DisplayRotation, OctDisplay};
 use embedded_graphics_core::prelude::*;
 
 /// Full size buffer for use with the 5in65f EPD
@@ -39,7 +39,7 @@ impl DrawTarget for Display5in65f {
 
 impl OriginDimensions for Display5in65f {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        rotated_size(WIDTH, HEIGHT, self.rotation)
     }
 }
 
@@ -260,4 +260,18 @@ mod tests {
                 _ => panic!("unexpected pattern"),
             })
     }
+
+    // pixel (0, 0) is the upper nibble of the first byte
+    #[test]
+    fn graphics_origin_nibble() {
+        use crate::color::OctColor;
+        use crate::graphics::OctDisplay;
+        use embedded_graphics::prelude::{Drawable, Pixel, Point};
+
+        let mut display = Display5in65f::default();
+        let _ = Pixel(Point::new(0, 0), OctColor::Black).draw(&mut display);
+        assert_eq!(display.buffer()[0] >> 4, OctColor::Black.get_nibble());
+        let _ = Pixel(Point::new(1, 0), OctColor::Red).draw(&mut display);
+        assert_eq!(display.buffer()[0] & 0x0F, OctColor::Red.get_nibble());
+    }
 }
@@ -11,9 +11,11 @@ use embedded_hal::{
     digital::v2::{InputPin, OutputPin},
 };
 
-use crate::color::OctColor;
-use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::color::{OctColor, OctColorMap};
+use crate::interface::{DisplayInterface, InterfaceConfig};
+use crate::traits::{
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -30,9 +32,12 @@ pub const HEIGHT: u32 = 448;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
 const IS_BUSY_LOW: bool = true;
+// No `BLACK_BIT` here: the frame memory takes a 4 bit color index per pixel,
+// with 0x0 for black, see [OctColor]
 
 /// Epd5in65f driver
 ///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd5in65f<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
@@ -50,7 +55,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         // Reset the device
         self.interface.reset(delay, 2);
 
@@ -69,6 +74,7 @@ where
         delay.delay_ms(100);
 
         self.update_vcom(spi)?;
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -91,23 +97,82 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd5in65f { interface, color };
+        Epd5in65f { interface, color }
+    }
 
-        epd.init(spi, delay)?;
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
 
-        Ok(epd)
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
         Ok(())
     }
 
@@ -116,35 +181,24 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_busy_high();
         self.update_vcom(spi)?;
         self.send_resolution(spi)?;
-        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)?;
+        self.interface.cmd(spi, Command::DataStartTransmission1)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
         Ok(())
     }
 
-    fn update_partial_frame(
+    fn display_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
-    }
-
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
-        self.wait_busy_high();
-        self.command(spi, Command::PowerOn)?;
-        self.wait_busy_high();
-        self.command(spi, Command::DisplayRefresh)?;
-        self.wait_busy_high();
-        self.command(spi, Command::PowerOff)?;
-        self.wait_busy_low();
-        Ok(())
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.start_refresh(spi)?;
+        self.finish_refresh(spi)
     }
 
     fn update_and_display_frame(
@@ -152,20 +206,24 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         let bg = OctColor::colors_byte(self.color, self.color);
         self.wait_busy_high();
         self.update_vcom(spi)?;
         self.send_resolution(spi)?;
         self.command(spi, Command::DataStartTransmission1)?;
         self.interface.data_x_times(spi, bg, WIDTH * HEIGHT / 2)?;
-        self.display_frame(spi, delay)?;
         Ok(())
     }
 
@@ -189,8 +247,8 @@ where
         &mut self,
         _spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        Err(DisplayError::Unsupported)
     }
 
     fn is_busy(&self) -> bool {
@@ -207,11 +265,71 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    /// Starts displaying the frame in the buffer without waiting for the refresh to end
+    ///
+    /// A full refresh takes around 30 seconds, poll [is_busy](WaveshareDisplay::is_busy) until
+    /// it is done and then call [finish_refresh](Epd5in65f::finish_refresh), or stop it early
+    /// with [abort_refresh](Epd5in65f::abort_refresh).
+    pub fn start_refresh(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.wait_busy_high();
+        self.command(spi, Command::PowerOn)?;
+        self.wait_busy_high();
+        self.command(spi, Command::DisplayRefresh)?;
+        Ok(())
+    }
+
+    /// Waits for a refresh started with [start_refresh](Epd5in65f::start_refresh) and powers off
+    pub fn finish_refresh(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_busy_high();
+        self.command(spi, Command::PowerOff)?;
+        self.wait_busy_low();
+        Ok(())
+    }
+
+    /// Halts a refresh started with [start_refresh](Epd5in65f::start_refresh)
+    ///
+    /// The controller gets a hardware reset and is initialised again, so a new frame can be
+    /// sent right away. What the panel shows after an abort is undefined, it is usually a
+    /// mix of the old and the new image until the next full refresh.
+    pub fn abort_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    /// Like [update_frame](WaveshareDisplay::update_frame), but every pixel is shown
+    /// as the color `map` substitutes for it
+    pub fn update_frame_remapped(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        map: &OctColorMap,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_busy_high();
+        self.update_vcom(spi)?;
+        self.send_resolution(spi)?;
+        self.command(spi, Command::DataStartTransmission1)?;
+        let mut chunk = [0u8; 32];
+        for bytes in buffer.chunks(chunk.len()) {
+            for (mapped, &byte) in chunk.iter_mut().zip(bytes) {
+                *mapped = map.map_byte(byte);
+            }
+            self.send_data(spi, &chunk[..bytes.len()])?;
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.data(spi, data)
     }
 
@@ -220,17 +338,17 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
     fn wait_busy_high(&mut self) {
-        let _ = self.interface.wait_until_idle(true);
+        self.interface.wait_until_idle(true);
     }
     fn wait_busy_low(&mut self) {
-        let _ = self.interface.wait_until_idle(false);
+        self.interface.wait_until_idle(false);
     }
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
         let w = self.width();
         let h = self.height();
 
@@ -241,7 +359,7 @@ where
         self.send_data(spi, &[h as u8])
     }
 
-    fn update_vcom(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
+    fn update_vcom(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
         let bg_color = (self.color.get_nibble() & 0b111) << 5;
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x17 | bg_color])?;
         Ok(())
@@ -251,6 +369,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Event, MockDelay, Recorder};
 
     #[test]
     fn epd_size() {
@@ -258,4 +377,91 @@ mod tests {
         assert_eq!(HEIGHT, 448);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
     }
+
+    #[test]
+    fn abort_refresh_resets_and_reinitialises() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd5in65f<_, _, _, _, _, MockDelay> =
+            Epd5in65f::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_background_color(OctColor::Red);
+
+        epd.start_refresh(&mut mock.spi()).unwrap();
+        assert_eq!(mock.commands(), [0x04, 0x12]);
+
+        mock.clear();
+        epd.abort_refresh(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+
+        assert_eq!(mock.events()[0], Event::Reset(true));
+        assert_eq!(mock.commands()[..2], [0x00, 0x01]);
+        assert!(mock.commands().contains(&0xE3));
+        // the background color survives and is sent again
+        assert_eq!(mock.data_after(0x50).unwrap(), [0x17 | (0x04 << 5)]);
+    }
+
+    #[test]
+    fn update_frame_remaps_colors() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd5in65f<_, _, _, _, _, MockDelay> =
+            Epd5in65f::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let map = OctColorMap::new()
+            .remap(OctColor::Orange, OctColor::Red)
+            .remap(OctColor::HiZ, OctColor::White);
+        let buffer = [0x66u8; 40];
+
+        epd.update_frame_remapped(&mut mock.spi(), &buffer, &map, &mut mock.delay())
+            .unwrap();
+
+        assert_eq!(mock.data_after(0x10).unwrap(), [0x44; 40]);
+
+        mock.clear();
+        epd.update_frame_remapped(&mut mock.spi(), &[0x70, 0x16], &map, &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.data_after(0x10).unwrap(), [0x10, 0x14]);
+    }
+
+    #[test]
+    fn clear_frame_only_fills_ram() {
+        // every wait for the powered on controller reads the busy line high once
+        let mock = Recorder::new(!IS_BUSY_LOW);
+        let mut epd: Epd5in65f<_, _, _, _, _, MockDelay> =
+            Epd5in65f::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        mock.busy_for(1);
+        epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.commands(), [0x50, 0x61, 0x10]);
+
+        mock.clear();
+        mock.busy_for(4);
+        epd.clear_and_display(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.commands(), [0x50, 0x61, 0x10, 0x04, 0x12, 0x02]);
+    }
+
+    #[test]
+    fn clear_frame_palette() {
+        // four bits per pixel, two pixels of the same color in every byte
+        let expected = [
+            (OctColor::Black, 0x00),
+            (OctColor::White, 0x11),
+            (OctColor::Red, 0x44),
+        ];
+        for (color, byte) in expected {
+            let mock = Recorder::new(!IS_BUSY_LOW);
+            let mut epd: Epd5in65f<_, _, _, _, _, MockDelay> =
+                Epd5in65f::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+            epd.assume_initialized();
+            epd.set_background_color(color);
+
+            mock.busy_for(1);
+            epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+
+            let data = mock.data_after(0x10).unwrap();
+            assert_eq!(data.len(), (WIDTH * HEIGHT / 2) as usize);
+            assert!(data.iter().all(|&b| b == byte));
+        }
+    }
 }
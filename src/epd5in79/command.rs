@@ -0,0 +1,96 @@
+//! SPI Commands for the Waveshare 5.79" E-Ink Display
+
+use crate::traits;
+
+/// Epd5in79 commands
+///
+/// The panel is driven by two cascaded controllers. Commands with the highest bit set
+/// address the slave controller which drives the right half of the panel.
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Enters deep sleep mode, 0x03 keeps the RAM
+    DeepSleepMode = 0x10,
+
+    /// Defines the data entry mode of the master
+    DataEntryModeSetting = 0x11,
+
+    /// Resets the commands and parameters to their S/W Reset default values, except R10h
+    SwReset = 0x12,
+
+    /// Selects the internal or an external temperature sensor
+    TemperatureSensorSelection = 0x18,
+
+    /// Activates the display update sequence
+    MasterActivation = 0x20,
+
+    /// Display update sequence option
+    DisplayUpdateControl2 = 0x22,
+
+    /// Writes the black/white RAM of the master
+    WriteRam = 0x24,
+
+    /// Writes the old data RAM of the master
+    WriteRam2 = 0x26,
+
+    /// Selects the waveform of the border
+    BorderWaveformControl = 0x3C,
+
+    /// Start and end of the x addresses of the master, in bytes
+    SetRamXAddressStartEndPosition = 0x44,
+
+    /// Start and end of the y addresses of the master
+    SetRamYAddressStartEndPosition = 0x45,
+
+    /// Initial x address counter of the master
+    SetRamXAddressCounter = 0x4E,
+
+    /// Initial y address counter of the master
+    SetRamYAddressCounter = 0x4F,
+
+    /// Defines the data entry mode of the slave
+    SlaveDataEntryModeSetting = 0x91,
+
+    /// Writes the black/white RAM of the slave
+    SlaveWriteRam = 0xA4,
+
+    /// Writes the old data RAM of the slave
+    SlaveWriteRam2 = 0xA6,
+
+    /// Start and end of the x addresses of the slave, in bytes
+    SlaveSetRamXAddressStartEndPosition = 0xC4,
+
+    /// Start and end of the y addresses of the slave
+    SlaveSetRamYAddressStartEndPosition = 0xC5,
+
+    /// Initial x address counter of the slave
+    SlaveSetRamXAddressCounter = 0xCE,
+
+    /// Initial y address counter of the slave
+    SlaveSetRamYAddressCounter = 0xCF,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::SwReset.address(), 0x12);
+        assert_eq!(Command::WriteRam.address(), 0x24);
+        assert_eq!(Command::SlaveWriteRam.address(), 0xA4);
+        assert_eq!(Command::SlaveSetRamYAddressCounter.address(), 0xCF);
+    }
+}
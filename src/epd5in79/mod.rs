@@ -0,0 +1,482 @@
+//! A simple Driver for the Waveshare 5.79" E-Ink Display via SPI
+//!
+//! The 792x272 panel is driven by two cascaded SSD1683 controllers. The master drives the
+//! left 400 pixels and the slave the right 400 pixels, both halves overlap by 8 pixels in
+//! the middle. The driver splits the frame between them, so the buffer is a normal
+//! black/white buffer of the whole panel.
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/5.79inch_e-Paper_Module)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_5in79.c)
+//!
+//! # Examples
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+//!use embedded_graphics::{
+//!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{epd5in79::*, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let mut spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::MockNoop::new();
+//!
+//!// Setup EPD
+//!let mut epd = Epd5in79::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+//!
+//!// Use display graphics from embedded-graphics
+//!let mut display = Display5in79::default();
+//!
+//!// Use embedded graphics for drawing a line
+//!let _ = Line::new(Point::new(0, 0), Point::new(791, 271))
+//!    .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+//!    .draw(&mut display);
+//!
+//!// Display updated frame
+//!epd.update_frame(&mut spi, &display.buffer(), &mut delay)?;
+//!epd.display_frame(&mut spi, &mut delay)?;
+//!
+//!// Set the EPD to sleep
+//!epd.sleep(&mut spi, &mut delay)?;
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::buffer_len;
+use crate::color::Color;
+use crate::interface::{DisplayInterface, InterfaceConfig};
+use crate::traits::{
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display5in79;
+
+/// Width of the display
+pub const WIDTH: u32 = 792;
+/// Height of the display
+pub const HEIGHT: u32 = 272;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const IS_BUSY_LOW: bool = false;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
+
+/// Bytes per row of the whole frame
+const ROW_LEN: usize = buffer_len(WIDTH as usize, 1);
+/// Bytes per row of the RAM of each controller, 400 pixels
+const HALF_ROW_LEN: usize = 50;
+/// First byte of a frame row which is sent to the slave
+const SLAVE_START: usize = ROW_LEN - HALF_ROW_LEN;
+
+/// Epd5in79 driver
+///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
+pub struct Epd5in79<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd5in79<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        // Reset the device
+        self.interface.reset(delay, 2);
+        self.wait_until_idle();
+
+        self.command(spi, Command::SwReset)?;
+        self.wait_until_idle();
+
+        // master: x increment, y decrement
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x01])?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[0x00, HALF_ROW_LEN as u8 - 1],
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00, 0x00],
+        )?;
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])?;
+        // internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])?;
+        self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00])?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8],
+        )?;
+        self.wait_until_idle();
+
+        // slave: x decrement, y decrement, it is mounted mirrored
+        self.cmd_with_data(spi, Command::SlaveDataEntryModeSetting, &[0x00])?;
+        self.cmd_with_data(
+            spi,
+            Command::SlaveSetRamXAddressStartEndPosition,
+            &[HALF_ROW_LEN as u8 - 1, 0x00],
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::SlaveSetRamYAddressStartEndPosition,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00, 0x00],
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::SlaveSetRamXAddressCounter,
+            &[HALF_ROW_LEN as u8 - 1],
+        )?;
+        self.cmd_with_data(
+            spi,
+            Command::SlaveSetRamYAddressCounter,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8],
+        )?;
+        self.wait_until_idle();
+
+        self.interface.set_initialized();
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd5in79<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        Epd5in79 { interface, color }
+    }
+
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::SwReset, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        // deep sleep mode 2, RAM isn't kept
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x03])?;
+        self.interface.set_sleeping();
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        // every row is split between the controllers, so only full frames can be sent
+        if buffer.len() != ROW_LEN * HEIGHT as usize {
+            return Err(DisplayError::InvalidData);
+        }
+        self.wait_until_idle();
+
+        self.command(spi, Command::WriteRam)?;
+        for row in self
+            .interface
+            .frame_rows(buffer, HEIGHT)
+            .flat_map(|rows| rows.chunks(ROW_LEN))
+        {
+            self.interface.data(spi, &row[..HALF_ROW_LEN])?;
+        }
+
+        self.command(spi, Command::SlaveWriteRam)?;
+        for row in self
+            .interface
+            .frame_rows(buffer, HEIGHT)
+            .flat_map(|rows| rows.chunks(ROW_LEN))
+        {
+            self.interface.data(spi, &row[SLAVE_START..])?;
+        }
+        Ok(())
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.wait_until_idle();
+        // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
+        self.command(spi, Command::MasterActivation)?;
+        self.wait_until_idle();
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle();
+
+        let color = self.color.panel_byte(BLACK_BIT);
+        let half_len = (HALF_ROW_LEN as u32) * HEIGHT;
+
+        self.command(spi, Command::WriteRam)?;
+        self.interface.data_x_times(spi, color, half_len)?;
+        self.command(spi, Command::SlaveWriteRam)?;
+        self.interface.data_x_times(spi, color, half_len)
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        Err(DisplayError::Unsupported)
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd5in79<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockDelay, Recorder};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 792);
+        assert_eq!(HEIGHT, 272);
+        assert_eq!(ROW_LEN, 99);
+        assert_eq!(SLAVE_START, 49);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+    }
+
+    #[test]
+    fn init_commands() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let _epd = Epd5in79::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            mock.commands(),
+            [0x12, 0x11, 0x44, 0x45, 0x3C, 0x18, 0x4E, 0x4F, 0x91, 0xC4, 0xC5, 0xCE, 0xCF]
+        );
+        assert_eq!(mock.data_after(0x44).unwrap(), [0x00, 0x31]);
+        assert_eq!(mock.data_after(0x45).unwrap(), [0x0F, 0x01, 0x00, 0x00]);
+        assert_eq!(mock.data_after(0xC4).unwrap(), [0x31, 0x00]);
+    }
+
+    #[test]
+    fn frame_is_split_between_controllers() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd5in79<_, _, _, _, _, MockDelay> =
+            Epd5in79::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        // number each byte of a row by its column
+        let mut buffer = [0u8; ROW_LEN * HEIGHT as usize];
+        for row in buffer.chunks_mut(ROW_LEN) {
+            for (column, byte) in row.iter_mut().enumerate() {
+                *byte = column as u8;
+            }
+        }
+        epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+
+        let master = mock.data_after(0x24).unwrap();
+        let slave = mock.data_after(0xA4).unwrap();
+        assert_eq!(master.len(), HALF_ROW_LEN * HEIGHT as usize);
+        assert_eq!(slave.len(), HALF_ROW_LEN * HEIGHT as usize);
+        assert_eq!(master[..HALF_ROW_LEN], buffer[..HALF_ROW_LEN]);
+        // the byte in the middle is sent to both
+        assert_eq!(slave[0], 49);
+        assert_eq!(slave[HALF_ROW_LEN - 1], 98);
+    }
+
+    #[test]
+    fn short_frame_is_refused() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd5in79<_, _, _, _, _, MockDelay> =
+            Epd5in79::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        let buffer = [0xFF; ROW_LEN * HEIGHT as usize - 1];
+        assert_eq!(
+            epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay()),
+            Err(DisplayError::InvalidData)
+        );
+        assert_eq!(
+            epd.update_frame(&mut mock.spi(), &buffer[..30], &mut mock.delay()),
+            Err(DisplayError::InvalidData)
+        );
+        assert_eq!(mock.events(), []);
+    }
+}
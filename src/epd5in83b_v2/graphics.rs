@@ -1,6 +1,6 @@
-use crate::color::TriColor;
+use crate::color::{ChromaticColor, TriColor};
 use crate::epd5in83b_v2::{DEFAULT_BACKGROUND_COLOR, HEIGHT, NUM_DISPLAY_BITS, WIDTH};
-use crate::graphics::{DisplayColorRendering, DisplayRotation};
+use crate::graphics::{rotated_size, DisplayColorRendering, DisplayRotation};
 use crate::prelude::TriDisplay;
 use embedded_graphics_core::prelude::*;
 
@@ -11,6 +11,7 @@ use embedded_graphics_core::prelude::*;
 pub struct Display5in83 {
     buffer: [u8; 2 * NUM_DISPLAY_BITS as usize],
     rotation: DisplayRotation,
+    chromatic: ChromaticColor,
 }
 
 impl Default for Display5in83 {
@@ -18,6 +19,7 @@ impl Default for Display5in83 {
         let mut display = Display5in83 {
             buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 2 * NUM_DISPLAY_BITS as usize],
             rotation: DisplayRotation::default(),
+            chromatic: ChromaticColor::default(),
         };
         // We need to invert chromatic part to black so it will be render white
         let offset = display.chromatic_offset();
@@ -43,7 +45,7 @@ impl DrawTarget for Display5in83 {
 
 impl OriginDimensions for Display5in83 {
     fn size(&self) -> Size {
-        Size::new(WIDTH, HEIGHT)
+        rotated_size(WIDTH, HEIGHT, self.rotation)
     }
 }
 
@@ -64,6 +66,14 @@ impl TriDisplay for Display5in83 {
         self.rotation
     }
 
+    fn set_chromatic_color(&mut self, chromatic: ChromaticColor) {
+        self.chromatic = chromatic;
+    }
+
+    fn chromatic_color(&self) -> ChromaticColor {
+        self.chromatic
+    }
+
     fn chromatic_offset(&self) -> usize {
         NUM_DISPLAY_BITS as usize
     }
@@ -188,4 +198,22 @@ mod tests {
             );
         }
     }
+
+    // pixel (0, 0) is the most significant bit of the first byte of both planes, black
+    // clears the bit of the b/w plane, with negative rendering: a set bit in the chromatic plane is chromatic
+    #[test]
+    fn graphics_origin_bit() {
+        use crate::color::TriColor;
+        use crate::graphics::TriDisplay;
+        use embedded_graphics::prelude::{Drawable, Pixel, Point};
+
+        let mut display = Display5in83::default();
+        let _ = Pixel(Point::new(0, 0), TriColor::Black).draw(&mut display);
+        assert_eq!(display.bw_buffer()[0] & 0x80, 0b0000_0000);
+        assert_eq!(display.chromatic_buffer()[0] & 0x80, 0b0000_0000);
+
+        let _ = Pixel(Point::new(0, 0), TriColor::Chromatic).draw(&mut display);
+        assert_eq!(display.bw_buffer()[0] & 0x80, 0b1000_0000);
+        assert_eq!(display.chromatic_buffer()[0] & 0x80, 0b1000_0000);
+    }
 }
@@ -0,0 +1,429 @@
+//! A simple Driver for the Waveshare 5.83" (G) 4 color E-Ink Display via SPI
+//!
+//! Unlike the tri-color 5.83" (B) panels with one plane per color, the G series takes
+//! black, white, yellow and red as two bits per pixel in a single RAM plane, see
+//! [QuadColor]. It uses the same controller and command set as the
+//! [2.36" (G)](crate::epd2in36g).
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/5.83inch_e-Paper_HAT_(G))
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_5in83g.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd5in83g.py)
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::color::QuadColor;
+use crate::interface::{DisplayInterface, InterfaceConfig};
+use crate::traits::{
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+
+#[cfg(feature = "graphics")]
+mod graphics;
+#[cfg(feature = "graphics")]
+pub use self::graphics::Display5in83g;
+
+/// Width of the display
+pub const WIDTH: u32 = 648;
+/// Height of the display
+pub const HEIGHT: u32 = 480;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+const IS_BUSY_LOW: bool = true;
+// No `BLACK_BIT` here: the frame memory takes a 2 bit color index per pixel,
+// with 0b00 for black, see [QuadColor]
+
+/// Computes the needed buffer length for 2 bits per pixel. Takes care of rounding up
+/// in case width is not divisible by 4.
+pub const fn buffer_len(width: usize, height: usize) -> usize {
+    width.div_ceil(4) * height
+}
+
+/// Epd5in83g driver
+///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
+pub struct Epd5in83g<SPI, CS, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: QuadColor,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd5in83g<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        // Reset the device
+        self.interface.reset(delay, 2);
+
+        self.cmd_with_data(spi, Command::Undocumented66, &[0x49, 0x55, 0x13, 0x5D])?;
+        self.cmd_with_data(spi, Command::Undocumented66, &[0x49, 0x55])?;
+        self.cmd_with_data(spi, Command::UndocumentedB0, &[0x03])?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x4F, 0x69])?;
+        self.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x00])?;
+        self.cmd_with_data(
+            spi,
+            Command::UndocumentedF0,
+            &[0xF6, 0x0D, 0x00, 0x00, 0x00],
+        )?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0xCF, 0xDE, 0x0F])?;
+        self.cmd_with_data(spi, Command::TemperatureSensor, &[0x00])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x30])?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x0C, 0x05])?;
+        self.send_resolution(spi)?;
+        self.cmd_with_data(spi, Command::Undocumented84, &[0x01])?;
+
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle();
+        self.interface.power_on_settle(delay);
+        self.interface.set_initialized();
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for Epd5in83g<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = QuadColor;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        Epd5in83g { interface, color }
+    }
+
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.start_transmission(spi)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
+        self.cmd_with_data(spi, Command::Undocumented68, &[0x00])?;
+        Ok(())
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x01])?;
+        self.wait_until_idle();
+        // the display is powered on again before the next transmission
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00])?;
+        self.wait_until_idle();
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        let color_value = self.color.get_byte_value();
+
+        self.start_transmission(spi)?;
+        self.interface.data_x_times(
+            spi,
+            color_value,
+            buffer_len(WIDTH as usize, HEIGHT as usize) as u32,
+        )?;
+        self.cmd_with_data(spi, Command::Undocumented68, &[0x00])?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        Err(DisplayError::Unsupported)
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd5in83g<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle(&mut self) {
+        self.interface.wait_until_idle(IS_BUSY_LOW);
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        let w = self.width();
+        let h = self.height();
+
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[(w >> 8) as u8, w as u8, (h >> 8) as u8, h as u8],
+        )
+    }
+
+    /// Powers the display on (it is powered off after every refresh) and starts the data transmission
+    fn start_transmission(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::Undocumented68, &[0x01])?;
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle();
+        self.command(spi, Command::DataStartTransmission)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Event, Recorder};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 648);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(buffer_len(WIDTH as usize, HEIGHT as usize), 77760);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+    }
+
+    #[test]
+    fn init_commands() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let _epd = Epd5in83g::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            mock.commands(),
+            [0x66, 0x66, 0xB0, 0x00, 0x03, 0xF0, 0x06, 0x41, 0x50, 0x60, 0x61, 0x84, 0x04]
+        );
+        assert_eq!(mock.data_after(0x61).unwrap(), [0x02, 0x88, 0x01, 0xE0]);
+    }
+
+    #[test]
+    fn clear_frame_packs_background() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd5in83g::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_background_color(QuadColor::Yellow);
+
+        epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+
+        let events = mock.events();
+        assert_eq!(
+            events[..4],
+            [
+                Event::Command(0x68),
+                Event::data(&[0x01]),
+                Event::Command(0x04),
+                Event::Command(0x10),
+            ]
+        );
+        assert_eq!(events[4], Event::data(&[0xAA; 77760]));
+    }
+
+    #[test]
+    fn clear_frame_palette() {
+        // two bits per pixel, four pixels of the same color in every byte
+        let expected = [
+            (QuadColor::Black, 0x00),
+            (QuadColor::White, 0x55),
+            (QuadColor::Yellow, 0xAA),
+            (QuadColor::Red, 0xFF),
+        ];
+        for (color, byte) in expected {
+            let mock = Recorder::new(IS_BUSY_LOW);
+            let mut epd =
+                Epd5in83g::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+            epd.assume_initialized();
+            epd.set_background_color(color);
+
+            epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+
+            let data = mock.data_after(0x10).unwrap();
+            assert_eq!(data.len(), buffer_len(WIDTH as usize, HEIGHT as usize));
+            assert!(data.iter().all(|&b| b == byte));
+        }
+    }
+
+    #[test]
+    fn update_frame_sends_single_plane() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd5in83g::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        let mut buffer = [0x55; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        buffer[0] = QuadColor::colors_byte(
+            QuadColor::Red,
+            QuadColor::Yellow,
+            QuadColor::White,
+            QuadColor::Black,
+        );
+        epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+
+        assert_eq!(mock.commands(), [0x68, 0x04, 0x10, 0x68]);
+        assert_eq!(mock.data_after(0x10).unwrap(), buffer);
+    }
+}
@@ -15,8 +15,11 @@ use embedded_hal::{
 };
 
 use crate::color::Color;
-use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::interface::{DisplayInterface, InterfaceConfig};
+use crate::traits::{
+    DisplayError, DisplayState, DriverOutputControl, InternalWiAdditions, RefreshLut,
+    WaveshareDisplay,
+};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -33,14 +36,22 @@ pub const HEIGHT: u32 = 528;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White; // Inverted for HD as compared to 7in5 v2 (HD: 0xFF = White)
 const IS_BUSY_LOW: bool = false;
+/// The gate setting of the Waveshare reference code, 0x2AF is sent as the last gate line
+/// instead of the height and the gates are scanned with TB set
+const DEFAULT_DRIVER_OUTPUT: DriverOutputControl = DriverOutputControl::new(0x2B0).scan(0x01);
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
 
 /// EPD7in5 (HD) driver
 ///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd7in5<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     /// Background Color
     color: Color,
+    /// Gate lines and scan direction
+    driver_output: DriverOutputControl,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -53,7 +64,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         // Reset the device
         self.interface.reset(delay, 2);
 
@@ -73,7 +84,8 @@ where
 
         self.cmd_with_data(spi, Command::SoftStart, &[0xAE, 0xC7, 0xC3, 0xC0, 0x40])?;
 
-        self.cmd_with_data(spi, Command::DriverOutputControl, &[0xAF, 0x02, 0x01])?;
+        let driver_output = self.driver_output.to_bytes();
+        self.cmd_with_data(spi, Command::DriverOutputControl, &driver_output)?;
 
         self.cmd_with_data(spi, Command::DataEntry, &[0x01])?;
 
@@ -84,6 +96,7 @@ where
 
         self.cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])?;
 
+        // loads the temperature and the LUT, nothing is displayed
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xB1])?;
 
         self.command(spi, Command::MasterActivation)?;
@@ -92,6 +105,7 @@ where
         self.cmd_with_data(spi, Command::SetRamXAc, &[0x00, 0x00])?;
         self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])?;
 
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -114,24 +128,87 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd7in5 { interface, color };
+        Epd7in5 {
+            interface,
+            color,
+            driver_output: DEFAULT_DRIVER_OUTPUT,
+        }
+    }
 
-        epd.init(spi, delay)?;
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
 
-        Ok(epd)
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::SwReset, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle();
         self.cmd_with_data(spi, Command::DeepSleep, &[0x01])?;
+        self.interface.set_sleeping();
         Ok(())
     }
 
@@ -140,27 +217,23 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         _delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle();
         self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])?;
-        self.cmd_with_data(spi, Command::WriteRamBw, buffer)?;
+        self.interface.cmd(spi, Command::WriteRamBw)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
         Ok(())
     }
 
-    fn update_partial_frame(
+    fn display_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
-    }
-
-    fn display_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.command(spi, Command::MasterActivation)?;
         self.wait_until_idle();
         Ok(())
@@ -171,15 +244,20 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
         self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         let pixel_count = WIDTH * HEIGHT / 8;
-        let background_color_byte = self.color.get_byte_value();
+        let background_color_byte = self.color.panel_byte(BLACK_BIT);
 
         self.wait_until_idle();
         self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])?;
@@ -191,8 +269,6 @@ where
         }
 
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])?;
-        self.command(spi, Command::MasterActivation)?;
-        self.wait_until_idle();
         Ok(())
     }
 
@@ -216,8 +292,8 @@ where
         &mut self,
         _spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        Err(DisplayError::Unsupported)
     }
 
     fn is_busy(&self) -> bool {
@@ -234,7 +310,7 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
@@ -243,10 +319,23 @@ where
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
+    /// Sets the number of gate lines and the scan direction, for panels with another height
+    ///
+    /// The setting is kept for the next [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_driver_output_control(
+        &mut self,
+        spi: &mut SPI,
+        control: DriverOutputControl,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.driver_output = control;
+        self.wait_until_idle();
+        self.cmd_with_data(spi, Command::DriverOutputControl, &control.to_bytes())
+    }
+
     fn wait_until_idle(&mut self) {
         self.interface.wait_until_idle(IS_BUSY_LOW)
     }
@@ -255,6 +344,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Event, Recorder};
 
     #[test]
     fn epd_size() {
@@ -262,4 +352,70 @@ mod tests {
         assert_eq!(HEIGHT, 528);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn init_commands() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let _epd = Epd7in5::new(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+        )
+        .unwrap();
+
+        assert_eq!(mock.events()[0], Event::Reset(true));
+        assert_eq!(
+            mock.commands(),
+            [0x12, 0x46, 0x47, 0x0C, 0x01, 0x11, 0x44, 0x45, 0x3C, 0x18, 0x22, 0x20, 0x4E, 0x4F]
+        );
+        // the x window covers the whole width
+        let x_window = mock.data_after(0x44).unwrap();
+        assert_eq!(
+            u16::from_le_bytes([x_window[2], x_window[3]]) as u32,
+            WIDTH - 1
+        );
+        assert_eq!(mock.data_after(0x22).unwrap(), [0xB1]);
+    }
+
+    #[test]
+    fn clear_frame_only_fills_ram() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd7in5<_, _, _, _, _, crate::mock::MockDelay> =
+            Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.commands(), [0x4F, 0x24, 0x26, 0x22]);
+
+        mock.clear();
+        epd.clear_and_display(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.commands(), [0x4F, 0x24, 0x26, 0x22, 0x20]);
+    }
+
+    #[test]
+    fn init_isnt_a_refresh() {
+        use crate::mock::MockDelay;
+
+        fn now_ms() -> u32 {
+            0
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd7in5<_, _, _, _, _, MockDelay> =
+            Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+
+        // the init activates the controller to load the LUT, that doesn't count
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(
+            epd.display_frame(&mut mock.spi(), &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+    }
 }
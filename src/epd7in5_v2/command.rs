@@ -66,7 +66,11 @@ pub(crate) enum Command {
     /// RED or NEW_DATA
     DataStartTransmission2 = 0x13,
 
-    /// Dual SPI - what for?
+    /// Dual SPI mode
+    ///
+    /// Lets the controller receive the image data over a second data line. The driver
+    /// always disables it (`0x00`), the SPI of `embedded-hal` only offers a single data
+    /// line and neither the full nor a partial frame upload interleave the planes for it.
     DualSpi = 0x15,
 
     /// This command builds the VCOM Look-Up Table (LUTC).
@@ -114,8 +118,8 @@ pub(crate) enum Command {
     /// This command defines alternative resolution and this setting is of higher priority
     /// than the RES\[1:0\] in R00H (PSR).
     TconResolution = 0x61,
-    /// This command defines MCU host direct access external memory mode.
-    SpiFlashControl = 0x65,
+    /// Sets the first source (HST) and the first gate (VST) which are driven.
+    GateSourceStart = 0x65,
 
     /// The LUT_REV / Chip Revision is read from OTP address = 25001 and 25000.
     Revision = 0x70,
@@ -149,5 +153,6 @@ mod tests {
     fn command_addr() {
         assert_eq!(Command::PanelSetting.address(), 0x00);
         assert_eq!(Command::DisplayRefresh.address(), 0x12);
+        assert_eq!(Command::GateSourceStart.address(), 0x65);
     }
 }
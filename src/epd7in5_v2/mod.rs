@@ -15,9 +15,14 @@ use embedded_hal::{
     digital::v2::{InputPin, OutputPin},
 };
 
+use crate::buffer_len;
 use crate::color::Color;
-use crate::interface::DisplayInterface;
-use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::interface::{DisplayInterface, InterfaceConfig};
+use crate::panel_setting::PanelSettingBuilder;
+use crate::rle::Packet;
+use crate::traits::{
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
 
 pub(crate) mod command;
 use self::command::Command;
@@ -34,14 +39,34 @@ pub const HEIGHT: u32 = 480;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 const IS_BUSY_LOW: bool = true;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = true;
+/// PanelSetting: KW mode, LUT from OTP, booster on, no soft reset
+const PANEL_SETTING: PanelSettingBuilder = PanelSettingBuilder::new().black_white(true);
 
 /// Epd7in5 (V2) driver
 ///
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
 pub struct Epd7in5<SPI, CS, BUSY, DC, RST, DELAY> {
     /// Connection Interface
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
     /// Background Color
     color: Color,
+    /// Resolution programmed into the controller
+    width: u32,
+    height: u32,
+    /// Gate scan direction, see [set_gate_scan_direction](Epd7in5::set_gate_scan_direction)
+    scan_up: bool,
+    /// Source shift direction, see [set_source_shift_direction](Epd7in5::set_source_shift_direction)
+    shift_right: bool,
+    /// First driven gate, see [set_first_gate](Epd7in5::set_first_gate)
+    first_gate: u16,
+    /// Whether sleep floats VCOM and the border, see [set_float_vcom_on_sleep](Epd7in5::set_float_vcom_on_sleep)
+    float_vcom_on_sleep: bool,
+    /// Voltages, see [set_power_setting](Epd7in5::set_power_setting)
+    power_setting: PowerSetting,
+    /// Whether waiting sends status reads, see [set_status_polling](Epd7in5::set_status_polling)
+    poll_status: bool,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
@@ -54,7 +79,75 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        let pending = self.start_power_on(spi, delay)?;
+        self.await_power_on(spi, delay, pending)
+    }
+}
+
+/// Voltages of the power setting register, see [set_power_setting](Epd7in5::set_power_setting)
+///
+/// The levels are the raw register values of the datasheet. The defaults are the values the
+/// driver always used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerSetting {
+    /// Internal generation of the border, VSH/VSL and VGH/VGL voltages (`0x07`: all internal)
+    pub internal: u8,
+    /// VCOM slew rate (bit 4) and VGH/VGL level (bits 0-2, `0x7`: ±20V)
+    pub vghl: u8,
+    /// VSH level, 2.4V + 0.2V per step (`0x3F`: 15V)
+    pub vsh: u8,
+    /// VSL level, -2.4V - 0.2V per step (`0x3F`: -15V)
+    pub vsl: u8,
+}
+
+impl PowerSetting {
+    fn bytes(&self) -> [u8; 4] {
+        [
+            self.internal & 0x0F,
+            self.vghl & 0x17,
+            self.vsh & 0x3F,
+            self.vsl & 0x3F,
+        ]
+    }
+}
+
+impl Default for PowerSetting {
+    fn default() -> Self {
+        PowerSetting {
+            internal: 0x07,
+            vghl: 0x17,
+            vsh: 0x3F,
+            vsl: 0x3F,
+        }
+    }
+}
+
+/// A power on started with [start_power_on](Epd7in5::start_power_on) which wasn't waited for yet
+#[must_use = "the init is only finished by await_power_on"]
+#[derive(Debug)]
+pub struct PowerOnPending(());
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> Epd7in5<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Runs the first half of the init up to powering on without waiting for the power on
+    ///
+    /// Powering on takes around 100ms, which can be used for other work. The init is finished
+    /// with [await_power_on](Epd7in5::await_power_on). Use it on a driver created with
+    /// [new_without_init](WaveshareDisplay::new_without_init), which refuses frames with
+    /// [DisplayError::NotInitialized] until then.
+    pub fn start_power_on(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<PowerOnPending, DisplayError<SPI::Error>> {
         // Reset the device
         self.interface.reset(delay, 2);
 
@@ -64,16 +157,31 @@ where
         // https://www.waveshare.com/w/upload/6/60/7.5inch_e-Paper_V2_Specification.pdf
 
         self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x27, 0x17])?;
-        self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x17, 0x3F, 0x3F])?;
+        self.cmd_with_data(spi, Command::PowerSetting, &self.power_setting.bytes())?;
         self.command(spi, Command::PowerOn)?;
+        Ok(PowerOnPending(()))
+    }
+
+    /// Waits for the power on started with [start_power_on](Epd7in5::start_power_on) and
+    /// finishes the init
+    pub fn await_power_on(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        _pending: PowerOnPending,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x1F])?;
+        self.interface.power_on_settle(delay);
+        self.send_panel_setting(spi)?;
         self.cmd_with_data(spi, Command::PllControl, &[0x06])?;
-        self.cmd_with_data(spi, Command::TconResolution, &[0x03, 0x20, 0x01, 0xE0])?;
+        self.send_resolution(spi)?;
+        // a single data line only, see Command::DualSpi
         self.cmd_with_data(spi, Command::DualSpi, &[0x00])?;
         self.cmd_with_data(spi, Command::TconSetting, &[0x22])?;
+        self.send_gate_source_start(spi)?;
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x10, 0x07])?;
         self.wait_until_idle(spi, delay)?;
+        self.interface.set_initialized();
         Ok(())
     }
 }
@@ -96,26 +204,100 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error> {
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         let interface = DisplayInterface::new(cs, busy, dc, rst);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd7in5 { interface, color };
+        Epd7in5 {
+            interface,
+            color,
+            width: WIDTH,
+            height: HEIGHT,
+            scan_up: true,
+            shift_right: true,
+            first_gate: 0,
+            float_vcom_on_sleep: true,
+            power_setting: PowerSetting::default(),
+            poll_status: false,
+        }
+    }
 
-        epd.init(spi, delay)?;
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
 
-        Ok(epd)
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.interface
+            .probe(spi, delay, Command::PowerOn, 2, IS_BUSY_LOW)
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.init(spi, delay)
     }
 
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
         self.wait_until_idle(spi, delay)?;
+        if self.float_vcom_on_sleep {
+            // border and VCOM floating, so no charge is left on the pixels
+            self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xF7])?;
+        }
         self.command(spi, Command::PowerOff)?;
         self.wait_until_idle(spi, delay)?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
         Ok(())
     }
 
@@ -124,25 +306,24 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle(spi, delay)?;
-        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
+        self.command(spi, Command::DataStartTransmission2)?;
+        let height = self.height;
+        for rows in self.interface.frame_rows(buffer, height) {
+            self.send_panel_data(spi, rows)?;
+        }
         Ok(())
     }
 
-    fn update_partial_frame(
+    fn display_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
-    }
-
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
         self.wait_until_idle(spi, delay)?;
         self.command(spi, Command::DisplayRefresh)?;
         Ok(())
@@ -153,23 +334,34 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.update_frame(spi, buffer, delay)?;
-        self.command(spi, Command::DisplayRefresh)?;
+        self.display_frame(spi, delay)?;
         Ok(())
     }
 
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
         self.wait_until_idle(spi, delay)?;
         self.send_resolution(spi)?;
 
         self.command(spi, Command::DataStartTransmission1)?;
-        self.interface.data_x_times(spi, 0x00, WIDTH * HEIGHT / 8)?;
+        self.interface.data_x_times(
+            spi,
+            self.color.panel_byte(BLACK_BIT),
+            self.width * self.height / 8,
+        )?;
 
         self.command(spi, Command::DataStartTransmission2)?;
-        self.interface.data_x_times(spi, 0x00, WIDTH * HEIGHT / 8)?;
-
-        self.command(spi, Command::DisplayRefresh)?;
+        self.interface.data_x_times(
+            spi,
+            self.color.panel_byte(BLACK_BIT),
+            self.width * self.height / 8,
+        )?;
         Ok(())
     }
 
@@ -182,19 +374,19 @@ where
     }
 
     fn width(&self) -> u32 {
-        WIDTH
+        self.width
     }
 
     fn height(&self) -> u32 {
-        HEIGHT
+        self.height
     }
 
     fn set_lut(
         &mut self,
         _spi: &mut SPI,
         _refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error> {
-        unimplemented!();
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        Err(DisplayError::Unsupported)
     }
 
     fn is_busy(&self) -> bool {
@@ -211,46 +403,225 @@ where
     RST: OutputPin,
     DELAY: DelayMs<u8>,
 {
-    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), SPI::Error> {
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd(spi, command)
     }
 
-    fn send_data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
-        self.interface.data(spi, data)
-    }
-
     fn cmd_with_data(
         &mut self,
         spi: &mut SPI,
         command: Command,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.interface.cmd_with_data(spi, command, data)
     }
 
-    fn wait_until_idle(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error> {
+    /// Sends a graphics buffer, which uses 0 for black, with the polarity of the panel
+    fn send_panel_data(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        if BLACK_BIT {
+            self.interface.data_inverted(spi, buffer)
+        } else {
+            self.interface.data(spi, buffer)
+        }
+    }
+
+    fn wait_until_idle(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        if !self.poll_status {
+            self.interface.wait_until_idle(IS_BUSY_LOW);
+            return Ok(());
+        }
         while self.interface.is_busy(IS_BUSY_LOW) {
             self.interface.cmd(spi, Command::GetStatus)?;
-            delay.delay_ms(20);
+            self.interface.busy_poll_delay(delay);
         }
         Ok(())
     }
 
-    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), SPI::Error> {
-        let w = self.width();
-        let h = self.height();
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        let resolution = tcon_resolution(self.width, self.height);
+        self.cmd_with_data(spi, Command::TconResolution, &resolution)
+    }
 
-        self.command(spi, Command::TconResolution)?;
-        self.send_data(spi, &[(w >> 8) as u8])?;
-        self.send_data(spi, &[w as u8])?;
-        self.send_data(spi, &[(h >> 8) as u8])?;
-        self.send_data(spi, &[h as u8])
+    /// Sets the resolution used by the controller, for panels with non-standard cuts
+    ///
+    /// The width needs to be a multiple of 8. The new size is also used by [`width`](WaveshareDisplay::width) and
+    /// [`height`](WaveshareDisplay::height) and for clearing the frame.
+    pub fn set_resolution(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        width: u32,
+        height: u32,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.width = width;
+        self.height = height;
+        self.wait_until_idle(spi, delay)?;
+        self.send_resolution(spi)
+    }
+
+    /// Sets the direction in which the gates are scanned, i.e. flips the frame vertically
+    ///
+    /// `scan_up` is the default and scans from the first gate to the last. Together with
+    /// [set_source_shift_direction](Epd7in5::set_source_shift_direction) the frame is rotated
+    /// by 180° in the controller, which is cheaper than rotating in software.
+    /// The setting is kept over [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_gate_scan_direction(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        scan_up: bool,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.scan_up = scan_up;
+        self.wait_until_idle(spi, delay)?;
+        self.send_panel_setting(spi)
+    }
+
+    /// Sets the direction in which the sources are shifted, i.e. flips the frame horizontally
+    ///
+    /// `shift_right` is the default and shifts from the first source to the last.
+    /// The setting is kept over [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_source_shift_direction(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        shift_right: bool,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.shift_right = shift_right;
+        self.wait_until_idle(spi, delay)?;
+        self.send_panel_setting(spi)
+    }
+
+    /// Sets the first gate which is driven, the frame starts on this row of the panel
+    ///
+    /// The gate is a 10 bit value, the default is 0. The setting is kept over
+    /// [wake_up](WaveshareDisplay::wake_up).
+    pub fn set_first_gate(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        gate: u16,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.first_gate = gate & 0x3FF;
+        self.wait_until_idle(spi, delay)?;
+        self.send_gate_source_start(spi)
+    }
+
+    /// Sets the voltages the panel is driven with, e.g. to tune the contrast or to lower them
+    ///
+    /// Values outside the bits of the register are masked off. The setting is kept over
+    /// [wake_up](WaveshareDisplay::wake_up). Check the datasheet, voltages out of the
+    /// specification of the panel can damage it.
+    pub fn set_power_setting(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        setting: PowerSetting,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.power_setting = setting;
+        self.wait_until_idle(spi, delay)?;
+        self.cmd_with_data(spi, Command::PowerSetting, &setting.bytes())
+    }
+
+    /// Sets whether waiting for the busy pin also reads the status in between (default: false)
+    ///
+    /// The busy pin of the controller follows its state by itself, so by default only the
+    /// pin is polled and nothing is sent while waiting. The Waveshare reference code reads
+    /// the status in between, turn this on if a panel only releases busy that way. The
+    /// interval is set with [InterfaceConfig::busy_poll_interval_ms].
+    pub fn set_status_polling(&mut self, poll_status: bool) {
+        self.poll_status = poll_status;
     }
+
+    /// Sets whether [sleep](WaveshareDisplay::sleep) floats VCOM and the border before
+    /// powering off (default: true)
+    ///
+    /// This is the power down sequence of the datasheet. A panel which keeps VCOM driven
+    /// while the analog part powers down can keep a residual image. Only turn it off if the
+    /// border has to keep its color. The init restores the VCOM setting, so the setting has
+    /// no effect until the next sleep.
+    pub fn set_float_vcom_on_sleep(&mut self, float_vcom: bool) {
+        self.float_vcom_on_sleep = float_vcom;
+    }
+
+    /// Sends a run-length encoded frame, decoding it on the fly
+    ///
+    /// See [rle](crate::rle) for the format, the decoded frame has the layout of the buffer
+    /// of [update_frame](WaveshareDisplay::update_frame). Runs are sent byte by byte, so no
+    /// decoded copy of the frame is needed, e.g. for images stored compressed in flash.
+    /// Fails with [DisplayError::InvalidData] before sending anything if the stream is
+    /// truncated or doesn't decode to a full frame.
+    pub fn update_frame_rle(
+        &mut self,
+        spi: &mut SPI,
+        rle: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        let frame_len = buffer_len(self.width as usize, self.height as usize);
+        if crate::rle::decoded_len(rle) != Some(frame_len) {
+            return Err(DisplayError::InvalidData);
+        }
+
+        self.wait_until_idle(spi, delay)?;
+        self.command(spi, Command::DataStartTransmission2)?;
+        for packet in crate::rle::packets(rle) {
+            match packet {
+                Ok(Packet::Literal(literal)) => self.send_panel_data(spi, literal)?,
+                Ok(Packet::Run(byte, count)) => {
+                    let byte = if BLACK_BIT { !byte } else { byte };
+                    self.interface.data_x_times(spi, byte, count)?;
+                }
+                Err(()) => return Err(DisplayError::InvalidData),
+            }
+        }
+        Ok(())
+    }
+
+    fn send_panel_setting(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        let setting = PANEL_SETTING
+            .scan_up(self.scan_up)
+            .shift_right(self.shift_right)
+            .build();
+        self.cmd_with_data(spi, Command::PanelSetting, &[setting])
+    }
+
+    fn send_gate_source_start(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        // the first source stays at 0
+        self.cmd_with_data(
+            spi,
+            Command::GateSourceStart,
+            &[
+                0x00,
+                0x00,
+                (self.first_gate >> 8) as u8,
+                self.first_gate as u8,
+            ],
+        )
+    }
+}
+
+/// TconResolution bytes: horizontal (HRES) and vertical (VRES) resolution, each big endian
+fn tcon_resolution(width: u32, height: u32) -> [u8; 4] {
+    [
+        (width >> 8) as u8,
+        width as u8,
+        (height >> 8) as u8,
+        height as u8,
+    ]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{Event, MockDelay, Recorder};
 
     #[test]
     fn epd_size() {
@@ -258,4 +629,358 @@ mod tests {
         assert_eq!(HEIGHT, 480);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn tcon_resolution_bytes() {
+        assert_eq!(tcon_resolution(WIDTH, HEIGHT), [0x03, 0x20, 0x01, 0xE0]);
+        assert_eq!(tcon_resolution(640, 384), [0x02, 0x80, 0x01, 0x80]);
+        assert_eq!(tcon_resolution(200, 96), [0x00, 0xC8, 0x00, 0x60]);
+    }
+
+    #[test]
+    fn set_resolution_writes_tcon() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.set_resolution(&mut mock.spi(), &mut mock.delay(), 640, 384)
+            .unwrap();
+        assert_eq!(
+            mock.events(),
+            [Event::Command(0x61), Event::data(&[0x02, 0x80, 0x01, 0x80])]
+        );
+        assert_eq!((epd.width(), epd.height()), (640, 384));
+    }
+
+    #[test]
+    fn vertical_flip_uses_the_set_resolution() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_interface_config(InterfaceConfig::new().vertical_flip(true));
+        epd.set_resolution(&mut mock.spi(), &mut mock.delay(), 16, 3)
+            .unwrap();
+        mock.clear();
+
+        // three rows of two bytes, sent from the bottom row up and inverted for the panel
+        let buffer = [0x10, 0x11, 0x20, 0x21, 0x30, 0x31];
+        epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        assert_eq!(
+            mock.data_after(0x13).unwrap(),
+            [!0x30, !0x31, !0x20, !0x21, !0x10, !0x11]
+        );
+    }
+
+    #[test]
+    fn black_bit_polarity() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        // black and white pixels of the graphics buffer are flipped for this panel
+        let buffer = [
+            Color::Black.get_byte_value(),
+            Color::White.get_byte_value(),
+            0x0F,
+        ];
+        epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.data_after(0x13).unwrap(), [0xFF, 0x00, 0xF0]);
+
+        for (color, byte) in [(Color::White, 0x00), (Color::Black, 0xFF)] {
+            mock.clear();
+            epd.set_background_color(color);
+            epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+            let data = mock.data_after(0x13).unwrap();
+            assert!(data.iter().all(|&b| b == byte));
+        }
+    }
+
+    #[test]
+    fn frame_is_sent_in_chunks_of_the_config() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let buffer = std::vec![0x00; buffer_len(WIDTH as usize, HEIGHT as usize)];
+
+        // the command and the frame in chunks of 256 bytes
+        epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.writes(), 1 + 48000_usize.div_ceil(256));
+
+        epd.set_interface_config(InterfaceConfig::new().chunk_size(64));
+        epd.update_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.writes(), 1 + 188 + 1 + 750);
+        assert!(mock.data_after(0x13).unwrap().iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn clear_frame_only_fills_ram() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd7in5<_, _, _, _, _, MockDelay> =
+            Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.commands(), [0x61, 0x10, 0x13]);
+
+        mock.clear();
+        epd.clear_and_display(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        let commands = mock.commands();
+        assert_eq!(commands[..3], [0x61, 0x10, 0x13]);
+        assert_eq!(commands.iter().filter(|&&c| c == 0x12).count(), 1);
+    }
+
+    #[test]
+    fn update_and_display_frame_keeps_the_refresh_interval() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static NOW_MS: AtomicU32 = AtomicU32::new(0);
+        fn now_ms() -> u32 {
+            NOW_MS.load(Ordering::SeqCst)
+        }
+
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd7in5<_, _, _, _, _, MockDelay> =
+            Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_min_refresh_interval_ms(1000, now_ms);
+        let buffer = std::vec![0xFF; crate::buffer_len(WIDTH as usize, HEIGHT as usize)];
+
+        epd.update_and_display_frame(&mut mock.spi(), &buffer, &mut mock.delay())
+            .unwrap();
+        assert!(mock.commands().contains(&0x12));
+
+        // the frame is still sent, but the refresh is refused
+        mock.clear();
+        NOW_MS.store(999, Ordering::SeqCst);
+        assert_eq!(
+            epd.update_and_display_frame(&mut mock.spi(), &buffer, &mut mock.delay()),
+            Err(DisplayError::RefreshTooSoon)
+        );
+        assert!(!mock.commands().contains(&0x12));
+    }
+
+    #[test]
+    fn partial_refresh_is_unsupported() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd7in5<_, _, _, _, _, MockDelay> =
+            Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        assert_eq!(
+            epd.update_partial_frame(&mut mock.spi(), &[0xFF; 2], 0, 0, 8, 2),
+            Err(DisplayError::Unsupported)
+        );
+        assert_eq!(
+            epd.set_lut(&mut mock.spi(), Some(RefreshLut::Quick)),
+            Err(DisplayError::Unsupported)
+        );
+        assert_eq!(epd.max_partial_refresh_hz(), 0);
+        assert_eq!(mock.events(), []);
+    }
+
+    #[test]
+    fn update_raw_passes_bytes_through() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: Epd7in5<_, _, _, _, _, MockDelay> =
+            Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(
+            epd.update_raw(&mut mock.spi(), 0x13, &[0x00]),
+            Err(DisplayError::NotInitialized)
+        );
+        epd.assume_initialized();
+
+        let plane = [0x00, 0xFF, 0x12, 0x34, 0xA5];
+        epd.update_raw(&mut mock.spi(), 0x13, &plane).unwrap();
+        assert_eq!(mock.events(), [Event::Command(0x13), Event::data(&plane)]);
+    }
+
+    #[test]
+    fn gate_scan_direction_and_first_gate() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        epd.set_gate_scan_direction(&mut mock.spi(), &mut mock.delay(), false)
+            .unwrap();
+        assert_eq!(mock.events(), [Event::Command(0x00), Event::data(&[0x17])]);
+
+        mock.clear();
+        epd.set_first_gate(&mut mock.spi(), &mut mock.delay(), 0x123)
+            .unwrap();
+        assert_eq!(
+            mock.events(),
+            [Event::Command(0x65), Event::data(&[0x00, 0x00, 0x01, 0x23])]
+        );
+
+        // both are sent again by the init
+        mock.clear();
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x00).unwrap(), [0x17]);
+        assert_eq!(mock.data_after(0x65).unwrap(), [0x00, 0x00, 0x01, 0x23]);
+
+        mock.clear();
+        epd.set_gate_scan_direction(&mut mock.spi(), &mut mock.delay(), true)
+            .unwrap();
+        assert_eq!(mock.data_after(0x00).unwrap(), [0x1F]);
+    }
+
+    #[test]
+    fn both_scan_directions_rotate_by_180_degrees() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        epd.set_source_shift_direction(&mut mock.spi(), &mut mock.delay(), false)
+            .unwrap();
+        assert_eq!(mock.events(), [Event::Command(0x00), Event::data(&[0x1B])]);
+
+        mock.clear();
+        epd.set_gate_scan_direction(&mut mock.spi(), &mut mock.delay(), false)
+            .unwrap();
+        assert_eq!(mock.data_after(0x00).unwrap(), [0x13]);
+
+        // both are sent again by the init
+        mock.clear();
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x00).unwrap(), [0x13]);
+    }
+
+    #[test]
+    fn power_setting_register() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        // the default is what the init always sent
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x01).unwrap(), [0x07, 0x17, 0x3F, 0x3F]);
+
+        mock.clear();
+        let setting = PowerSetting {
+            internal: 0x03,
+            vghl: 0x04,
+            vsh: 0x26,
+            vsl: 0xFF,
+        };
+        epd.set_power_setting(&mut mock.spi(), &mut mock.delay(), setting)
+            .unwrap();
+        assert_eq!(
+            mock.events(),
+            [Event::Command(0x01), Event::data(&[0x03, 0x04, 0x26, 0x3F])]
+        );
+
+        // and kept for the next init
+        mock.clear();
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.data_after(0x01).unwrap(), [0x03, 0x04, 0x26, 0x3F]);
+    }
+
+    #[test]
+    fn rle_frame_is_decoded_while_sending() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        epd.set_resolution(&mut mock.spi(), &mut mock.delay(), 16, 3)
+            .unwrap();
+
+        // two literal bytes, then a run of four
+        let rle = [0x01, 0x0F, 0xF0, 0x82, 0xFF];
+        mock.clear();
+        epd.update_frame_rle(&mut mock.spi(), &rle, &mut mock.delay())
+            .unwrap();
+        let sent = mock.data_after(0x13).unwrap();
+        assert_eq!(sent, [0xF0, 0x0F, 0x00, 0x00, 0x00, 0x00]);
+
+        // the same as sending the decoded frame
+        mock.clear();
+        epd.update_frame(
+            &mut mock.spi(),
+            &[0x0F, 0xF0, 0xFF, 0xFF, 0xFF, 0xFF],
+            &mut mock.delay(),
+        )
+        .unwrap();
+        assert_eq!(mock.data_after(0x13).unwrap(), sent);
+
+        // too short, too long and truncated streams are refused before sending
+        mock.clear();
+        for rle in [&[0x83, 0xFF][..], &[0x85, 0xFF], &[0x01, 0x0F, 0xF0, 0x82]] {
+            assert_eq!(
+                epd.update_frame_rle(&mut mock.spi(), rle, &mut mock.delay()),
+                Err(DisplayError::InvalidData)
+            );
+        }
+        assert!(mock.events().is_empty());
+    }
+
+    #[test]
+    fn waiting_only_polls_the_pin() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        mock.busy_for(3);
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        // only the refresh itself
+        assert_eq!(mock.events(), [Event::Command(0x12)]);
+
+        mock.clear();
+        epd.set_status_polling(true);
+        mock.busy_for(2);
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert_eq!(mock.commands(), [0x71, 0x71, 0x12]);
+    }
+
+    #[test]
+    fn sleep_sequence() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        epd.sleep(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(
+            mock.events(),
+            [
+                Event::Command(0x50),
+                Event::data(&[0xF7]),
+                Event::Command(0x02),
+                Event::Command(0x07),
+                Event::data(&[0xA5]),
+            ]
+        );
+
+        mock.clear();
+        epd.set_float_vcom_on_sleep(false);
+        epd.sleep(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(mock.commands(), [0x02, 0x07]);
+    }
+
+    #[test]
+    fn deferred_power_on_waits_later() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Epd7in5::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        mock.busy_for(2);
+        let pending = epd
+            .start_power_on(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        // nothing polled the busy pin yet
+        assert_eq!(mock.commands(), [0x06, 0x01, 0x04]);
+        assert_eq!(
+            epd.display_frame(&mut mock.spi(), &mut mock.delay()),
+            Err(DisplayError::NotInitialized)
+        );
+
+        mock.clear();
+        epd.await_power_on(&mut mock.spi(), &mut mock.delay(), pending)
+            .unwrap();
+        // waits for the busy pin, then the rest of the init
+        assert!(!epd.is_busy());
+        assert_eq!(mock.commands()[0], 0x00);
+        assert!(epd
+            .display_frame(&mut mock.spi(), &mut mock.delay())
+            .is_ok());
+    }
 }
@@ -0,0 +1,300 @@
+//! Packed frame buffers which don't need a driver or embedded-graphics
+//!
+//! A [Framebuffer] holds the pixels in the byte layout the panels take, so images can be
+//! prepared ahead of time, e.g. on the host in a build script, and the packed bytes shipped
+//! to the device and passed straight to `update_frame`.
+//!
+//! Each row starts at a new byte and the first pixel sits in the highest bits of a byte.
+//! With one bit per pixel this is the layout of [buffer_len](crate::buffer_len).
+//!
+//! ```
+//! use epd_waveshare::color::TriColor;
+//! use epd_waveshare::framebuffer::Framebuffer;
+//!
+//! let mut buffer = [0u8; Framebuffer::<TriColor>::buffer_len(16, 2)];
+//! let mut frame = Framebuffer::new(16, 2, &mut buffer);
+//! frame.fill(TriColor::White);
+//! frame.set_pixel(0, 0, TriColor::Chromatic);
+//! frame.set_pixel(1, 0, TriColor::Black);
+//! assert_eq!(frame.get_pixel(0, 0), Some(TriColor::Chromatic));
+//!
+//! // the b/w and the chromatic plane of a tri-color upload
+//! let mut bw = [0u8; 4];
+//! let mut chromatic = [0u8; 4];
+//! frame.plane(0, &mut bw);
+//! frame.plane(1, &mut chromatic);
+//! assert_eq!(bw, [0xBF, 0xFF, 0xFF, 0xFF]);
+//! assert_eq!(chromatic, [0x7F, 0xFF, 0xFF, 0xFF]);
+//! ```
+
+use crate::color::{Color, OctColor, QuadColor, TriColor};
+use core::marker::PhantomData;
+
+/// A color which is stored with a fixed number of bits per pixel
+pub trait PackedColor: Copy {
+    /// Bits per pixel, 1, 2 or 4
+    const BITS: usize;
+
+    /// Gets the bits of the color, only the lowest [BITS](Self::BITS) are used
+    fn to_bits(self) -> u8;
+
+    /// Takes the lowest [BITS](Self::BITS) bits and converts them to a color
+    fn from_bits(bits: u8) -> Self;
+}
+
+impl PackedColor for Color {
+    const BITS: usize = 1;
+
+    fn to_bits(self) -> u8 {
+        self.get_bit_value()
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        Color::from(bits & 0x01)
+    }
+}
+
+/// The low bit is the b/w plane and the high bit the chromatic plane, both with the
+/// [Color] encoding (cleared bit means black or chromatic).
+impl PackedColor for TriColor {
+    const BITS: usize = 2;
+
+    fn to_bits(self) -> u8 {
+        match self {
+            TriColor::White => 0b11,
+            TriColor::Black => 0b10,
+            TriColor::Chromatic => 0b01,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b11 => TriColor::White,
+            0b10 => TriColor::Black,
+            _ => TriColor::Chromatic,
+        }
+    }
+}
+
+impl PackedColor for QuadColor {
+    const BITS: usize = 2;
+
+    fn to_bits(self) -> u8 {
+        self.get_bits()
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        QuadColor::from_bits(bits)
+    }
+}
+
+/// Unused nibbles are read back as [OctColor::HiZ]
+impl PackedColor for OctColor {
+    const BITS: usize = 4;
+
+    fn to_bits(self) -> u8 {
+        self.get_nibble()
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        OctColor::from_nibble(bits & 0x07).unwrap_or(OctColor::HiZ)
+    }
+}
+
+/// A frame of `width` x `height` pixels packed into a borrowed buffer
+pub struct Framebuffer<'a, C> {
+    width: u32,
+    height: u32,
+    buffer: &'a mut [u8],
+    color: PhantomData<C>,
+}
+
+impl<'a, C: PackedColor> Framebuffer<'a, C> {
+    /// Bytes needed for a frame of `width` x `height` pixels
+    pub const fn buffer_len(width: u32, height: u32) -> usize {
+        Self::row_len(width) * height as usize
+    }
+
+    const fn row_len(width: u32) -> usize {
+        (width as usize * C::BITS).div_ceil(8)
+    }
+
+    /// Wraps `buffer`, which must be at least [buffer_len](Self::buffer_len) bytes long
+    ///
+    /// The content of the buffer is kept, so already packed bytes can be read back with
+    /// [get_pixel](Self::get_pixel). Use [fill](Self::fill) to start from a blank frame.
+    pub fn new(width: u32, height: u32, buffer: &'a mut [u8]) -> Self {
+        assert!(buffer.len() >= Self::buffer_len(width, height));
+        Framebuffer {
+            width,
+            height,
+            buffer,
+            color: PhantomData,
+        }
+    }
+
+    /// Width of the frame in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the frame in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the byte index and the shift of the pixel, or `None` outside of the frame
+    fn position(&self, x: u32, y: u32) -> Option<(usize, usize)> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let bit = x as usize * C::BITS;
+        let index = y as usize * Self::row_len(self.width) + bit / 8;
+        Some((index, 8 - C::BITS - bit % 8))
+    }
+
+    /// Sets a pixel, pixels outside of the frame are ignored
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: C) {
+        if let Some((index, shift)) = self.position(x, y) {
+            let mask = ((1u16 << C::BITS) - 1) as u8;
+            let byte = &mut self.buffer[index];
+            *byte = (*byte & !(mask << shift)) | ((color.to_bits() & mask) << shift);
+        }
+    }
+
+    /// Gets a pixel, or `None` outside of the frame
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<C> {
+        let (index, shift) = self.position(x, y)?;
+        Some(C::from_bits(self.buffer[index] >> shift))
+    }
+
+    /// Sets every pixel of the frame to `color`
+    pub fn fill(&mut self, color: C) {
+        let mut byte = 0;
+        for _ in 0..8 / C::BITS {
+            byte = (byte << C::BITS) | color.to_bits();
+        }
+        let len = Self::buffer_len(self.width, self.height);
+        self.buffer[..len].fill(byte);
+    }
+
+    /// The packed bytes of the frame
+    pub fn bytes(&self) -> &[u8] {
+        &self.buffer[..Self::buffer_len(self.width, self.height)]
+    }
+
+    /// Writes bit `bit` of every pixel into `out` as a plane with one bit per pixel
+    ///
+    /// E.g. the b/w and the chromatic plane of a tri-color panel or the two planes of a
+    /// grayscale upload. `out` has the layout of [buffer_len](crate::buffer_len) and must be
+    /// at least that long. Unused bits at the end of a row are set.
+    pub fn plane(&self, bit: usize, out: &mut [u8]) {
+        assert!(bit < C::BITS);
+        let row_len = (self.width as usize).div_ceil(8);
+        let out = &mut out[..row_len * self.height as usize];
+        out.fill(0xFF);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (index, shift) = self.position(x, y).unwrap();
+                if (self.buffer[index] >> (shift + bit)) & 0x01 == 0 {
+                    let out_index = y as usize * row_len + x as usize / 8;
+                    out[out_index] &= !(0x80 >> (x % 8));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<C: PackedColor + PartialEq + core::fmt::Debug>(colors: &[C]) {
+        let mut buffer = [0u8; 64];
+        let (width, height) = (11, 3);
+        assert!(Framebuffer::<C>::buffer_len(width, height) <= buffer.len());
+        let mut frame = Framebuffer::new(width, height, &mut buffer);
+
+        let color_at = |x: u32, y: u32| colors[(x + y * 3) as usize % colors.len()];
+        for y in 0..height {
+            for x in 0..width {
+                frame.set_pixel(x, y, color_at(x, y));
+            }
+        }
+        frame.set_pixel(width, 0, colors[0]);
+
+        // unpacking the exported bytes gives the same pixels back
+        let mut copy = [0u8; 64];
+        let len = frame.bytes().len();
+        copy[..len].copy_from_slice(frame.bytes());
+        let unpacked = Framebuffer::<C>::new(width, height, &mut copy);
+        for y in 0..height {
+            for x in 0..width {
+                assert_eq!(unpacked.get_pixel(x, y), Some(color_at(x, y)));
+            }
+        }
+        assert_eq!(unpacked.get_pixel(width, 0), None);
+        assert_eq!(unpacked.get_pixel(0, height), None);
+    }
+
+    #[test]
+    fn packing_round_trips() {
+        round_trip(&[Color::Black, Color::White, Color::White]);
+        round_trip(&[TriColor::Black, TriColor::White, TriColor::Chromatic]);
+        round_trip(&QuadColor::all());
+        round_trip(&OctColor::all());
+    }
+
+    #[test]
+    fn packing_matches_the_panel_layout() {
+        let mut buffer = [0u8; 2];
+        let mut frame = Framebuffer::new(4, 1, &mut buffer);
+        frame.fill(QuadColor::White);
+        frame.set_pixel(0, 0, QuadColor::Red);
+        frame.set_pixel(3, 0, QuadColor::Yellow);
+        let expected = QuadColor::colors_byte(
+            QuadColor::Red,
+            QuadColor::White,
+            QuadColor::White,
+            QuadColor::Yellow,
+        );
+        assert_eq!(frame.bytes(), [expected]);
+
+        let mut frame = Framebuffer::new(3, 1, &mut buffer);
+        frame.fill(OctColor::White);
+        frame.set_pixel(1, 0, OctColor::Orange);
+        assert_eq!(
+            frame.bytes(),
+            [
+                OctColor::colors_byte(OctColor::White, OctColor::Orange),
+                OctColor::colors_byte(OctColor::White, OctColor::White),
+            ]
+        );
+
+        let mut frame = Framebuffer::new(10, 1, &mut buffer);
+        frame.fill(Color::White);
+        frame.set_pixel(9, 0, Color::Black);
+        assert_eq!(frame.bytes(), [0xFF, 0xBF]);
+    }
+
+    #[test]
+    fn tri_color_planes_match_compose() {
+        let mut buffer = [0u8; Framebuffer::<TriColor>::buffer_len(12, 2)];
+        let mut frame = Framebuffer::new(12, 2, &mut buffer);
+        frame.fill(TriColor::White);
+        frame.set_pixel(0, 0, TriColor::Black);
+        frame.set_pixel(9, 1, TriColor::Chromatic);
+
+        let mut bw = [0u8; 4];
+        let mut chromatic = [0u8; 4];
+        frame.plane(0, &mut bw);
+        frame.plane(1, &mut chromatic);
+        assert_eq!(bw, [0x7F, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(chromatic, [0xFF, 0xFF, 0xFF, 0xBF]);
+
+        let mut black_layer = [0x7F, 0xFF, 0xFF, 0xBF];
+        let (bw_plane, chromatic_plane) = TriColor::compose(&mut black_layer, &chromatic).unwrap();
+        assert_eq!(bw_plane, bw);
+        assert_eq!(chromatic_plane, chromatic);
+    }
+}
@@ -0,0 +1,165 @@
+//! Golden image tests of the graphics layer
+//!
+//! Renders the same scene into one display of every color model and compares the buffers
+//! byte by byte against the goldens next to this file. Any change of the packing or the
+//! rotation shows up here.
+//!
+//! After an intended change of the rendering, rewrite the goldens with
+//! `EPD_UPDATE_GOLDEN=1 cargo test golden` and check the new images, e.g. by dumping them
+//! with [write_pbm](crate::graphics::Display::write_pbm).
+
+extern crate std;
+
+use crate::color::{ChromaticColor, OctColor, QuadColor, TriColor};
+use crate::graphics::{Display, DisplayRotation, GrayDisplay, OctDisplay, QuadDisplay, TriDisplay};
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::{BinaryColor, Gray4},
+    prelude::*,
+    primitives::{Line, PrimitiveStyle, Rectangle},
+    text::{Baseline, Text},
+};
+use std::{format, fs, path::PathBuf};
+
+/// Rows of the scene, panels which are too large for a small golden only compare these
+const SCENE_ROWS: usize = 32;
+
+/// Draws text, lines and a filled rect into the top left 64x32 pixels of `display`
+fn scene<D, C>(display: &mut D, fg: C, accent: C)
+where
+    D: DrawTarget<Color = C>,
+    D::Error: core::fmt::Debug,
+    C: PixelColor,
+{
+    Rectangle::new(Point::zero(), Size::new(64, SCENE_ROWS as u32))
+        .into_styled(PrimitiveStyle::with_stroke(fg, 1))
+        .draw(display)
+        .unwrap();
+    Text::with_baseline(
+        "Ab 42",
+        Point::new(3, 3),
+        MonoTextStyle::new(&FONT_6X10, fg),
+        Baseline::Top,
+    )
+    .draw(display)
+    .unwrap();
+    Line::new(Point::new(2, 29), Point::new(61, 14))
+        .into_styled(PrimitiveStyle::with_stroke(fg, 2))
+        .draw(display)
+        .unwrap();
+    Rectangle::new(Point::new(40, 4), Size::new(19, 9))
+        .into_styled(PrimitiveStyle::with_fill(accent))
+        .draw(display)
+        .unwrap();
+}
+
+/// Compares `actual` with the golden `name`, or rewrites the golden if asked to
+fn check_golden(name: &str, actual: &[u8]) {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "src",
+        "golden",
+        &format!("{}.bin", name),
+    ]
+    .iter()
+    .collect();
+
+    if std::env::var_os("EPD_UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let golden = fs::read(&path).unwrap();
+    assert_eq!(golden.len(), actual.len(), "size of golden {}", name);
+    if let Some(index) = golden.iter().zip(actual).position(|(a, b)| a != b) {
+        panic!(
+            "golden {} differs first at byte {}: expected {:#04x}, got {:#04x}",
+            name, index, golden[index], actual[index]
+        );
+    }
+}
+
+/// Asserts that everything after the scene rows is still the background
+fn assert_background(rest: &[u8], background: u8) {
+    assert!(rest.iter().all(|&byte| byte == background));
+}
+
+#[test]
+fn golden_mono() {
+    use crate::epd1in54::Display1in54;
+
+    let mut display = Display1in54::default();
+    scene(&mut display, BinaryColor::On, BinaryColor::On);
+    check_golden("mono", display.buffer());
+}
+
+#[test]
+fn golden_mono_rotated() {
+    use crate::epd2in9::Display2in9;
+
+    let mut display = Display2in9::default();
+    display.set_rotation(DisplayRotation::Rotate90);
+    scene(&mut display, BinaryColor::On, BinaryColor::On);
+    check_golden("mono_rotate90", display.buffer());
+}
+
+#[test]
+fn golden_mono_is_sent_as_is() {
+    use crate::epd1in54::{Display1in54, Epd1in54};
+    use crate::mock::{MockDelay, Recorder};
+    use crate::traits::WaveshareDisplay;
+
+    let mut display = Display1in54::default();
+    scene(&mut display, BinaryColor::On, BinaryColor::On);
+
+    let mock = Recorder::new(false);
+    let mut epd: Epd1in54<_, _, _, _, _, MockDelay> =
+        Epd1in54::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+    epd.assume_initialized();
+    epd.update_frame(&mut mock.spi(), display.buffer(), &mut mock.delay())
+        .unwrap();
+    check_golden("mono", &mock.data_after(0x24).unwrap());
+}
+
+#[test]
+fn golden_tri() {
+    use crate::epd2in13bc::Display2in13bc;
+
+    let mut display = Display2in13bc::default();
+    display.set_chromatic_color(ChromaticColor::Red);
+    scene(&mut display, TriColor::Black, TriColor::Chromatic);
+    check_golden("tri", display.buffer());
+}
+
+#[test]
+fn golden_quad() {
+    use crate::epd2in36g::{Display2in36g, WIDTH};
+
+    let mut display = Display2in36g::default();
+    scene(&mut display, QuadColor::Black, QuadColor::Red);
+    let (scene_rows, rest) = display.buffer().split_at(WIDTH as usize / 4 * SCENE_ROWS);
+    check_golden("quad", scene_rows);
+    assert_background(rest, Display2in36g::default().buffer()[0]);
+}
+
+#[test]
+fn golden_oct() {
+    use crate::epd4in01f::{Display4in01f, WIDTH};
+
+    let mut display = Display4in01f::default();
+    scene(&mut display, OctColor::Black, OctColor::Orange);
+    let (scene_rows, rest) = display.buffer().split_at(WIDTH as usize / 2 * SCENE_ROWS);
+    check_golden("oct", scene_rows);
+    assert_background(rest, Display4in01f::default().buffer()[0]);
+}
+
+#[test]
+fn golden_gray() {
+    use crate::epd4in2::{Display4in2Gray, WIDTH};
+
+    let mut display = Display4in2Gray::default();
+    scene(&mut display, Gray4::BLACK, Gray4::new(8));
+    let (scene_rows, rest) = display.buffer().split_at(WIDTH as usize / 4 * SCENE_ROWS);
+    check_golden("gray", scene_rows);
+    assert_background(rest, Display4in2Gray::default().buffer()[0]);
+}
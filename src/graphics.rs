@@ -1,14 +1,36 @@
 //! Graphics Support for EPDs
+//!
+//! # Buffer layout
+//!
+//! All buffers are stored row by row without rotation, starting with the top left pixel,
+//! and every row starts on a new byte. Inside a byte the leftmost pixel is in the most
+//! significant bits:
+//!
+//! - [Display]: 1 bit per pixel, pixel (0, 0) is `0x80` of the first byte. A set bit is white,
+//!   a cleared bit black, independent of the polarity the panel uses in its RAM.
+//! - [TriDisplay]: a b/w plane like [Display] followed by the chromatic plane with the same
+//!   layout, see [DisplayColorRendering] for the meaning of its bits.
+//! - [QuadDisplay] and [GrayDisplay]: 2 bits per pixel, pixel (0, 0) are the bits `0xC0`.
+//! - [OctDisplay]: 4 bits per pixel, pixel (0, 0) is the upper nibble.
+//!
+//! Every driver has a test pinning the position of pixel (0, 0) in its buffer.
 
 use crate::buffer_len;
-use crate::color::{Color, OctColor, TriColor};
-use embedded_graphics_core::pixelcolor::BinaryColor;
+use crate::color::{ChromaticColor, Color, OctColor, QuadColor, TriColor, TriOverlay};
+use crate::traits::{DirectRam, DisplayError, RefreshLut, WaveshareDisplay};
+use embedded_graphics_core::pixelcolor::{BinaryColor, Gray4, GrayColor, Rgb888};
 use embedded_graphics_core::prelude::*;
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_hal::{
+    blocking::{delay::DelayMs, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
 
 /// Displayrotation
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub enum DisplayRotation {
     /// No rotation
+    #[default]
     Rotate0,
     /// Rotate by 90 degrees clockwise
     Rotate90,
@@ -18,12 +40,6 @@ pub enum DisplayRotation {
     Rotate270,
 }
 
-impl Default for DisplayRotation {
-    fn default() -> Self {
-        DisplayRotation::Rotate0
-    }
-}
-
 /// Display specific pixel output configuration
 ///
 /// Different chromatic displays differently treat the bits in chromatic color planes.
@@ -41,6 +57,43 @@ pub enum DisplayColorRendering {
     Negative,
 }
 
+/// Known images for bringing up a new panel, see e.g. [Display::draw_test_pattern]
+///
+/// Byte order and wiring mistakes are easy to spot on them: swapped bytes break up the
+/// checkerboard, and mirrored or rotated buffers move the bars and the gradient.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestPattern {
+    /// Squares with an edge of the given number of pixels, starting with black at the top left
+    Checkerboard(u32),
+    /// Vertical bars of all colors of the panel from left to right, starting with black
+    ColorBars,
+    /// Fades from black on the left to white on the right, ordered dithered where the panel
+    /// has no grays
+    Gradient,
+}
+
+impl TestPattern {
+    /// Picks the color of `point` in an area of `size` from `palette`
+    ///
+    /// `palette` starts with black and white, the other colors are only used by the bars.
+    fn pick<C: Copy>(self, point: Point, size: Size, palette: &[C]) -> C {
+        const BAYER: [[u32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+        let (x, y) = (point.x as u32, point.y as u32);
+
+        match self {
+            TestPattern::Checkerboard(edge) => {
+                let edge = edge.max(1);
+                palette[((x / edge + y / edge) % 2) as usize]
+            }
+            TestPattern::ColorBars => palette[(x * palette.len() as u32 / size.width) as usize],
+            TestPattern::Gradient => {
+                let level = x * 16 / size.width;
+                palette[(level > BAYER[(y % 4) as usize][(x % 4) as usize]) as usize]
+            }
+        }
+    }
+}
+
 /// Necessary traits for all displays to implement for drawing
 ///
 /// Adds support for:
@@ -55,6 +108,133 @@ pub trait Display: DrawTarget<Color = BinaryColor> {
         }
     }
 
+    /// Fills the buffer with `background_color`, e.g. `Display::default().with_background(..)`
+    fn with_background(mut self, background_color: Color) -> Self
+    where
+        Self: Sized,
+    {
+        self.clear_buffer(background_color);
+        self
+    }
+
+    /// Draws a horizontal progress bar, e.g. for a battery level
+    ///
+    /// The left `fraction` of `area`, rounded to whole pixels, is filled with `fg` and the
+    /// rest with `bg`. `fraction` is clamped to `0.0..=1.0`.
+    fn draw_progress_bar(
+        &mut self,
+        area: Rectangle,
+        fraction: f32,
+        fg: BinaryColor,
+        bg: BinaryColor,
+    ) -> Result<(), Self::Error> {
+        let fraction = if fraction > 0.0 {
+            fraction.min(1.0)
+        } else {
+            0.0
+        };
+        let filled = (area.size.width as f32 * fraction + 0.5) as u32;
+
+        let done = Rectangle::new(area.top_left, Size::new(filled, area.size.height));
+        let rest = Rectangle::new(
+            area.top_left + Point::new(filled as i32, 0),
+            Size::new(area.size.width - filled, area.size.height),
+        );
+        self.fill_solid(&done, fg)?;
+        self.fill_solid(&rest, bg)
+    }
+
+    /// Draws `item` centered on the display and returns where its top left corner landed
+    ///
+    /// The item is centered by its bounding box, so text is centered with the metrics of its
+    /// font. The center follows the rotation of the display.
+    ///
+    /// ```
+    /// use embedded_graphics::{
+    ///     mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    ///     pixelcolor::BinaryColor,
+    ///     prelude::*,
+    ///     text::Text,
+    /// };
+    /// use epd_waveshare::{epd2in9::Display2in9, graphics::Display};
+    ///
+    /// let mut display = Display2in9::default();
+    /// let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+    /// let top_left = display
+    ///     .draw_centered(&Text::new("Ready", Point::zero(), style))
+    ///     .unwrap();
+    /// assert_eq!(top_left, Point::new((128 - 5 * 6) / 2, (296 - 10) / 2));
+    /// ```
+    fn draw_centered<T>(&mut self, item: &T) -> Result<Point, Self::Error>
+    where
+        Self: Sized,
+        T: Drawable<Color = BinaryColor> + Dimensions,
+    {
+        let area = self.bounding_box();
+        let item_area = item.bounding_box();
+        let top_left = area.top_left
+            + Point::new(
+                (area.size.width as i32 - item_area.size.width as i32) / 2,
+                (area.size.height as i32 - item_area.size.height as i32) / 2,
+            );
+        item.draw(&mut Translated {
+            target: self,
+            offset: top_left - item_area.top_left,
+        })?;
+        Ok(top_left)
+    }
+
+    /// Fills the whole display with a [TestPattern]
+    fn draw_test_pattern(&mut self, pattern: TestPattern) -> Result<(), Self::Error> {
+        let area = self.bounding_box();
+        let palette = [BinaryColor::On, BinaryColor::Off];
+        self.draw_iter(
+            area.points()
+                .map(|point| Pixel(point, pattern.pick(point, area.size, &palette))),
+        )
+    }
+
+    /// Moves the whole content `pixels` to the right, or to the left for negative values
+    ///
+    /// The pixels shifted out are dropped and the free edge is filled with `background`.
+    /// Shifts by single pixels, e.g. for a smooth ticker with partial refreshes. The
+    /// direction follows the rotation of the display.
+    fn scroll_horizontal(&mut self, pixels: i32, background: Color) {
+        let logical = self.bounding_box().size;
+        let rotation = self.rotation();
+        let size = rotated_size(logical.width, logical.height, rotation);
+        let fill = background.get_byte_value();
+        let buffer = self.get_mut_buffer();
+        let row_len = size.width.div_ceil(8) as usize;
+
+        match rotation {
+            DisplayRotation::Rotate0 => shift_row_bits(buffer, row_len, size.width, pixels, fill),
+            DisplayRotation::Rotate180 => {
+                shift_row_bits(buffer, row_len, size.width, -pixels, fill)
+            }
+            DisplayRotation::Rotate90 => shift_rows(buffer, row_len, pixels, fill),
+            DisplayRotation::Rotate270 => shift_rows(buffer, row_len, -pixels, fill),
+        }
+    }
+
+    /// Writes the buffer as binary PBM image, e.g. to compare it against a golden image in CI
+    ///
+    /// The image shows the buffer as it is sent to the panel, i.e. without the rotation.
+    #[cfg(any(feature = "std", test))]
+    fn write_pbm<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let size = rotated_size(
+            self.bounding_box().size.width,
+            self.bounding_box().size.height,
+            self.rotation(),
+        );
+        write!(writer, "P4\n{} {}\n", size.width, size.height)?;
+        // same layout, but a set bit is black in PBMs
+        for byte in self.buffer() {
+            writer.write_all(&[!byte])?;
+        }
+        Ok(())
+    }
+
     /// Returns the buffer
     fn buffer(&self) -> &[u8];
 
@@ -109,6 +289,14 @@ pub trait Display: DrawTarget<Color = BinaryColor> {
 /// - Drawing (With the help of DrawTarget/Embedded Graphics)
 /// - Rotations
 /// - Clearing
+///
+/// Every pixel is written to both planes, so the last color drawn at a point wins:
+/// [TriColor::Black] clears the bit in the b/w plane, [TriColor::Chromatic] sets the
+/// chromatic bit and whitens the b/w plane, and [TriColor::White] resets both. A chromatic
+/// pixel therefore always shows, even where the b/w plane was black before.
+///
+/// Content in other colors, e.g. `BinaryColor` fonts or `Rgb565` images, can be drawn
+/// through [convert_colors](TriDisplay::convert_colors).
 pub trait TriDisplay: DrawTarget<Color = TriColor> {
     /// Clears the buffer of the display with the chosen background color
     fn clear_buffer(&mut self, background_color: TriColor) {
@@ -117,6 +305,27 @@ pub trait TriDisplay: DrawTarget<Color = TriColor> {
         }
     }
 
+    /// Fills the buffer with `background_color`, e.g. `Display::default().with_background(..)`
+    fn with_background(mut self, background_color: TriColor) -> Self
+    where
+        Self: Sized,
+    {
+        self.clear_buffer(background_color);
+        self
+    }
+
+    /// Fills the whole display with a [TestPattern]
+    ///
+    /// The bars show black, white and the chromatic color.
+    fn draw_test_pattern(&mut self, pattern: TestPattern) -> Result<(), Self::Error> {
+        let area = self.bounding_box();
+        let palette = [TriColor::Black, TriColor::White, TriColor::Chromatic];
+        self.draw_iter(
+            area.points()
+                .map(|point| Pixel(point, pattern.pick(point, area.size, &palette))),
+        )
+    }
+
     /// Returns the buffer
     fn buffer(&self) -> &[u8];
 
@@ -138,6 +347,57 @@ pub trait TriDisplay: DrawTarget<Color = TriColor> {
     /// return the chromatic part of the buffer
     fn chromatic_buffer(&self) -> &[u8];
 
+    /// Sets whether the chromatic pixels of the panel are red or yellow
+    ///
+    /// Only changes how the colors are presented, e.g. in a simulator,
+    /// the buffer stays the same. Displays which don't store the color ignore it.
+    fn set_chromatic_color(&mut self, _chromatic: ChromaticColor) {}
+
+    /// Get the color of the chromatic pixels, [ChromaticColor::Red] for displays
+    /// which don't store it
+    fn chromatic_color(&self) -> ChromaticColor {
+        ChromaticColor::default()
+    }
+
+    /// Returns a [DrawTarget] for any color which converts into a [TriColor]
+    ///
+    /// `BinaryColor::On` is drawn black, RGB colors map to the nearest of black, white and
+    /// red or yellow, the latter two both become [TriColor::Chromatic]. The same as
+    /// `color_converted` of embedded-graphics, without depending on more than its core.
+    fn convert_colors<C>(&mut self) -> ColorConverted<'_, Self, C>
+    where
+        Self: Sized,
+        C: PixelColor + Into<TriColor>,
+    {
+        ColorConverted::new(self)
+    }
+
+    /// Returns a [DrawTarget] for overlays, [TriOverlay::TRANSPARENT] pixels aren't drawn
+    ///
+    /// ```
+    /// use embedded_graphics::{prelude::*, primitives::{PrimitiveStyle, Rectangle}};
+    /// use epd_waveshare::{color::*, epd2in13bc::Display2in13bc, graphics::TriDisplay};
+    ///
+    /// let mut display = Display2in13bc::default().with_background(TriColor::Black);
+    /// Rectangle::new(Point::new(0, 0), Size::new(8, 1))
+    ///     .into_styled(PrimitiveStyle::with_fill(TriOverlay::TRANSPARENT))
+    ///     .draw(&mut display.overlay())
+    ///     .unwrap();
+    /// assert_eq!(display.bw_buffer()[0], 0x00);
+    /// ```
+    fn overlay(&mut self) -> Overlay<'_, Self>
+    where
+        Self: Sized,
+    {
+        Overlay { target: self }
+    }
+
+    /// Returns how `color` looks on the panel
+    fn color_rgb(&self, color: TriColor) -> Rgb888 {
+        let (r, g, b) = color.rgb(self.chromatic_color());
+        Rgb888::new(r, g, b)
+    }
+
     /// Helperfunction for the Embedded Graphics draw trait
     ///
     /// Becomes uneccesary when const_generics become stablised
@@ -148,8 +408,8 @@ pub trait TriDisplay: DrawTarget<Color = TriColor> {
     /// * `height` - Screen height in pixels
     /// * `pixel` - Pixel to draw
     /// * `rendering` - Chooses rendering mode for the color plane,
-    ///  whether it is positive or negative. Check [DisplayColorRendering] for details.
-    ///  This is a hardware defined setting, that needs to be checked from the datasheet.
+    ///   whether it is positive or negative. Check [DisplayColorRendering] for details.
+    ///   This is a hardware defined setting, that needs to be checked from the datasheet.
     fn draw_helper_tri(
         &mut self,
         width: u32,
@@ -222,6 +482,139 @@ pub trait TriDisplay: DrawTarget<Color = TriColor> {
     }
 }
 
+/// A [DrawTarget] converting every pixel with [Into] before it is drawn to `D`
+///
+/// ```
+/// use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::{PrimitiveStyle, Rectangle}};
+/// use epd_waveshare::{color::TriColor, epd2in13bc::Display2in13bc, graphics::TriDisplay};
+///
+/// let mut display = Display2in13bc::default();
+/// Rectangle::new(Point::new(0, 0), Size::new(8, 1))
+///     .into_styled(PrimitiveStyle::with_fill(Rgb565::RED))
+///     .draw(&mut display.convert_colors())
+///     .unwrap();
+/// assert_eq!(display.chromatic_buffer()[0], 0x00);
+/// ```
+pub struct ColorConverted<'a, D, C> {
+    target: &'a mut D,
+    color: core::marker::PhantomData<C>,
+}
+
+impl<'a, D, C> ColorConverted<'a, D, C>
+where
+    D: DrawTarget,
+    C: PixelColor + Into<D::Color>,
+{
+    /// Wraps `target`
+    pub fn new(target: &'a mut D) -> Self {
+        ColorConverted {
+            target,
+            color: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<D, C> DrawTarget for ColorConverted<'_, D, C>
+where
+    D: DrawTarget,
+    C: PixelColor + Into<D::Color>,
+{
+    type Color = C;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, color.into())),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.target.fill_solid(area, color.into())
+    }
+}
+
+impl<D: DrawTarget, C> Dimensions for ColorConverted<'_, D, C> {
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+/// A [DrawTarget] of [TriOverlay] pixels, only the pixels with a color are drawn to `D`
+///
+/// Returned by [TriDisplay::overlay].
+pub struct Overlay<'a, D> {
+    target: &'a mut D,
+}
+
+impl<D: TriDisplay> DrawTarget for Overlay<'_, D> {
+    type Color = TriOverlay;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .filter_map(|Pixel(point, TriOverlay(color))| Some(Pixel(point, color?))),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        match color.0 {
+            Some(color) => self.target.fill_solid(area, color),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<D: TriDisplay> Dimensions for Overlay<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+/// Draws into `target` with every pixel moved by `offset`
+struct Translated<'a, D> {
+    target: &'a mut D,
+    offset: Point,
+}
+
+impl<D: DrawTarget> DrawTarget for Translated<'_, D> {
+    type Color = D::Color;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let offset = self.offset;
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point + offset, color)),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = Rectangle::new(area.top_left + self.offset, area.size);
+        self.target.fill_solid(&area, color)
+    }
+}
+
+impl<D: DrawTarget> Dimensions for Translated<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        let area = self.target.bounding_box();
+        Rectangle::new(area.top_left - self.offset, area.size)
+    }
+}
+
 /// Necessary traits for all displays to implement for drawing
 ///
 /// Adds support for:
@@ -236,6 +629,35 @@ pub trait OctDisplay: DrawTarget<Color = OctColor> {
         }
     }
 
+    /// Fills the buffer with `background_color`, e.g. `Display::default().with_background(..)`
+    fn with_background(mut self, background_color: OctColor) -> Self
+    where
+        Self: Sized,
+    {
+        self.clear_buffer(background_color);
+        self
+    }
+
+    /// Fills the whole display with a [TestPattern]
+    ///
+    /// The bars show all colors but [OctColor::HiZ].
+    fn draw_test_pattern(&mut self, pattern: TestPattern) -> Result<(), Self::Error> {
+        let area = self.bounding_box();
+        let palette = [
+            OctColor::Black,
+            OctColor::White,
+            OctColor::Green,
+            OctColor::Blue,
+            OctColor::Red,
+            OctColor::Yellow,
+            OctColor::Orange,
+        ];
+        self.draw_iter(
+            area.points()
+                .map(|point| Pixel(point, pattern.pick(point, area.size, &palette))),
+        )
+    }
+
     /// Returns the buffer
     fn buffer(&self) -> &[u8];
 
@@ -281,126 +703,1966 @@ pub trait OctDisplay: DrawTarget<Color = OctColor> {
     }
 }
 
-/// A variable Display without a predefined buffer
-///
-/// The buffer can be created as following:
-/// buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH / 8 * HEIGHT]
-/// If WIDTH is not a multiple of 8, don't forget to round it up (ie. (WIDTH + 7) / 8)
-///
-/// Example:
-/// ```rust,no_run
-/// # use epd_waveshare::epd2in9::DEFAULT_BACKGROUND_COLOR;
-/// # use epd_waveshare::prelude::*;
-/// # use epd_waveshare::graphics::VarDisplay;
-/// # use epd_waveshare::color::Black;
-/// # use embedded_graphics::prelude::*;
-/// # use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle};
-/// let width = 128;
-/// let height = 296;
+/// Packs an RGB image into the buffer format of the 7 color displays
 ///
-/// let mut buffer = [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 128 / 8 * 296];
-/// let mut display = VarDisplay::new(width, height, &mut buffer);
+/// Every pixel is mapped to the nearest of the 7 colors (HiZ isn't used) and two pixels
+/// are packed into each byte, the first one in the upper nibble. A missing last pixel is
+/// filled with white, pixels which don't fit into `out` are dropped.
 ///
-/// display.set_rotation(DisplayRotation::Rotate90);
+/// ```
+/// use embedded_graphics::pixelcolor::Rgb888;
+/// use epd_waveshare::{color::OctColor, graphics::pack_octcolor};
 ///
-/// let _ = Line::new(Point::new(0, 120), Point::new(0, 295))
-///         .into_styled(PrimitiveStyle::with_stroke(Black, 1))
-///         .draw(&mut display);
+/// let image = [Rgb888::new(250, 10, 0), Rgb888::new(0, 0, 90)];
+/// let mut buffer = [0u8; 1];
+/// pack_octcolor(&image, &mut buffer);
+/// assert_eq!(buffer[0], OctColor::colors_byte(OctColor::Red, OctColor::Black));
 /// ```
-pub struct VarDisplay<'a> {
-    width: u32,
-    height: u32,
-    rotation: DisplayRotation,
-    buffer: &'a mut [u8], //buffer: Box<u8>//[u8; 15000]
+pub fn pack_octcolor(rgb: &[Rgb888], out: &mut [u8]) {
+    pack_octcolor_with(rgb, out, |_, color| nearest_oct_color(color, 0));
 }
 
-impl<'a> VarDisplay<'a> {
-    /// Create a new variable sized display.
-    ///
-    /// Buffersize must be at least (width + 7) / 8 * height bytes.
-    pub fn new(width: u32, height: u32, buffer: &'a mut [u8]) -> VarDisplay<'a> {
-        let len = buffer.len() as u32;
-        assert!(buffer_len(width as usize, height as usize) >= len as usize);
-        VarDisplay {
-            width,
-            height,
-            rotation: DisplayRotation::default(),
-            buffer,
-        }
+/// Like [pack_octcolor], but with an ordered dither to approximate the colors in between
+///
+/// `width` is the width of the image in pixels, needed for the position of each pixel in
+/// the 4x4 dither pattern.
+pub fn pack_octcolor_dithered(rgb: &[Rgb888], width: usize, out: &mut [u8]) {
+    const BAYER: [[i32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+    pack_octcolor_with(rgb, out, |index, color| {
+        let (x, y) = (index % width.max(1), index / width.max(1));
+        // spread the threshold over +-120, about half the distance between two colors
+        let offset = (BAYER[y % 4][x % 4] * 2 + 1) * 8 - 128;
+        nearest_oct_color(color, offset)
+    });
+}
+
+fn pack_octcolor_with<F>(rgb: &[Rgb888], out: &mut [u8], mut map: F)
+where
+    F: FnMut(usize, Rgb888) -> OctColor,
+{
+    for (byte, (index, pair)) in out.iter_mut().zip(rgb.chunks(2).enumerate()) {
+        let first = map(index * 2, pair[0]);
+        let second = pair
+            .get(1)
+            .map_or(OctColor::White, |&color| map(index * 2 + 1, color));
+        *byte = OctColor::colors_byte(first, second);
     }
 }
 
-impl<'a> DrawTarget for VarDisplay<'a> {
-    type Color = BinaryColor;
-    type Error = core::convert::Infallible;
+/// Returns the nearest of the 7 displayable colors after shifting all channels by `offset`
+fn nearest_oct_color(color: Rgb888, offset: i32) -> OctColor {
+    let channel = |c: u8| (i32::from(c) + offset).clamp(0, 255);
+    let (r, g, b) = (channel(color.r()), channel(color.g()), channel(color.b()));
+
+    OctColor::all()
+        .iter()
+        .copied()
+        .filter(|&c| c != OctColor::HiZ)
+        .min_by_key(|c| {
+            let (cr, cg, cb) = c.rgb();
+            (i32::from(cr) - r).pow(2) + (i32::from(cg) - g).pow(2) + (i32::from(cb) - b).pow(2)
+        })
+        .unwrap_or(OctColor::White)
+}
 
-    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
-    where
-        I: IntoIterator<Item = Pixel<Self::Color>>,
-    {
-        for pixel in pixels {
-            self.draw_helper(self.width, self.height, pixel)?;
-        }
-        Ok(())
-    }
+/// Mean squared error of each channel between an image and its quantized colors
+///
+/// Lower is better, 0 means the colors are displayed exactly. The channels are compared
+/// in the 0-255 range, so the largest possible error is 65025.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QuantizationError {
+    /// Mean squared error of the red channel
+    pub red: u32,
+    /// Mean squared error of the green channel
+    pub green: u32,
+    /// Mean squared error of the blue channel
+    pub blue: u32,
 }
 
-impl<'a> OriginDimensions for VarDisplay<'a> {
-    fn size(&self) -> Size {
-        Size::new(self.width, self.height)
+impl QuantizationError {
+    /// Mean squared error over all channels
+    pub fn mean(&self) -> u32 {
+        (self.red + self.green + self.blue) / 3
     }
 }
 
-impl<'a> Display for VarDisplay<'a> {
-    fn buffer(&self) -> &[u8] {
-        self.buffer
+/// Computes the [QuantizationError] between `rgb` and the colors the display shows instead
+///
+/// `quantized` yields the displayed color of each pixel in the same order as `rgb`, e.g.
+/// for [TriColor]s:
+///
+/// ```
+/// use embedded_graphics::pixelcolor::Rgb888;
+/// use epd_waveshare::{color::{ChromaticColor, TriColor}, graphics::quantization_error};
+///
+/// let image = [Rgb888::new(255, 255, 255), Rgb888::new(255, 0, 16)];
+/// let colors = [TriColor::White, TriColor::Chromatic];
+/// let error = quantization_error(
+///     &image,
+///     colors.iter().map(|color| {
+///         let (r, g, b) = color.rgb(ChromaticColor::Red);
+///         Rgb888::new(r, g, b)
+///     }),
+/// );
+/// assert_eq!(error.blue, 128);
+/// ```
+///
+/// Pixels without a counterpart are ignored.
+pub fn quantization_error<I>(rgb: &[Rgb888], quantized: I) -> QuantizationError
+where
+    I: IntoIterator<Item = Rgb888>,
+{
+    let square = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2) as u64;
+
+    let (mut red, mut green, mut blue, mut count) = (0u64, 0u64, 0u64, 0u64);
+    for (original, shown) in rgb.iter().zip(quantized) {
+        red += square(original.r(), shown.r());
+        green += square(original.g(), shown.g());
+        blue += square(original.b(), shown.b());
+        count += 1;
     }
 
-    fn get_mut_buffer(&mut self) -> &mut [u8] {
-        self.buffer
+    let count = count.max(1);
+    QuantizationError {
+        red: (red / count) as u32,
+        green: (green / count) as u32,
+        blue: (blue / count) as u32,
     }
+}
 
-    fn set_rotation(&mut self, rotation: DisplayRotation) {
-        self.rotation = rotation;
-    }
+/// Computes the [QuantizationError] of a buffer packed with [pack_octcolor] or
+/// [pack_octcolor_dithered] against the original image
+pub fn octcolor_quantization_error(rgb: &[Rgb888], buffer: &[u8]) -> QuantizationError {
+    let shown = buffer
+        .iter()
+        .flat_map(|&byte| [byte >> 4, byte & 0x0F])
+        .map(|nibble| {
+            let (r, g, b) = OctColor::from_nibble(nibble).unwrap_or(OctColor::HiZ).rgb();
+            Rgb888::new(r, g, b)
+        });
+    quantization_error(rgb, shown)
+}
 
-    fn rotation(&self) -> DisplayRotation {
-        self.rotation
+/// Necessary traits for all displays to implement for drawing
+///
+/// Adds support for:
+/// - Drawing (With the help of DrawTarget/Embedded Graphics)
+/// - Rotations
+/// - Clearing
+pub trait QuadDisplay: DrawTarget<Color = QuadColor> {
+    /// Clears the buffer of the display with the chosen background color
+    fn clear_buffer(&mut self, background_color: QuadColor) {
+        for elem in self.get_mut_buffer().iter_mut() {
+            *elem = background_color.get_byte_value();
+        }
     }
-}
 
-// Checks if a pos is outside the defined display
-fn outside_display(p: Point, width: u32, height: u32, rotation: DisplayRotation) -> bool {
-    if p.x < 0 || p.y < 0 {
-        return true;
+    /// Fills the buffer with `background_color`, e.g. `Display::default().with_background(..)`
+    fn with_background(mut self, background_color: QuadColor) -> Self
+    where
+        Self: Sized,
+    {
+        self.clear_buffer(background_color);
+        self
     }
-    let (x, y) = (p.x as u32, p.y as u32);
-    match rotation {
-        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
-            if x >= width || y >= height {
-                return true;
-            }
-        }
-        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
-            if y >= width || x >= height {
-                return true;
-            }
-        }
+
+    /// Fills the whole display with a [TestPattern]
+    fn draw_test_pattern(&mut self, pattern: TestPattern) -> Result<(), Self::Error> {
+        let area = self.bounding_box();
+        let palette = [
+            QuadColor::Black,
+            QuadColor::White,
+            QuadColor::Yellow,
+            QuadColor::Red,
+        ];
+        self.draw_iter(
+            area.points()
+                .map(|point| Pixel(point, pattern.pick(point, area.size, &palette))),
+        )
     }
-    false
-}
 
-fn find_rotation(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u32) {
-    let nx;
-    let ny;
-    match rotation {
-        DisplayRotation::Rotate0 => {
-            nx = x;
-            ny = y;
-        }
-        DisplayRotation::Rotate90 => {
-            nx = width - 1 - y;
-            ny = x;
+    /// Returns the buffer
+    fn buffer(&self) -> &[u8];
+
+    /// Returns a mutable buffer
+    fn get_mut_buffer(&mut self) -> &mut [u8];
+
+    /// Sets the rotation of the display
+    fn set_rotation(&mut self, rotation: DisplayRotation);
+
+    /// Get the current rotation of the display
+    fn rotation(&self) -> DisplayRotation;
+
+    /// Helperfunction for the Embedded Graphics draw trait
+    ///
+    /// Becomes uneccesary when const_generics become stablised
+    fn draw_helper(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixel: Pixel<QuadColor>,
+    ) -> Result<(), Self::Error> {
+        let rotation = self.rotation();
+        let buffer = self.get_mut_buffer();
+
+        let Pixel(point, color) = pixel;
+        if outside_display(point, width, height, rotation) {
+            return Ok(());
+        }
+
+        // Give us index inside the buffer and the shift of the 2 bits in that u8 which need to be changed
+        let (index, shift) =
+            find_quad_position(point.x as u32, point.y as u32, width, height, rotation);
+        let index = index as usize;
+
+        buffer[index] = (buffer[index] & !(0x03 << shift)) | (color.get_bits() << shift);
+        Ok(())
+    }
+}
+
+/// Displays with 4 gray levels, drawn with 16 level [Gray4] colors
+///
+/// The buffer holds 2 bits per pixel with the first pixel in the highest bits, `0b11` is
+/// white and `0b00` black. Colors in between the 4 levels are ordered dithered, so
+/// antialiased edges (see [AntialiasedLine]) keep their smooth look.
+pub trait GrayDisplay: DrawTarget<Color = Gray4> {
+    /// Clears the buffer of the display with the chosen background color
+    fn clear_buffer(&mut self, background_color: Gray4) {
+        let level = (background_color.luma() * 3 + 7) / 15;
+        for elem in self.get_mut_buffer().iter_mut() {
+            *elem = level * 0x55;
+        }
+    }
+
+    /// Fills the buffer with `background_color`, e.g. `Display::default().with_background(..)`
+    fn with_background(mut self, background_color: Gray4) -> Self
+    where
+        Self: Sized,
+    {
+        self.clear_buffer(background_color);
+        self
+    }
+
+    /// Fills the whole display with a [TestPattern]
+    ///
+    /// The bars show the 4 levels of the panel, the gradient uses all 16 grays.
+    fn draw_test_pattern(&mut self, pattern: TestPattern) -> Result<(), Self::Error> {
+        let area = self.bounding_box();
+        let levels = [Gray4::BLACK, Gray4::new(5), Gray4::new(10), Gray4::WHITE];
+        self.draw_iter(area.points().map(|point| {
+            let color = match pattern {
+                TestPattern::Checkerboard(_) => {
+                    pattern.pick(point, area.size, &[Gray4::BLACK, Gray4::WHITE])
+                }
+                TestPattern::ColorBars => pattern.pick(point, area.size, &levels),
+                TestPattern::Gradient => Gray4::new((point.x as u32 * 16 / area.size.width) as u8),
+            };
+            Pixel(point, color)
+        }))
+    }
+
+    /// Exports the buffer with 1 bit per pixel for a black and white panel
+    ///
+    /// Pixels darker than `threshold` become black, the others white. `out` gets the layout
+    /// of [buffer_len](crate::buffer_len) for the unrotated size of the display and must be
+    /// at least that long. Unused bits at the end of a row are set.
+    fn to_1bpp(&self, threshold: Gray4, out: &mut [u8]) {
+        let size = rotated_size(
+            self.bounding_box().size.width,
+            self.bounding_box().size.height,
+            self.rotation(),
+        );
+        let row_len = size.width.div_ceil(8) as usize;
+        let out = &mut out[..row_len * size.height as usize];
+        out.fill(0xFF);
+        let quad_row_len = size.width.div_ceil(4) as usize;
+        for (y, row) in self.buffer().chunks(quad_row_len).enumerate() {
+            for x in 0..size.width as usize {
+                let level = (row[x / 4] >> (6 - 2 * (x % 4))) & 0x03;
+                if level * 5 < threshold.luma() {
+                    out[y * row_len + x / 8] &= !(0x80 >> (x % 8));
+                }
+            }
+        }
+    }
+
+    /// Exports the buffer with 2 bits per pixel for a panel with 4 gray levels
+    ///
+    /// This is the layout of the buffer itself, `out` must be at least as long.
+    fn to_2bpp(&self, out: &mut [u8]) {
+        let buffer = self.buffer();
+        out[..buffer.len()].copy_from_slice(buffer);
+    }
+
+    /// Writes the buffer as binary PGM image with the 4 levels of the panel, e.g. to compare it
+    /// against a golden image in CI
+    ///
+    /// The image shows the buffer as it is sent to the panel, i.e. without the rotation.
+    #[cfg(any(feature = "std", test))]
+    fn write_pgm<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let size = rotated_size(
+            self.bounding_box().size.width,
+            self.bounding_box().size.height,
+            self.rotation(),
+        );
+        write!(writer, "P5\n{} {}\n3\n", size.width, size.height)?;
+        let row_len = size.width.div_ceil(4) as usize;
+        for row in self.buffer().chunks(row_len) {
+            for x in 0..size.width as usize {
+                writer.write_all(&[(row[x / 4] >> (6 - 2 * (x % 4))) & 0x03])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the buffer
+    fn buffer(&self) -> &[u8];
+
+    /// Returns a mutable buffer
+    fn get_mut_buffer(&mut self) -> &mut [u8];
+
+    /// Sets the rotation of the display
+    fn set_rotation(&mut self, rotation: DisplayRotation);
+
+    /// Get the current rotation of the display
+    fn rotation(&self) -> DisplayRotation;
+
+    /// Helperfunction for the Embedded Graphics draw trait
+    ///
+    /// Becomes uneccesary when const_generics become stablised
+    fn draw_helper(
+        &mut self,
+        width: u32,
+        height: u32,
+        pixel: Pixel<Gray4>,
+    ) -> Result<(), Self::Error> {
+        let rotation = self.rotation();
+        let buffer = self.get_mut_buffer();
+
+        let Pixel(point, color) = pixel;
+        if outside_display(point, width, height, rotation) {
+            return Ok(());
+        }
+
+        let (x, y) = (point.x as u32, point.y as u32);
+        let (index, shift) = find_quad_position(x, y, width, height, rotation);
+        let index = index as usize;
+
+        buffer[index] =
+            (buffer[index] & !(0x03 << shift)) | (dithered_gray_level(color, x, y) << shift);
+        Ok(())
+    }
+}
+
+/// Quantises a 16 level gray to the 4 levels of the display with a 2x2 ordered dither
+fn dithered_gray_level(color: Gray4, x: u32, y: u32) -> u8 {
+    const BAYER: [[u16; 2]; 2] = [[0, 2], [3, 1]];
+    let threshold = BAYER[(y % 2) as usize][(x % 2) as usize] * 2 + 1;
+    let level = (u16::from(color.luma()) * 24 + threshold * 15) / 120;
+    level.min(3) as u8
+}
+
+/// A line with antialiased edges for [Gray4] draw targets like [GrayDisplay]s
+///
+/// The pixels along the edges of the line get a gray between `color` and white depending
+/// on how much of them is covered, so the line is meant to be drawn onto a white background.
+///
+/// ```rust
+/// # use epd_waveshare::graphics::AntialiasedLine;
+/// # use epd_waveshare::epd4in2::Display4in2Gray;
+/// # use embedded_graphics::{pixelcolor::Gray4, prelude::*};
+/// let mut display = Display4in2Gray::default();
+/// let _ = AntialiasedLine::new(Point::new(10, 10), Point::new(200, 90), 3, Gray4::BLACK)
+///     .draw(&mut display);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AntialiasedLine {
+    /// Center of the first end of the line
+    pub start: Point,
+    /// Center of the second end of the line
+    pub end: Point,
+    /// Width of the line in pixels
+    pub stroke_width: u32,
+    /// Color of the pixels fully covered by the line
+    pub color: Gray4,
+}
+
+impl AntialiasedLine {
+    /// Creates a new antialiased line
+    pub const fn new(start: Point, end: Point, stroke_width: u32, color: Gray4) -> Self {
+        AntialiasedLine {
+            start,
+            end,
+            stroke_width,
+            color,
+        }
+    }
+
+    /// Returns how much of the pixel at `point` is covered by the line, in 1/16 of a pixel
+    fn coverage(&self, point: Point) -> u32 {
+        // everything is in 1/16 of a pixel and measured from the pixel centers
+        let (ax, ay) = (i64::from(self.start.x) * 16, i64::from(self.start.y) * 16);
+        let (dx, dy) = (
+            i64::from(self.end.x) * 16 - ax,
+            i64::from(self.end.y) * 16 - ay,
+        );
+        let (px, py) = (i64::from(point.x) * 16 - ax, i64::from(point.y) * 16 - ay);
+
+        let length_sq = dx * dx + dy * dy;
+        let along = if length_sq == 0 {
+            0
+        } else {
+            (px * dx + py * dy).clamp(0, length_sq)
+        };
+        let (ox, oy) = if length_sq == 0 {
+            (px, py)
+        } else {
+            (px - dx * along / length_sq, py - dy * along / length_sq)
+        };
+        let distance = (ox * ox + oy * oy).unsigned_abs().isqrt() as i64;
+
+        (i64::from(self.stroke_width) * 8 + 8 - distance).clamp(0, 16) as u32
+    }
+}
+
+impl Drawable for AntialiasedLine {
+    type Color = Gray4;
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let margin = (self.stroke_width / 2 + 1) as i32;
+        let (x0, x1) = (
+            self.start.x.min(self.end.x) - margin,
+            self.start.x.max(self.end.x) + margin,
+        );
+        let (y0, y1) = (
+            self.start.y.min(self.end.y) - margin,
+            self.start.y.max(self.end.y) + margin,
+        );
+        let fade = 15 - u32::from(self.color.luma());
+
+        let pixels = (y0..=y1)
+            .flat_map(move |y| (x0..=x1).map(move |x| Point::new(x, y)))
+            .filter_map(|point| match self.coverage(point) {
+                0 => None,
+                coverage => {
+                    let luma = 15 - (fade * coverage + 8) / 16;
+                    Some(Pixel(point, Gray4::new(luma as u8)))
+                }
+            });
+        target.draw_iter(pixels)
+    }
+}
+
+/// A full screen 1 bit image, e.g. a splash screen shown right after the start
+///
+/// `data` has the layout of an `ImageRaw<BinaryColor>` of embedded-graphics: every row starts
+/// on a new byte, the leftmost pixel is the most significant bit and a set bit is
+/// [BinaryColor::On], i.e. black. Drawing converts it to the layout of the display buffer.
+///
+/// ```rust
+/// # use epd_waveshare::graphics::PanelImage;
+/// # use epd_waveshare::epd2in9::{Display2in9, WIDTH, HEIGHT};
+/// # use embedded_graphics::prelude::*;
+/// static SPLASH: [u8; epd_waveshare::buffer_len(WIDTH as usize, HEIGHT as usize)] =
+///     [0x00; epd_waveshare::buffer_len(WIDTH as usize, HEIGHT as usize)];
+///
+/// let mut display = Display2in9::default();
+/// let _ = PanelImage::new(&SPLASH, WIDTH).draw(&mut display);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PanelImage<'a> {
+    data: &'a [u8],
+    width: u32,
+}
+
+impl<'a> PanelImage<'a> {
+    /// Creates an image `width` pixels wide, the height follows from the length of `data`
+    pub const fn new(data: &'a [u8], width: u32) -> Self {
+        PanelImage { data, width }
+    }
+
+    fn bytes_per_row(&self) -> usize {
+        (self.width as usize).div_ceil(8)
+    }
+}
+
+impl OriginDimensions for PanelImage<'_> {
+    fn size(&self) -> Size {
+        let height = match self.bytes_per_row() {
+            0 => 0,
+            bytes => self.data.len() / bytes,
+        };
+        Size::new(self.width, height as u32)
+    }
+}
+
+impl Drawable for PanelImage<'_> {
+    type Color = BinaryColor;
+    type Output = ();
+
+    fn draw<D>(&self, target: &mut D) -> Result<Self::Output, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let bytes_per_row = self.bytes_per_row();
+        let colors = self.bounding_box().points().map(|point| {
+            let (x, y) = (point.x as usize, point.y as usize);
+            let byte = self.data[y * bytes_per_row + x / 8];
+            BinaryColor::from(byte & (0x80 >> (x % 8)) != 0)
+        });
+        target.fill_contiguous(&self.bounding_box(), colors)
+    }
+}
+
+/// Seven segment digits for clocks, rendered once and then copied into partial windows
+///
+/// The glyphs are stored in the layout of the display buffer, so [glyph](SevenSegment::glyph)
+/// can be sent as it is with [update_partial_frame](crate::prelude::WaveshareDisplay::update_partial_frame)
+/// to a window of the glyph size. [draw_digit](SevenSegment::draw_digit) copies a digit into
+/// a display buffer instead, e.g. for the next full refresh.
+///
+/// ```rust
+/// # use epd_waveshare::graphics::SevenSegment;
+/// # use epd_waveshare::epd2in9::Display2in9;
+/// # use embedded_graphics::prelude::*;
+/// let mut glyphs = [0u8; SevenSegment::glyphs_len(24, 40)];
+/// let digits = SevenSegment::new(&mut glyphs, 24, 40, 4).unwrap();
+///
+/// let mut display = Display2in9::default();
+/// let _ = digits.draw_digit(&mut display, 4, Point::new(8, 10));
+/// let _ = digits.draw_digit(&mut display, 2, Point::new(40, 10));
+/// ```
+pub struct SevenSegment<'a> {
+    glyphs: &'a mut [u8],
+    width: u32,
+    height: u32,
+}
+
+impl<'a> SevenSegment<'a> {
+    /// Segments of the digits 0 to 9, bit 0 is the top segment `a`, bit 6 the middle one `g`
+    const DIGITS: [u8; 10] = [0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F];
+
+    /// Bytes needed to store the 10 glyphs of `width` x `height` pixels
+    pub const fn glyphs_len(width: u32, height: u32) -> usize {
+        10 * buffer_len(width as usize, height as usize)
+    }
+
+    /// Renders the digits 0 to 9 with segments `thickness` pixels wide into `glyphs`
+    ///
+    /// `width` has to be a multiple of 8, so the glyphs fill whole bytes of a partial window,
+    /// and `glyphs` has to hold [glyphs_len](SevenSegment::glyphs_len) bytes, otherwise
+    /// `None` is returned.
+    pub fn new(glyphs: &'a mut [u8], width: u32, height: u32, thickness: u32) -> Option<Self> {
+        if width == 0
+            || height == 0
+            || !width.is_multiple_of(8)
+            || glyphs.len() < Self::glyphs_len(width, height)
+        {
+            return None;
+        }
+
+        let glyph_len = buffer_len(width as usize, height as usize);
+        let (w, h, t) = (width as i32, height as i32, thickness as i32);
+        let middle = h / 2;
+        let horizontal = Size::new((w - 2 * t).max(0) as u32, thickness);
+        let vertical = Size::new(thickness, (middle - t).max(0) as u32);
+        let segments = [
+            Rectangle::new(Point::new(t, 0), horizontal),
+            Rectangle::new(Point::new(w - t, t), vertical),
+            Rectangle::new(Point::new(w - t, middle), vertical),
+            Rectangle::new(Point::new(t, h - t), horizontal),
+            Rectangle::new(Point::new(0, middle), vertical),
+            Rectangle::new(Point::new(0, t), vertical),
+            Rectangle::new(Point::new(t, middle - t / 2), horizontal),
+        ];
+
+        for (segments_on, glyph) in Self::DIGITS.iter().zip(glyphs.chunks_mut(glyph_len)) {
+            let mut display = VarDisplay::new_cleared(width, height, glyph);
+            for (i, segment) in segments.iter().enumerate() {
+                if segments_on & (1 << i) != 0 {
+                    let _ = display.fill_solid(segment, BinaryColor::On);
+                }
+            }
+        }
+
+        Some(SevenSegment {
+            glyphs,
+            width,
+            height,
+        })
+    }
+
+    /// Returns the size of a glyph
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// Returns the glyph of `digit` in the layout of the display buffer, `None` if `digit`
+    /// is larger than 9
+    pub fn glyph(&self, digit: u8) -> Option<&[u8]> {
+        if digit >= 10 {
+            return None;
+        }
+        let glyph_len = buffer_len(self.width as usize, self.height as usize);
+        let start = digit as usize * glyph_len;
+        Some(&self.glyphs[start..start + glyph_len])
+    }
+
+    /// Copies the glyph of `digit` into `display` with its top left corner at `top_left`
+    ///
+    /// Nothing is drawn if `digit` is larger than 9.
+    pub fn draw_digit<D>(&self, display: &mut D, digit: u8, top_left: Point) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let glyph = match self.glyph(digit) {
+            Some(glyph) => glyph,
+            None => return Ok(()),
+        };
+        let bytes_per_row = self.width as usize / 8;
+        let area = Rectangle::new(top_left, self.size());
+        let colors = Rectangle::new(Point::zero(), self.size())
+            .points()
+            .map(|point| {
+                let (x, y) = (point.x as usize, point.y as usize);
+                let byte = glyph[y * bytes_per_row + x / 8];
+                BinaryColor::from(byte & (0x80 >> (x % 8)) == 0)
+            });
+        display.fill_contiguous(&area, colors)
+    }
+}
+
+/// A variable Display without a predefined buffer
+///
+/// The buffer can be created as following:
+/// buffer: [DEFAULT_BACKGROUND_COLOR.get_byte_value(); WIDTH / 8 * HEIGHT]
+/// If WIDTH is not a multiple of 8, don't forget to round it up (ie. (WIDTH + 7) / 8)
+///
+/// Example:
+/// ```rust,no_run
+/// # use epd_waveshare::epd2in9::DEFAULT_BACKGROUND_COLOR;
+/// # use epd_waveshare::prelude::*;
+/// # use epd_waveshare::graphics::VarDisplay;
+/// # use epd_waveshare::color::Black;
+/// # use embedded_graphics::prelude::*;
+/// # use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle};
+/// let width = 128;
+/// let height = 296;
+///
+/// let mut buffer = [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 128 / 8 * 296];
+/// let mut display = VarDisplay::new(width, height, &mut buffer);
+///
+/// display.set_rotation(DisplayRotation::Rotate90);
+///
+/// let _ = Line::new(Point::new(0, 120), Point::new(0, 295))
+///         .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+///         .draw(&mut display);
+/// ```
+pub struct VarDisplay<'a> {
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    buffer: &'a mut [u8], //buffer: Box<u8>//[u8; 15000]
+}
+
+impl<'a> VarDisplay<'a> {
+    /// Create a new variable sized display.
+    ///
+    /// Buffersize must be at least (width + 7) / 8 * height bytes.
+    /// The content of the buffer is kept, see [new_cleared](Self::new_cleared).
+    pub fn new(width: u32, height: u32, buffer: &'a mut [u8]) -> VarDisplay<'a> {
+        let len = buffer.len() as u32;
+        assert!(buffer_len(width as usize, height as usize) >= len as usize);
+        VarDisplay {
+            width,
+            height,
+            rotation: DisplayRotation::default(),
+            buffer,
+        }
+    }
+
+    /// Like [new](Self::new), but fills the buffer with white
+    ///
+    /// Use [with_background](Display::with_background) for another background color.
+    pub fn new_cleared(width: u32, height: u32, buffer: &'a mut [u8]) -> VarDisplay<'a> {
+        let mut display = Self::new(width, height, buffer);
+        display.clear_buffer(Color::White);
+        display
+    }
+}
+
+impl<'a> DrawTarget for VarDisplay<'a> {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(self.width, self.height, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> OriginDimensions for VarDisplay<'a> {
+    fn size(&self) -> Size {
+        rotated_size(self.width, self.height, self.rotation)
+    }
+}
+
+impl<'a> Display for VarDisplay<'a> {
+    fn buffer(&self) -> &[u8] {
+        self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
+/// A small off-screen display which can be copied into a bigger display
+///
+/// Useful for sprites or for redrawing a region before it is put on the main framebuffer.
+/// The buffer is laid out like the one of a [VarDisplay].
+///
+/// Example:
+/// ```rust
+/// # use epd_waveshare::color::{Black, Color};
+/// # use epd_waveshare::epd2in9::Display2in9;
+/// # use epd_waveshare::graphics::SubDisplay;
+/// # use epd_waveshare::prelude::*;
+/// # use embedded_graphics::prelude::*;
+/// # use embedded_graphics::primitives::{Circle, PrimitiveStyle};
+/// let mut display = Display2in9::default();
+///
+/// let mut sprite_buffer = [Color::White.get_byte_value(); 2 * 16];
+/// let mut sprite = SubDisplay::new(16, 16, &mut sprite_buffer);
+/// let _ = Circle::new(Point::new(0, 0), 16)
+///     .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+///     .draw(&mut sprite);
+///
+/// // x doesn't need to be a multiple of 8
+/// sprite.blit(&mut display, 13, 40);
+/// ```
+pub struct SubDisplay<'a> {
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+    buffer: &'a mut [u8],
+}
+
+impl<'a> SubDisplay<'a> {
+    /// Create a new off-screen display.
+    ///
+    /// Buffersize must be at least (width + 7) / 8 * height bytes.
+    /// The content of the buffer is kept, see [new_cleared](Self::new_cleared).
+    pub fn new(width: u32, height: u32, buffer: &'a mut [u8]) -> SubDisplay<'a> {
+        assert!(buffer.len() >= buffer_len(width as usize, height as usize));
+        SubDisplay {
+            width,
+            height,
+            rotation: DisplayRotation::default(),
+            buffer,
+        }
+    }
+
+    /// Like [new](Self::new), but fills the buffer with white
+    ///
+    /// Use [with_background](Display::with_background) for another background color.
+    pub fn new_cleared(width: u32, height: u32, buffer: &'a mut [u8]) -> SubDisplay<'a> {
+        let mut display = Self::new(width, height, buffer);
+        display.clear_buffer(Color::White);
+        display
+    }
+
+    /// Copies the content into `target` with its top left corner at (x, y)
+    ///
+    /// The position is given in the unrotated coordinates of `target`, the part
+    /// which doesn't fit into `target` is cut off.
+    pub fn blit<D: Display>(&self, target: &mut D, x: u32, y: u32) {
+        let target_width = match target.rotation() {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
+                target.bounding_box().size.width
+            }
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                target.bounding_box().size.height
+            }
+        };
+        let target_stride = target_width.div_ceil(8) as usize;
+        let target_rows = target.buffer().len() / target_stride;
+        let stride = self.width.div_ceil(8) as usize;
+        let shift = x % 8;
+        let dest = target.get_mut_buffer();
+
+        for row in 0..self.height as usize {
+            let target_row = y as usize + row;
+            if target_row >= target_rows {
+                break;
+            }
+            for column in 0..stride {
+                let target_column = (x / 8) as usize + column;
+                if target_column >= target_stride {
+                    break;
+                }
+                // only the bits inside the width of this display are copied
+                let valid = (self.width - column as u32 * 8).min(8);
+                let mask = 0xFFu8 << (8 - valid);
+                let bits = self.buffer[row * stride + column] & mask;
+
+                let index = target_row * target_stride + target_column;
+                dest[index] = (dest[index] & !(mask >> shift)) | (bits >> shift);
+                if shift != 0 && target_column + 1 < target_stride {
+                    let index = index + 1;
+                    dest[index] = (dest[index] & !(mask << (8 - shift))) | (bits << (8 - shift));
+                }
+            }
+        }
+    }
+}
+
+impl<'a> DrawTarget for SubDisplay<'a> {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_helper(self.width, self.height, pixel)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> OriginDimensions for SubDisplay<'a> {
+    fn size(&self) -> Size {
+        rotated_size(self.width, self.height, self.rotation)
+    }
+}
+
+impl<'a> Display for SubDisplay<'a> {
+    fn buffer(&self) -> &[u8] {
+        self.buffer
+    }
+
+    fn get_mut_buffer(&mut self) -> &mut [u8] {
+        self.buffer
+    }
+
+    fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+}
+
+/// Number of bytes of one row the [DirectDisplay] collects before writing them to the controller
+const DIRECT_RUN_LEN: usize = 16;
+
+/// Number of written bytes the [DirectDisplay] remembers to merge later draws into them
+pub const DIRECT_SHADOW_LEN: usize = 64;
+
+/// Draws straight into the RAM of the controller without a local framebuffer
+///
+/// Meant for extremely RAM-constrained MCUs. Every pixel is forwarded to the
+/// controller, collecting neighbouring pixels of the same row into one
+/// auto-incremented RAM write. This is a lot slower than drawing into a buffer.
+///
+/// The controller RAM can't be read back, so the pixels of a byte which aren't drawn
+/// are taken from the last [DIRECT_SHADOW_LEN] bytes written through this display.
+/// Neighbouring draws sharing a byte, e.g. the glyphs of a text, keep each other's pixels
+/// as long as the shared byte is among these.
+///
+/// # Limitations
+///
+/// Every other byte is completed with the background color: drawing a single pixel into
+/// a byte written before the last [DIRECT_SHADOW_LEN] ones resets the 7 other pixels of
+/// that byte to the background, even if they were drawn earlier. Only draws which cover
+/// whole bytes, or which don't share bytes with older draws, are safe. Writes to the RAM
+/// made without this display, e.g. by a `clear_frame`, aren't seen either. Use a
+/// [Display] buffer if earlier content has to be kept.
+///
+/// The displayed image still needs to be refreshed with
+/// [`WaveshareDisplay::display_frame`](crate::traits::WaveshareDisplay::display_frame).
+///
+/// Example:
+/// ```rust,no_run
+/// # use embedded_hal_mock::*;
+/// # fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+/// use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
+/// use epd_waveshare::{color::{Black, Color}, epd1in54::*, graphics::DirectDisplay, prelude::*};
+/// #
+/// # let expectations = [];
+/// # let mut spi = spi::Mock::new(&expectations);
+/// # let expectations = [];
+/// # let cs_pin = pin::Mock::new(&expectations);
+/// # let busy_in = pin::Mock::new(&expectations);
+/// # let dc = pin::Mock::new(&expectations);
+/// # let rst = pin::Mock::new(&expectations);
+/// # let mut delay = delay::MockNoop::new();
+///
+/// let mut epd = Epd1in54::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+/// epd.clear_frame(&mut spi, &mut delay)?;
+///
+/// let mut display = DirectDisplay::new(&mut epd, &mut spi, Color::White);
+/// display.set_rotation(DisplayRotation::Rotate90);
+/// Line::new(Point::new(0, 120), Point::new(0, 195))
+///     .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+///     .draw(&mut display)?;
+///
+/// epd.display_frame(&mut spi, &mut delay)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct DirectDisplay<'a, SPI, EPD> {
+    epd: &'a mut EPD,
+    spi: &'a mut SPI,
+    background_color: Color,
+    rotation: DisplayRotation,
+    /// byte column and row of the first byte in `run`
+    run_start: (u32, u32),
+    run_len: usize,
+    run: [u8; DIRECT_RUN_LEN],
+    /// byte column, row and value of the last written bytes
+    shadow: [(u16, u16, u8); DIRECT_SHADOW_LEN],
+    shadow_len: usize,
+    /// entry replaced next once `shadow` is full
+    shadow_next: usize,
+}
+
+impl<'a, SPI, EPD> DirectDisplay<'a, SPI, EPD>
+where
+    SPI: Write<u8>,
+    EPD: DirectRam<SPI>,
+{
+    /// Creates a new direct display.
+    ///
+    /// `background_color` is used for the pixels of a byte which weren't drawn and
+    /// aren't remembered, and should match the color the controller RAM was cleared with.
+    pub fn new(epd: &'a mut EPD, spi: &'a mut SPI, background_color: Color) -> Self {
+        DirectDisplay {
+            epd,
+            spi,
+            background_color,
+            rotation: DisplayRotation::default(),
+            run_start: (0, 0),
+            run_len: 0,
+            run: [0; DIRECT_RUN_LEN],
+            shadow: [(0, 0, 0); DIRECT_SHADOW_LEN],
+            shadow_len: 0,
+            shadow_next: 0,
+        }
+    }
+
+    /// Sets the rotation of the display
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.rotation = rotation;
+    }
+
+    /// Get the current rotation of the display
+    pub fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    fn draw_pixel(&mut self, pixel: Pixel<BinaryColor>) -> Result<(), DisplayError<SPI::Error>> {
+        let Pixel(point, color) = pixel;
+        if outside_display(point, EPD::RAM_WIDTH, EPD::RAM_HEIGHT, self.rotation) {
+            return Ok(());
+        }
+
+        let (x_byte, y, bit) = find_ram_address(
+            point.x as u32,
+            point.y as u32,
+            EPD::RAM_WIDTH,
+            EPD::RAM_HEIGHT,
+            self.rotation,
+        );
+
+        let (run_x, run_y) = self.run_start;
+        let in_run = self.run_len > 0 && y == run_y && x_byte >= run_x;
+        let offset = if in_run && x_byte < run_x + self.run_len as u32 {
+            (x_byte - run_x) as usize
+        } else if in_run && x_byte == run_x + self.run_len as u32 && self.run_len < DIRECT_RUN_LEN {
+            self.run[self.run_len] = self.written_byte(x_byte, y);
+            self.run_len += 1;
+            self.run_len - 1
+        } else {
+            self.flush()?;
+            self.run_start = (x_byte, y);
+            self.run[0] = self.written_byte(x_byte, y);
+            self.run_len = 1;
+            0
+        };
+
+        match color {
+            // Black
+            BinaryColor::On => self.run[offset] &= !bit,
+            // White
+            BinaryColor::Off => self.run[offset] |= bit,
+        }
+        Ok(())
+    }
+
+    /// Returns the last value written to the byte, or the background if it isn't remembered
+    fn written_byte(&self, x_byte: u32, y: u32) -> u8 {
+        self.shadow[..self.shadow_len]
+            .iter()
+            .find(|(x, row, _)| u32::from(*x) == x_byte && u32::from(*row) == y)
+            .map_or(self.background_color.get_byte_value(), |(_, _, value)| {
+                *value
+            })
+    }
+
+    fn remember(&mut self, x_byte: u32, y: u32, value: u8) {
+        let (x, row) = (x_byte as u16, y as u16);
+        if let Some(entry) = self.shadow[..self.shadow_len]
+            .iter_mut()
+            .find(|(ex, erow, _)| *ex == x && *erow == row)
+        {
+            entry.2 = value;
+        } else if self.shadow_len < DIRECT_SHADOW_LEN {
+            self.shadow[self.shadow_len] = (x, row, value);
+            self.shadow_len += 1;
+        } else {
+            self.shadow[self.shadow_next] = (x, row, value);
+            self.shadow_next = (self.shadow_next + 1) % DIRECT_SHADOW_LEN;
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), DisplayError<SPI::Error>> {
+        if self.run_len > 0 {
+            let (x_byte, y) = self.run_start;
+            self.epd
+                .write_ram(self.spi, x_byte, y, &self.run[..self.run_len])?;
+            let run = self.run;
+            for (i, value) in run[..self.run_len].iter().enumerate() {
+                self.remember(x_byte + i as u32, y, *value);
+            }
+            self.run_len = 0;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, SPI, EPD> DrawTarget for DirectDisplay<'a, SPI, EPD>
+where
+    SPI: Write<u8>,
+    EPD: DirectRam<SPI>,
+{
+    type Color = BinaryColor;
+    type Error = DisplayError<SPI::Error>;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for pixel in pixels {
+            self.draw_pixel(pixel)?;
+        }
+        self.flush()
+    }
+}
+
+impl<'a, SPI, EPD> OriginDimensions for DirectDisplay<'a, SPI, EPD>
+where
+    SPI: Write<u8>,
+    EPD: DirectRam<SPI>,
+{
+    fn size(&self) -> Size {
+        rotated_size(EPD::RAM_WIDTH, EPD::RAM_HEIGHT, self.rotation)
+    }
+}
+
+/// Maximum number of separate regions a [DirtyRegions] keeps track of
+pub const DIRTY_REGION_CAPACITY: usize = 8;
+
+/// Collects the changed areas of a black/white buffer and refreshes them together
+///
+/// Every partial refresh makes the panel flash, so instead of refreshing each small change
+/// on its own the regions are collected first. Regions which overlap or are at most
+/// `merge_distance` pixels apart are coalesced into their bounding box, far apart regions
+/// are kept separate so the pixels in between aren't sent. Once [DIRTY_REGION_CAPACITY]
+/// regions are tracked, a new one is merged into the region where this grows the area the least.
+///
+/// Regions are given in buffer coordinates, i.e. without the rotation of the display applied.
+///
+/// ```rust, no_run
+/// # use embedded_hal_mock::*;
+/// # fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+/// use embedded_graphics::{prelude::*, primitives::Rectangle};
+/// use epd_waveshare::{epd4in2::*, graphics::DirtyRegions, prelude::*};
+/// #
+/// # let expectations = [];
+/// # let mut spi = spi::Mock::new(&expectations);
+/// # let expectations = [];
+/// # let cs_pin = pin::Mock::new(&expectations);
+/// # let busy_in = pin::Mock::new(&expectations);
+/// # let dc = pin::Mock::new(&expectations);
+/// # let rst = pin::Mock::new(&expectations);
+/// # let mut delay = delay::MockNoop::new();
+///
+/// let mut epd = Epd4in2::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+/// let display = Display4in2::default();
+///
+/// let mut dirty = DirtyRegions::new(16);
+/// dirty.add(Rectangle::new(Point::new(8, 8), Size::new(16, 16)));
+/// dirty.add(Rectangle::new(Point::new(32, 8), Size::new(16, 16)));
+///
+/// // both regions end up in a single window and a single refresh
+/// let mut scratch = [0u8; 256];
+/// dirty.refresh(&mut epd, &mut spi, &mut delay, display.buffer(), WIDTH, &mut scratch)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DirtyRegions {
+    regions: [Rectangle; DIRTY_REGION_CAPACITY],
+    len: usize,
+    merge_distance: u32,
+}
+
+impl DirtyRegions {
+    /// Creates an empty collection which merges regions at most `merge_distance` pixels apart
+    pub const fn new(merge_distance: u32) -> Self {
+        DirtyRegions {
+            regions: [Rectangle::zero(); DIRTY_REGION_CAPACITY],
+            len: 0,
+            merge_distance,
+        }
+    }
+
+    /// Marks `area` as changed
+    pub fn add(&mut self, area: Rectangle) {
+        if area.is_zero_sized() {
+            return;
+        }
+
+        // merging can bring the result close to further regions, so repeat until nothing is near
+        let mut area = area;
+        while let Some(index) = self.regions().iter().position(|r| self.is_near(r, &area)) {
+            area = bounding_box(&self.regions[index], &area);
+            self.remove(index);
+        }
+
+        if self.len < DIRTY_REGION_CAPACITY {
+            self.regions[self.len] = area;
+            self.len += 1;
+        } else {
+            let index = (0..self.len)
+                .min_by_key(|&i| rect_area(&bounding_box(&self.regions[i], &area)))
+                .unwrap_or(0);
+            let merged = bounding_box(&self.regions[index], &area);
+            self.remove(index);
+            self.add(merged);
+        }
+    }
+
+    /// Returns the regions collected so far
+    pub fn regions(&self) -> &[Rectangle] {
+        &self.regions[..self.len]
+    }
+
+    /// Returns true if nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Forgets all regions
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Sends the collected regions with [WaveshareDisplay::update_partial_frame], refreshes
+    /// the display once and clears the regions afterwards
+    ///
+    /// `buffer` is the whole black/white frame, `width` its width in pixels. The regions are
+    /// widened to whole bytes and copied to the display through `scratch`, a region which
+    /// doesn't fit is sent in several bands of rows.
+    ///
+    /// Nothing is sent when no region changed, and nothing but a [DisplayError::InvalidData]
+    /// is returned if `scratch` can't hold a single row of the widest region.
+    pub fn refresh<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        buffer: &[u8],
+        width: u32,
+        scratch: &mut [u8],
+    ) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let row_len = buffer_len(width as usize, 1);
+        let height = (buffer.len() / row_len.max(1)) as u32;
+        let frame = Rectangle::new(Point::zero(), Size::new(width, height));
+        // partial windows start and end on whole bytes
+        let window_bytes = |area: &Rectangle| {
+            let x_byte = area.top_left.x as usize / 8;
+            let x_end = buffer_len(area.top_left.x as usize + area.size.width as usize, 1);
+            (x_byte, x_end - x_byte)
+        };
+
+        let widest = self
+            .regions()
+            .iter()
+            .map(|area| area.intersection(&frame))
+            .filter(|area| !area.is_zero_sized())
+            .map(|area| window_bytes(&area).1)
+            .max()
+            .unwrap_or(0);
+        if scratch.len() < widest {
+            return Err(DisplayError::InvalidData);
+        }
+
+        for area in self.regions() {
+            let area = area.intersection(&frame);
+            if area.is_zero_sized() {
+                continue;
+            }
+
+            let (x_byte, window_len) = window_bytes(&area);
+            let band_rows = scratch.len() / window_len;
+
+            let mut y = area.top_left.y as u32;
+            let y_end = y + area.size.height;
+            while y < y_end {
+                let rows = (band_rows as u32).min(y_end - y);
+                let data = &mut scratch[..window_len * rows as usize];
+                for (row, chunk) in data.chunks_mut(window_len).enumerate() {
+                    let start = (y as usize + row) * row_len + x_byte;
+                    chunk.copy_from_slice(&buffer[start..start + window_len]);
+                }
+                epd.update_partial_frame(
+                    spi,
+                    data,
+                    x_byte as u32 * 8,
+                    y,
+                    window_len as u32 * 8,
+                    rows,
+                )?;
+                y += rows;
+            }
+        }
+
+        self.clear();
+        epd.display_frame(spi, delay)
+    }
+
+    fn is_near(&self, a: &Rectangle, b: &Rectangle) -> bool {
+        let gap = |a_start: i32, a_len: u32, b_start: i32, b_len: u32| {
+            let a_end = a_start + a_len as i32;
+            let b_end = b_start + b_len as i32;
+            (b_start - a_end).max(a_start - b_end).max(0) as u32
+        };
+
+        gap(a.top_left.x, a.size.width, b.top_left.x, b.size.width) <= self.merge_distance
+            && gap(a.top_left.y, a.size.height, b.top_left.y, b.size.height) <= self.merge_distance
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.len -= 1;
+        self.regions[index] = self.regions[self.len];
+    }
+}
+
+/// A [Display] wrapper which remembers the area drawn to since the last flush
+///
+/// Draw into the wrapper like into the display itself, then call
+/// [flush_partial](TrackedDisplay::flush_partial) to send and refresh only the bounding box
+/// of everything drawn. Changes made through [inner_mut](TrackedDisplay::inner_mut) aren't
+/// tracked, mark them with [mark_dirty](TrackedDisplay::mark_dirty).
+///
+/// ```rust, no_run
+/// # use embedded_hal_mock::*;
+/// # fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+/// use embedded_graphics::{prelude::*, primitives::{Circle, PrimitiveStyle}};
+/// use epd_waveshare::{color::Black, epd4in2::*, graphics::TrackedDisplay, prelude::*};
+/// #
+/// # let expectations = [];
+/// # let mut spi = spi::Mock::new(&expectations);
+/// # let expectations = [];
+/// # let cs_pin = pin::Mock::new(&expectations);
+/// # let busy_in = pin::Mock::new(&expectations);
+/// # let dc = pin::Mock::new(&expectations);
+/// # let rst = pin::Mock::new(&expectations);
+/// # let mut delay = delay::MockNoop::new();
+///
+/// let mut epd = Epd4in2::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+/// let mut display = TrackedDisplay::new(Display4in2::default());
+///
+/// let _ = Circle::new(Point::new(100, 100), 20)
+///     .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+///     .draw(&mut display);
+///
+/// // only the 24x20 window around the circle is sent
+/// let mut scratch = [0u8; 256];
+/// display.flush_partial(&mut epd, &mut spi, &mut delay, &mut scratch)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TrackedDisplay<D> {
+    display: D,
+    /// Top left and bottom right pixel drawn to, in display coordinates
+    dirty: Option<(Point, Point)>,
+}
+
+impl<D: Display> TrackedDisplay<D> {
+    /// Wraps `display`, nothing is marked as changed yet
+    pub fn new(display: D) -> Self {
+        TrackedDisplay {
+            display,
+            dirty: None,
+        }
+    }
+
+    /// Returns the wrapped display
+    pub fn inner(&self) -> &D {
+        &self.display
+    }
+
+    /// Returns the wrapped display, changes made through it aren't tracked
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.display
+    }
+
+    /// Unwraps the display
+    pub fn into_inner(self) -> D {
+        self.display
+    }
+
+    /// Returns the bounding box of everything drawn since the last flush, in display
+    /// coordinates, i.e. with the rotation of the display applied
+    pub fn dirty_area(&self) -> Option<Rectangle> {
+        self.dirty
+            .map(|(min, max)| Rectangle::with_corners(min, max))
+    }
+
+    /// Marks `area` as changed, e.g. after drawing through [inner_mut](TrackedDisplay::inner_mut)
+    pub fn mark_dirty(&mut self, area: Rectangle) {
+        let area = area.intersection(&self.display.bounding_box());
+        if let Some(bottom_right) = area.bottom_right() {
+            self.extend(area.top_left);
+            self.extend(bottom_right);
+        }
+    }
+
+    /// Forgets the changed area without refreshing it
+    pub fn clear_dirty(&mut self) {
+        self.dirty = None;
+    }
+
+    /// Sends the changed area with [WaveshareDisplay::update_partial_frame], refreshes the
+    /// display once and forgets the area afterwards
+    ///
+    /// The window is in buffer coordinates, see [native_partial_window], and is copied to the
+    /// display through `scratch` like in [DirtyRegions::refresh]. Nothing is sent if nothing
+    /// was drawn since the last flush.
+    ///
+    /// # Panics
+    ///
+    /// If `scratch` can't hold a single row of the window.
+    pub fn flush_partial<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        scratch: &mut [u8],
+    ) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let Some(area) = self.dirty_area() else {
+            return Ok(());
+        };
+
+        let rotation = self.display.rotation();
+        let (width, height) = native_size(&self.display);
+
+        let mut regions = DirtyRegions::new(0);
+        regions.add(native_partial_window(area, width, height, rotation));
+        regions.refresh(epd, spi, delay, self.display.buffer(), width, scratch)?;
+        self.clear_dirty();
+        Ok(())
+    }
+
+    fn extend(&mut self, point: Point) {
+        self.dirty = Some(match self.dirty {
+            None => (point, point),
+            Some((min, max)) => (
+                Point::new(min.x.min(point.x), min.y.min(point.y)),
+                Point::new(max.x.max(point.x), max.y.max(point.y)),
+            ),
+        });
+    }
+}
+
+impl<D: Display> DrawTarget for TrackedDisplay<D> {
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let area = self.display.bounding_box();
+        for Pixel(point, color) in pixels {
+            if area.contains(point) {
+                self.extend(point);
+                self.display
+                    .draw_iter(core::iter::once(Pixel(point, color)))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.mark_dirty(*area);
+        self.display.fill_solid(area, color)
+    }
+}
+
+impl<D: Display> Dimensions for TrackedDisplay<D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.display.bounding_box()
+    }
+}
+
+/// Width and height of the buffer of `display`, i.e. without its rotation
+fn native_size<D: Display>(display: &D) -> (u32, u32) {
+    let size = display.bounding_box().size;
+    match display.rotation() {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => (size.width, size.height),
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => (size.height, size.width),
+    }
+}
+
+/// A front and a back [Display] buffer for partial refreshes of everything that changed
+///
+/// The front buffer holds what the panel shows, drawing goes to the back buffer.
+/// [swap](DoubleBuffer::swap) compares both, copies the back buffer to the front and returns
+/// the changed area, so unlike [TrackedDisplay] pixels drawn over with their old color
+/// don't count as changed. The back buffer keeps its content, drawing continues from the
+/// frame just shown.
+///
+/// ```rust, no_run
+/// # use embedded_hal_mock::*;
+/// # fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+/// use embedded_graphics::{prelude::*, primitives::{Circle, PrimitiveStyle}};
+/// use epd_waveshare::{color::Black, epd4in2::*, graphics::DoubleBuffer, prelude::*};
+/// #
+/// # let expectations = [];
+/// # let mut spi = spi::Mock::new(&expectations);
+/// # let expectations = [];
+/// # let cs_pin = pin::Mock::new(&expectations);
+/// # let busy_in = pin::Mock::new(&expectations);
+/// # let dc = pin::Mock::new(&expectations);
+/// # let rst = pin::Mock::new(&expectations);
+/// # let mut delay = delay::MockNoop::new();
+///
+/// let mut epd = Epd4in2::new(&mut spi, cs_pin, busy_in, dc, rst, &mut delay)?;
+/// let mut display = DoubleBuffer::new(Display4in2::default(), Display4in2::default());
+///
+/// let _ = Circle::new(Point::new(100, 100), 20)
+///     .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+///     .draw(&mut display);
+///
+/// let mut scratch = [0u8; 256];
+/// display.flush_partial(&mut epd, &mut spi, &mut delay, &mut scratch)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct DoubleBuffer<D> {
+    front: D,
+    back: D,
+    /// Largest changed area in percent of the display refreshed partially
+    max_partial_percent: u8,
+    /// Partial refreshes after which the next update is a full one
+    full_refresh_every: u32,
+    /// Partial refreshes since the last full one
+    partial_refreshes: u32,
+}
+
+/// How [DoubleBuffer::smart_update] refreshed the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmartUpdate {
+    /// Nothing changed, nothing was sent
+    Unchanged,
+    /// The changed area, in buffer coordinates, was refreshed with the quick LUT
+    Partial(Rectangle),
+    /// The whole frame was refreshed with the full LUT
+    Full,
+}
+
+impl<D: Display> DoubleBuffer<D> {
+    /// Takes `front` as the frame the panel shows and `back` to draw into
+    ///
+    /// The content of `front` is copied to `back`, so both start out the same.
+    ///
+    /// # Panics
+    ///
+    /// If the buffers of both displays have different lengths.
+    pub fn new(front: D, mut back: D) -> Self {
+        back.get_mut_buffer().copy_from_slice(front.buffer());
+        back.set_rotation(front.rotation());
+        DoubleBuffer {
+            front,
+            back,
+            max_partial_percent: 25,
+            full_refresh_every: 10,
+            partial_refreshes: 0,
+        }
+    }
+
+    /// Sets the largest change in percent of the display which
+    /// [smart_update](DoubleBuffer::smart_update) refreshes partially (default: 25)
+    pub fn set_max_partial_area(&mut self, percent: u8) {
+        self.max_partial_percent = percent.min(100);
+    }
+
+    /// Sets after how many partial refreshes [smart_update](DoubleBuffer::smart_update)
+    /// does a full one to clear the ghosting (default: 10)
+    ///
+    /// With 0 every update is a full refresh.
+    pub fn set_full_refresh_every(&mut self, partial_refreshes: u32) {
+        self.full_refresh_every = partial_refreshes;
+    }
+
+    /// Returns the frame shown since the last swap
+    pub fn front(&self) -> &D {
+        &self.front
+    }
+
+    /// Returns the frame being drawn
+    pub fn back(&self) -> &D {
+        &self.back
+    }
+
+    /// Returns the frame being drawn, e.g. to change its rotation
+    pub fn back_mut(&mut self) -> &mut D {
+        &mut self.back
+    }
+
+    /// Unwraps the front and the back display
+    pub fn into_inner(self) -> (D, D) {
+        (self.front, self.back)
+    }
+
+    /// Returns the area where the back buffer differs from the front buffer
+    ///
+    /// The area is in buffer coordinates, i.e. without the rotation of the display, and
+    /// widened to whole bytes, ready for [WaveshareDisplay::update_partial_frame].
+    pub fn changed_area(&self) -> Option<Rectangle> {
+        let (width, _) = native_size(&self.back);
+        let row_len = buffer_len(width as usize, 1);
+
+        let mut changed = self
+            .front
+            .buffer()
+            .iter()
+            .zip(self.back.buffer())
+            .enumerate()
+            .filter(|(_, (front, back))| front != back)
+            .map(|(index, _)| (index % row_len, index / row_len));
+        let (first_column, first_row) = changed.next()?;
+        let (min_column, max_column, last_row) = changed.fold(
+            (first_column, first_column, first_row),
+            |(min_column, max_column, _), (column, row)| {
+                (min_column.min(column), max_column.max(column), row)
+            },
+        );
+
+        let x = min_column as u32 * 8;
+        let x_end = ((max_column as u32 + 1) * 8).min(width);
+        Some(Rectangle::new(
+            Point::new(x as i32, first_row as i32),
+            Size::new(x_end - x, (last_row - first_row) as u32 + 1),
+        ))
+    }
+
+    /// Makes the back buffer the shown frame and returns the area that changed
+    ///
+    /// See [changed_area](DoubleBuffer::changed_area) for the coordinates of the area.
+    pub fn swap(&mut self) -> Option<Rectangle> {
+        let area = self.changed_area();
+        if area.is_some() {
+            self.front
+                .get_mut_buffer()
+                .copy_from_slice(self.back.buffer());
+        }
+        self.front.set_rotation(self.back.rotation());
+        area
+    }
+
+    /// Swaps the buffers and sends the changed area with
+    /// [WaveshareDisplay::update_partial_frame], then refreshes the display once
+    ///
+    /// The area is copied to the display through `scratch` like in [DirtyRegions::refresh].
+    /// Nothing is sent if both buffers are the same, and nothing but a
+    /// [DisplayError::InvalidData] is returned if `scratch` can't hold a single row of the
+    /// changed area.
+    pub fn flush_partial<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        scratch: &mut [u8],
+    ) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let Some(area) = self.swap() else {
+            return Ok(());
+        };
+
+        let (width, _) = native_size(&self.front);
+        let mut regions = DirtyRegions::new(0);
+        regions.add(area);
+        regions.refresh(epd, spi, delay, self.front.buffer(), width, scratch)
+    }
+
+    /// Swaps the buffers and refreshes the change partially or the whole frame
+    ///
+    /// Small changes are sent like [flush_partial](DoubleBuffer::flush_partial) and refreshed
+    /// with [RefreshLut::Quick]. Changes larger than the
+    /// [max partial area](DoubleBuffer::set_max_partial_area), and the update following
+    /// [full_refresh_every](DoubleBuffer::set_full_refresh_every) partial ones, send the
+    /// whole frame and refresh it with [RefreshLut::Full]. So does every update while the
+    /// driver [can't refresh partially](WaveshareDisplay::can_partial_refresh), e.g. the first
+    /// one after a sleep. The LUT is only switched when the driver doesn't use the needed one
+    /// yet.
+    ///
+    /// To compare with a frame rendered elsewhere, copy it into the buffer of
+    /// [back_mut](DoubleBuffer::back_mut) first.
+    ///
+    /// # Panics
+    ///
+    /// If `scratch` can't hold a single row of a partially refreshed area.
+    pub fn smart_update<SPI, CS, BUSY, DC, RST, DELAY, EPD>(
+        &mut self,
+        epd: &mut EPD,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        scratch: &mut [u8],
+    ) -> Result<SmartUpdate, DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let Some(area) = self.swap() else {
+            return Ok(SmartUpdate::Unchanged);
+        };
+
+        let (width, height) = native_size(&self.front);
+        let display_area = u64::from(width) * u64::from(height);
+        let large = rect_area(&area) * 100 > display_area * u64::from(self.max_partial_percent);
+        let due = self.partial_refreshes >= self.full_refresh_every;
+        if large || due || !epd.can_partial_refresh() {
+            if epd.refresh_lut() != RefreshLut::Full {
+                epd.set_lut(spi, Some(RefreshLut::Full))?;
+            }
+            epd.update_and_display_frame(spi, self.front.buffer(), delay)?;
+            self.partial_refreshes = 0;
+            return Ok(SmartUpdate::Full);
+        }
+
+        if epd.refresh_lut() != RefreshLut::Quick {
+            epd.set_lut(spi, Some(RefreshLut::Quick))?;
+        }
+        let mut regions = DirtyRegions::new(0);
+        regions.add(area);
+        regions.refresh(epd, spi, delay, self.front.buffer(), width, scratch)?;
+        self.partial_refreshes += 1;
+        Ok(SmartUpdate::Partial(area))
+    }
+}
+
+impl<D: Display> DrawTarget for DoubleBuffer<D> {
+    type Color = BinaryColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.back.draw_iter(pixels)
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.back.fill_solid(area, color)
+    }
+}
+
+impl<D: Display> Dimensions for DoubleBuffer<D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.back.bounding_box()
+    }
+}
+
+/// Initialises a display, draws a single frame and puts the display to sleep again
+///
+/// `draw` gets a display buffer filled with the default background, whatever it draws is
+/// sent to the panel and refreshed in one go. The sleeping driver is returned, it can be woken
+/// up again with [WaveshareDisplay::wake_up].
+///
+/// The types of the driver and the display buffer are inferred from the return value and the
+/// argument of `draw`:
+///
+/// ```rust, no_run
+/// # use embedded_hal_mock::*;
+/// # fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+/// use embedded_graphics::{
+///     pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
+/// };
+/// use epd_waveshare::{epd4in2::*, graphics::show_once, prelude::*};
+/// #
+/// # let expectations = [];
+/// # let mut spi = spi::Mock::new(&expectations);
+/// # let expectations = [];
+/// # let cs_pin = pin::Mock::new(&expectations);
+/// # let busy_in = pin::Mock::new(&expectations);
+/// # let dc = pin::Mock::new(&expectations);
+/// # let rst = pin::Mock::new(&expectations);
+/// # let mut delay = delay::MockNoop::new();
+///
+/// let _epd: Epd4in2<_, _, _, _, _, _> = show_once(
+///     &mut spi,
+///     cs_pin,
+///     busy_in,
+///     dc,
+///     rst,
+///     &mut delay,
+///     |display: &mut Display4in2| {
+///         let _ = Line::new(Point::new(0, 120), Point::new(0, 295))
+///             .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+///             .draw(display);
+///     },
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn show_once<SPI, CS, BUSY, DC, RST, DELAY, EPD, D, F>(
+    spi: &mut SPI,
+    cs: CS,
+    busy: BUSY,
+    dc: DC,
+    rst: RST,
+    delay: &mut DELAY,
+    draw: F,
+) -> Result<EPD, DisplayError<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+    EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    D: Display + Default,
+    F: FnOnce(&mut D),
+{
+    let mut epd = EPD::new(spi, cs, busy, dc, rst, delay)?;
+
+    let mut display = D::default();
+    draw(&mut display);
+
+    epd.update_and_display_frame(spi, display.buffer(), delay)?;
+    epd.sleep(spi, delay)?;
+    Ok(epd)
+}
+
+/// Returns the smallest rectangle containing both `a` and `b`
+fn bounding_box(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    let left = a.top_left.x.min(b.top_left.x);
+    let top = a.top_left.y.min(b.top_left.y);
+    let right = (a.top_left.x + a.size.width as i32).max(b.top_left.x + b.size.width as i32);
+    let bottom = (a.top_left.y + a.size.height as i32).max(b.top_left.y + b.size.height as i32);
+    Rectangle::new(
+        Point::new(left, top),
+        Size::new((right - left) as u32, (bottom - top) as u32),
+    )
+}
+
+fn rect_area(rect: &Rectangle) -> u64 {
+    u64::from(rect.size.width) * u64::from(rect.size.height)
+}
+
+/// Converts an area in rotated display coordinates into the partial window in buffer coordinates
+///
+/// With a rotation the partial window for [WaveshareDisplay::update_partial_frame] isn't the
+/// area which was drawn, e.g. the bounding box of a rotated text. `width` and `height` are the
+/// size of the buffer without rotation. The window is widened to whole bytes horizontally, as
+/// needed by the partial updates, and clipped to the display, so with a `width` which isn't a
+/// multiple of 8 the last window ends on the last column. An area outside of the display
+/// results in a zero sized window.
+pub fn native_partial_window(
+    area: Rectangle,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> Rectangle {
+    let display = Rectangle::new(Point::zero(), rotated_size(width, height, rotation));
+    let area = area.intersection(&display);
+    if area.is_zero_sized() {
+        return Rectangle::zero();
+    }
+
+    let (x0, y0) = (area.top_left.x as u32, area.top_left.y as u32);
+    let (x1, y1) = (x0 + area.size.width - 1, y0 + area.size.height - 1);
+    let (ax, ay) = find_rotation(x0, y0, width, height, rotation);
+    let (bx, by) = find_rotation(x1, y1, width, height, rotation);
+
+    let x_start = ax.min(bx) / 8 * 8;
+    let x_end = ((ax.max(bx) / 8 + 1) * 8).min(width);
+    let y_start = ay.min(by);
+    let y_end = ay.max(by) + 1;
+    Rectangle::new(
+        Point::new(x_start as i32, y_start as i32),
+        Size::new(x_end - x_start, y_end - y_start),
+    )
+}
+
+pub(crate) fn rotated_size(width: u32, height: u32, rotation: DisplayRotation) -> Size {
+    match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => Size::new(width, height),
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => Size::new(height, width),
+    }
+}
+
+// Checks if a pos is outside the defined display
+fn outside_display(p: Point, width: u32, height: u32, rotation: DisplayRotation) -> bool {
+    if p.x < 0 || p.y < 0 {
+        return true;
+    }
+    let (x, y) = (p.x as u32, p.y as u32);
+    match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
+            if x >= width || y >= height {
+                return true;
+            }
+        }
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+            if y >= width || x >= height {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Shifts every row of a 1 bit buffer by `pixels` to the right (left for negative values)
+fn shift_row_bits(buffer: &mut [u8], row_len: usize, width: u32, pixels: i32, fill: u8) {
+    if pixels == 0 {
+        return;
+    }
+    for row in buffer.chunks_mut(row_len) {
+        if pixels.unsigned_abs() >= width {
+            row.fill(fill);
+            continue;
+        }
+
+        // the padding at the end of the row is shifted in from the right
+        let used_bits = width % 8;
+        if used_bits != 0 {
+            let padding = 0xFF >> used_bits;
+            let last = row.len() - 1;
+            row[last] = (row[last] & !padding) | (fill & padding);
+        }
+
+        let shift = pixels.unsigned_abs() as usize;
+        let (bytes, bits) = (shift / 8, (shift % 8) as u32);
+        let byte = |row: &[u8], index: isize| -> u8 {
+            if index < 0 || index as usize >= row.len() {
+                fill
+            } else {
+                row[index as usize]
+            }
+        };
+
+        if pixels > 0 {
+            for i in (0..row.len()).rev() {
+                let src = i as isize - bytes as isize;
+                let high = byte(row, src - 1).checked_shl(8 - bits).unwrap_or(0);
+                row[i] = (byte(row, src) >> bits) | high;
+            }
+        } else {
+            for i in 0..row.len() {
+                let src = i as isize + bytes as isize;
+                let low = byte(row, src + 1).checked_shr(8 - bits).unwrap_or(0);
+                row[i] = (byte(row, src) << bits) | low;
+            }
+        }
+    }
+}
+
+/// Moves whole rows of a buffer `rows` down (up for negative values)
+fn shift_rows(buffer: &mut [u8], row_len: usize, rows: i32, fill: u8) {
+    let shift = (rows.unsigned_abs() as usize * row_len).min(buffer.len());
+    let len = buffer.len();
+    if rows > 0 {
+        buffer.copy_within(..len - shift, shift);
+        buffer[..shift].fill(fill);
+    } else {
+        buffer.copy_within(shift.., 0);
+        buffer[len - shift..].fill(fill);
+    }
+}
+
+fn find_rotation(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u32) {
+    let nx;
+    let ny;
+    match rotation {
+        DisplayRotation::Rotate0 => {
+            nx = x;
+            ny = y;
+        }
+        DisplayRotation::Rotate90 => {
+            nx = width - 1 - y;
+            ny = x;
         }
         DisplayRotation::Rotate180 => {
             nx = width - 1 - x;
@@ -421,29 +2683,58 @@ fn find_oct_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayR
     (
         /* what byte address is this? */
         nx / 2 + (width / 2) * ny,
-        /* is this the lower nibble (within byte)? */
+        /* is this the upper nibble (within byte)? */
         (nx & 0x1) == 0,
     )
 }
 
+#[rustfmt::skip]
+//returns index position in the u8-slice and the shift of the 2 bits inside that u8
+fn find_quad_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u8) {
+    let (nx, ny) = find_rotation(x, y, width, height, rotation);
+    (
+        nx / 4 + width.div_ceil(4) * ny,
+        /* the first pixel is stored in the highest bits */
+        6 - 2 * (nx % 4) as u8,
+    )
+}
+
 #[rustfmt::skip]
 //returns index position in the u8-slice and the bit-position inside that u8
 fn find_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u8) {
     let (nx, ny) = find_rotation(x, y, width, height, rotation);
     (
-        nx / 8 + ((width + 7) / 8) * ny,
+        nx / 8 + width.div_ceil(8) * ny,
         0x80 >> (nx % 8),
     )
 }
 
+//returns the byte column and row in the controller ram and the bit-position inside that byte
+fn find_ram_address(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> (u32, u32, u8) {
+    let (nx, ny) = find_rotation(x, y, width, height, rotation);
+    (nx / 8, ny, 0x80 >> (nx % 8))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{buffer_len, find_position, outside_display, Display, DisplayRotation, VarDisplay};
+    use super::{
+        buffer_len, find_position, find_ram_address, native_partial_window,
+        octcolor_quantization_error, outside_display, pack_octcolor, pack_octcolor_dithered,
+        quantization_error, DirtyRegions, Display, DisplayRotation, DoubleBuffer, OctDisplay,
+        PanelImage, SevenSegment, SmartUpdate, SubDisplay, TestPattern, TrackedDisplay, VarDisplay,
+        DIRTY_REGION_CAPACITY,
+    };
     use crate::color::Black;
-    use crate::color::Color;
+    use crate::color::{Color, OctColor};
     use embedded_graphics::{
         prelude::*,
-        primitives::{Line, PrimitiveStyle},
+        primitives::{Line, PrimitiveStyle, Rectangle},
     };
 
     #[test]
@@ -465,11 +2756,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn new_display_is_filled_with_background() {
+        let mut buffer = [0x0Fu8; 4 * 16];
+        let display = SubDisplay::new(32, 16, &mut buffer);
+        assert!(display.buffer().iter().all(|&byte| byte == 0x0F));
+
+        let display = SubDisplay::new_cleared(32, 16, display.buffer);
+        assert!(display.buffer().iter().all(|&byte| byte == 0xFF));
+
+        let mut var_buffer = [0x0Fu8; 4 * 16];
+        let display = VarDisplay::new_cleared(32, 16, &mut var_buffer);
+        assert!(display.buffer().iter().all(|&byte| byte == 0xFF));
+
+        let display = display.with_background(Color::Black);
+        assert!(display.buffer().iter().all(|&byte| byte == 0x00));
+
+        let display = crate::epd5in65f::Display5in65f::default().with_background(OctColor::Green);
+        assert!(display
+            .buffer()
+            .iter()
+            .all(|&byte| byte == OctColor::colors_byte(OctColor::Green, OctColor::Green)));
+    }
+
     #[test]
     fn rotation_overflow() {
         use crate::epd4in2::{HEIGHT, WIDTH};
-        let width = WIDTH as u32;
-        let height = HEIGHT as u32;
+        let width = WIDTH;
+        let height = HEIGHT;
         test_rotation_overflow(width, height, DisplayRotation::Rotate0);
         test_rotation_overflow(width, height, DisplayRotation::Rotate90);
         test_rotation_overflow(width, height, DisplayRotation::Rotate180);
@@ -480,7 +2794,7 @@ mod tests {
         let max_value = width / 8 * height;
         for x in 0..(width + height) {
             //limit x because it runs too long
-            for y in 0..(u32::max_value()) {
+            for y in 0..(u32::MAX) {
                 if outside_display(Point::new(x as i32, y as i32), width, height, rotation2) {
                     break;
                 } else {
@@ -491,6 +2805,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ram_address_mapping() {
+        let (width, height) = (128, 296);
+
+        let rotation = DisplayRotation::Rotate0;
+        assert_eq!(
+            find_ram_address(0, 0, width, height, rotation),
+            (0, 0, 0x80)
+        );
+        assert_eq!(
+            find_ram_address(9, 3, width, height, rotation),
+            (1, 3, 0x40)
+        );
+        assert_eq!(
+            find_ram_address(127, 295, width, height, rotation),
+            (15, 295, 0x01)
+        );
+
+        let rotation = DisplayRotation::Rotate90;
+        assert_eq!(
+            find_ram_address(0, 0, width, height, rotation),
+            (15, 0, 0x01)
+        );
+        assert_eq!(
+            find_ram_address(295, 127, width, height, rotation),
+            (0, 295, 0x80)
+        );
+
+        let rotation = DisplayRotation::Rotate180;
+        assert_eq!(
+            find_ram_address(0, 0, width, height, rotation),
+            (15, 295, 0x01)
+        );
+
+        let rotation = DisplayRotation::Rotate270;
+        assert_eq!(
+            find_ram_address(0, 0, width, height, rotation),
+            (0, 295, 0x80)
+        );
+        assert_eq!(
+            find_ram_address(295, 0, width, height, rotation),
+            (0, 0, 0x80)
+        );
+    }
+
+    #[test]
+    fn ram_address_matches_buffer_position() {
+        let (width, height) = (128, 296);
+        for &rotation in &[
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ] {
+            for (x, y) in [(0, 0), (13, 77), (100, 127), (127, 5)] {
+                let (index, bit) = find_position(x, y, width, height, rotation);
+                let (x_byte, ram_y, ram_bit) = find_ram_address(x, y, width, height, rotation);
+                assert_eq!(index, x_byte + width / 8 * ram_y);
+                assert_eq!(bit, ram_bit);
+            }
+        }
+    }
+
     #[test]
     fn graphics_rotation_0() {
         use crate::epd2in9::DEFAULT_BACKGROUND_COLOR;
@@ -539,4 +2916,989 @@ mod tests {
             assert_eq!(byte, DEFAULT_BACKGROUND_COLOR.get_byte_value());
         }
     }
+
+    // 12x2 white sprite with a 4 pixel long black line at the start of the first row
+    fn blit_pattern(x: u32, y: u32) -> [u8; 16] {
+        let mut target = [Color::Black.get_byte_value(); 4 * 4];
+        let mut display = VarDisplay::new(32, 4, &mut target);
+
+        let mut sprite_buffer = [Color::White.get_byte_value(); 2 * 2];
+        let mut sprite = SubDisplay::new(12, 2, &mut sprite_buffer);
+        let _ = Line::new(Point::new(0, 0), Point::new(3, 0))
+            .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+            .draw(&mut sprite);
+        assert_eq!(sprite.buffer(), [0x0F, 0xFF, 0xFF, 0xFF]);
+
+        sprite.blit(&mut display, x, y);
+        target
+    }
+
+    #[test]
+    fn blit_aligned() {
+        #[rustfmt::skip]
+        assert_eq!(blit_pattern(8, 1), [
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x0F, 0xF0, 0x00,
+            0x00, 0xFF, 0xF0, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ]);
+    }
+
+    #[test]
+    fn blit_unaligned() {
+        #[rustfmt::skip]
+        assert_eq!(blit_pattern(3, 0), [
+            0x01, 0xFE, 0x00, 0x00,
+            0x1F, 0xFE, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ]);
+    }
+
+    #[test]
+    fn blit_clipped() {
+        #[rustfmt::skip]
+        assert_eq!(blit_pattern(26, 3), [
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x03,
+        ]);
+    }
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn dirty_regions_merge_nearby() {
+        let mut dirty = DirtyRegions::new(4);
+        dirty.add(rect(0, 0, 8, 8));
+        // 4 pixels to the right, still within the merge distance
+        dirty.add(rect(12, 2, 8, 8));
+        assert_eq!(dirty.regions(), [rect(0, 0, 20, 10)]);
+
+        // overlapping regions always merge
+        let mut dirty = DirtyRegions::new(0);
+        dirty.add(rect(0, 0, 8, 8));
+        dirty.add(rect(4, 4, 8, 8));
+        assert_eq!(dirty.regions(), [rect(0, 0, 12, 12)]);
+    }
+
+    #[test]
+    fn dirty_regions_keep_far_apart_separate() {
+        let mut dirty = DirtyRegions::new(4);
+        dirty.add(rect(0, 0, 8, 8));
+        dirty.add(rect(13, 0, 8, 8));
+        dirty.add(rect(0, 100, 8, 8));
+        assert_eq!(dirty.regions().len(), 3);
+
+        // a region between two others pulls both in
+        dirty.add(rect(6, 0, 4, 4));
+        assert_eq!(dirty.regions().len(), 2);
+        assert!(dirty.regions().contains(&rect(0, 0, 21, 8)));
+
+        // empty areas are ignored
+        dirty.add(rect(200, 200, 0, 8));
+        assert_eq!(dirty.regions().len(), 2);
+    }
+
+    #[test]
+    fn dirty_regions_merge_when_full() {
+        let mut dirty = DirtyRegions::new(0);
+        for i in 0..DIRTY_REGION_CAPACITY as i32 {
+            dirty.add(rect(i * 100, 0, 8, 8));
+        }
+        assert_eq!(dirty.regions().len(), DIRTY_REGION_CAPACITY);
+
+        // joins the closest region instead of getting lost
+        dirty.add(rect(10, 0, 8, 8));
+        assert_eq!(dirty.regions().len(), DIRTY_REGION_CAPACITY);
+        assert!(dirty.regions().contains(&rect(0, 0, 18, 8)));
+    }
+
+    #[test]
+    fn dirty_regions_refresh_once() {
+        use crate::epd4in2::{Epd4in2, WIDTH};
+        use crate::mock::{Event, MockDelay, Recorder};
+        use crate::traits::WaveshareDisplay;
+        extern crate std;
+        use std::vec::Vec;
+
+        let mock = Recorder::new(true);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        let row_len = buffer_len(WIDTH as usize, 1);
+        let mut buffer = [0xFFu8; 50 * 4];
+        buffer[row_len + 1] = 0x0F;
+        buffer[row_len * 3 + 1] = 0xF0;
+
+        let mut dirty = DirtyRegions::new(0);
+        // widened to the byte at x 8..16, the two rows are sent in separate bands
+        dirty.add(rect(10, 1, 3, 1));
+        dirty.add(rect(9, 3, 2, 1));
+        assert_eq!(dirty.regions().len(), 2);
+
+        let mut scratch = [0u8; 1];
+        dirty
+            .refresh(
+                &mut epd,
+                &mut mock.spi(),
+                &mut mock.delay(),
+                &buffer,
+                WIDTH,
+                &mut scratch,
+            )
+            .unwrap();
+
+        let events = mock.events();
+        let windows: Vec<_> = events
+            .windows(2)
+            .filter(|pair| pair[0] == Event::Command(0x13))
+            .map(|pair| pair[1].clone())
+            .collect();
+        assert_eq!(windows, [Event::data(&[0x0F]), Event::data(&[0xF0])]);
+        assert_eq!(mock.commands().iter().filter(|&&c| c == 0x12).count(), 1);
+        assert!(dirty.is_empty());
+    }
+
+    #[test]
+    fn dirty_regions_refuse_a_small_scratch_buffer() {
+        use crate::epd4in2::{Epd4in2, WIDTH};
+        use crate::mock::{MockDelay, Recorder};
+        use crate::traits::{DisplayError, WaveshareDisplay};
+
+        let mock = Recorder::new(true);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        let buffer = [0xFFu8; 50 * 4];
+        let mut dirty = DirtyRegions::new(0);
+        // a narrow region first, the second one needs 3 bytes per row
+        dirty.add(rect(0, 0, 8, 1));
+        dirty.add(rect(100, 2, 20, 2));
+
+        let mut scratch = [0u8; 2];
+        assert_eq!(
+            dirty.refresh(
+                &mut epd,
+                &mut mock.spi(),
+                &mut mock.delay(),
+                &buffer,
+                WIDTH,
+                &mut scratch,
+            ),
+            Err(DisplayError::InvalidData)
+        );
+        assert_eq!(mock.events(), []);
+        assert!(!dirty.is_empty());
+    }
+
+    #[test]
+    fn tracked_display_bounds() {
+        use crate::epd4in2::{Display4in2, Epd4in2};
+        use crate::mock::{Event, MockDelay, Recorder};
+        use crate::traits::WaveshareDisplay;
+
+        let mut display = TrackedDisplay::new(Display4in2::default());
+        assert_eq!(display.dirty_area(), None);
+
+        let _ = Pixel(Point::new(30, 40), Black).draw(&mut display);
+        assert_eq!(display.dirty_area(), Some(rect(30, 40, 1, 1)));
+
+        let _ = Line::new(Point::new(12, 50), Point::new(20, 45))
+            .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+            .draw(&mut display);
+        assert_eq!(display.dirty_area(), Some(rect(12, 40, 19, 11)));
+
+        // filled areas are clipped to the display, pixels outside are ignored
+        let _ = display.fill_solid(&rect(395, 290, 20, 20), Black);
+        let _ = Pixel(Point::new(-1, 500), Black).draw(&mut display);
+        assert_eq!(display.dirty_area(), Some(rect(12, 40, 388, 260)));
+        assert_eq!(display.inner().buffer()[40 * 50 + 3] & 0x02, 0);
+
+        display.clear_dirty();
+        let _ = Pixel(Point::new(9, 2), Black).draw(&mut display);
+
+        let mock = Recorder::new(true);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let mut scratch = [0u8; 8];
+        display
+            .flush_partial(&mut epd, &mut mock.spi(), &mut mock.delay(), &mut scratch)
+            .unwrap();
+        assert_eq!(display.dirty_area(), None);
+
+        // a single byte of the window at x 8..16 in row 2 is sent, then refreshed once
+        let events = mock.events();
+        let data = events
+            .windows(2)
+            .find(|pair| pair[0] == Event::Command(0x13))
+            .map(|pair| pair[1].clone());
+        assert_eq!(data, Some(Event::data(&[0xBF])));
+        assert_eq!(mock.commands().iter().filter(|&&c| c == 0x12).count(), 1);
+
+        // nothing drawn, nothing sent
+        mock.clear();
+        display
+            .flush_partial(&mut epd, &mut mock.spi(), &mut mock.delay(), &mut scratch)
+            .unwrap();
+        assert!(mock.events().is_empty());
+    }
+
+    #[test]
+    fn smart_update_picks_partial_or_full_refresh() {
+        use crate::epd4in2::{Display4in2, Epd4in2};
+        use crate::mock::{MockDelay, Recorder};
+        use crate::traits::{RefreshLut, WaveshareDisplay};
+
+        let mock = Recorder::new(true);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let mut display = DoubleBuffer::new(Display4in2::default(), Display4in2::default());
+        display.set_full_refresh_every(2);
+        let mut scratch = [0u8; 64];
+        let mut update = |display: &mut DoubleBuffer<Display4in2>| {
+            mock.clear();
+            display
+                .smart_update(&mut epd, &mut mock.spi(), &mut mock.delay(), &mut scratch)
+                .unwrap()
+        };
+
+        assert_eq!(update(&mut display), SmartUpdate::Unchanged);
+        assert!(mock.commands().is_empty());
+
+        // without a base image the first refresh is a full one
+        let _ = Pixel(Point::new(0, 0), Black).draw(&mut display);
+        assert_eq!(update(&mut display), SmartUpdate::Full);
+
+        // a small change is refreshed partially with the quick LUT
+        let _ = Pixel(Point::new(30, 40), Black).draw(&mut display);
+        assert_eq!(
+            update(&mut display),
+            SmartUpdate::Partial(rect(24, 40, 8, 1))
+        );
+        assert!(mock.commands().contains(&0x90));
+        let _ = Pixel(Point::new(31, 40), Black).draw(&mut display);
+        assert!(matches!(update(&mut display), SmartUpdate::Partial(_)));
+
+        // after the configured partial refreshes the next one is full, even if small
+        let _ = Pixel(Point::new(32, 40), Black).draw(&mut display);
+        assert_eq!(update(&mut display), SmartUpdate::Full);
+        assert!(!mock.commands().contains(&0x90));
+
+        // a large change is a full refresh
+        let _ = Pixel(Point::new(33, 40), Black).draw(&mut display);
+        assert!(matches!(update(&mut display), SmartUpdate::Partial(_)));
+        let _ = display.fill_solid(&rect(0, 0, 400, 200), Black);
+        assert_eq!(update(&mut display), SmartUpdate::Full);
+        assert_eq!(epd.refresh_lut(), RefreshLut::Full);
+    }
+
+    #[test]
+    fn double_buffer_swap_returns_changed_area() {
+        use crate::epd4in2::{Display4in2, Epd4in2};
+        use crate::mock::{Event, MockDelay, Recorder};
+        use crate::traits::WaveshareDisplay;
+
+        let mut display = DoubleBuffer::new(Display4in2::default(), Display4in2::default());
+        assert_eq!(display.swap(), None);
+
+        // widened to whole bytes, from the first to the last changed row
+        let _ = Pixel(Point::new(30, 40), Black).draw(&mut display);
+        let _ = Pixel(Point::new(9, 52), Black).draw(&mut display);
+        assert_eq!(display.changed_area(), Some(rect(8, 40, 24, 13)));
+        assert_eq!(display.swap(), Some(rect(8, 40, 24, 13)));
+        assert_eq!(display.front().buffer(), display.back().buffer());
+        assert_eq!(display.swap(), None);
+
+        // drawing the same pixels again changes nothing
+        let _ = Pixel(Point::new(30, 40), Black).draw(&mut display);
+        assert_eq!(display.swap(), None);
+
+        // in buffer coordinates when rotated
+        display.back_mut().set_rotation(DisplayRotation::Rotate180);
+        let _ = Pixel(Point::new(0, 0), Black).draw(&mut display);
+        assert_eq!(display.swap(), Some(rect(392, 299, 8, 1)));
+        assert!(matches!(
+            display.front().rotation(),
+            DisplayRotation::Rotate180
+        ));
+
+        let _ = Pixel(Point::new(0, 1), Black).draw(&mut display);
+        let mock = Recorder::new(true);
+        let mut epd: Epd4in2<_, _, _, _, _, MockDelay> =
+            Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let mut scratch = [0u8; 8];
+        display
+            .flush_partial(&mut epd, &mut mock.spi(), &mut mock.delay(), &mut scratch)
+            .unwrap();
+        let events = mock.events();
+        let data = events
+            .windows(2)
+            .find(|pair| pair[0] == Event::Command(0x13))
+            .map(|pair| pair[1].clone());
+        assert_eq!(data, Some(Event::data(&[0xFE])));
+        assert_eq!(mock.commands().iter().filter(|&&c| c == 0x12).count(), 1);
+
+        mock.clear();
+        display
+            .flush_partial(&mut epd, &mut mock.spi(), &mut mock.delay(), &mut scratch)
+            .unwrap();
+        assert!(mock.events().is_empty());
+    }
+
+    #[test]
+    fn pack_octcolor_picks_nearest() {
+        use embedded_graphics::pixelcolor::Rgb888;
+
+        let image = [
+            Rgb888::new(255, 0, 0),
+            Rgb888::new(250, 120, 10),
+            Rgb888::new(10, 10, 10),
+            Rgb888::new(20, 220, 30),
+            // gray is never mapped to HiZ
+            Rgb888::new(150, 150, 150),
+        ];
+        let mut buffer = [0u8; 3];
+        pack_octcolor(&image, &mut buffer);
+
+        assert_eq!(
+            buffer,
+            [
+                OctColor::colors_byte(OctColor::Red, OctColor::Orange),
+                OctColor::colors_byte(OctColor::Black, OctColor::Green),
+                // the missing last pixel is white
+                OctColor::colors_byte(OctColor::White, OctColor::White),
+            ]
+        );
+    }
+
+    #[test]
+    fn pack_octcolor_dithers_mid_gray() {
+        use embedded_graphics::pixelcolor::Rgb888;
+
+        let image = [Rgb888::new(128, 128, 128); 16];
+        let mut buffer = [0u8; 8];
+        pack_octcolor_dithered(&image, 4, &mut buffer);
+
+        let colors = buffer
+            .iter()
+            .flat_map(|&byte| {
+                let (a, b) = OctColor::split_byte(byte).unwrap();
+                [a, b]
+            })
+            .fold([0; 8], |mut count, color| {
+                count[color as usize] += 1;
+                count
+            });
+        // a mix instead of a single flat color
+        assert!(colors[OctColor::Black as usize] >= 4);
+        assert!(colors[OctColor::White as usize] >= 4);
+        assert!(colors.iter().all(|&count| count < 16));
+
+        // flat colors of the palette stay as they are
+        let image = [Rgb888::new(0, 0, 255); 16];
+        pack_octcolor_dithered(&image, 4, &mut buffer);
+        assert!(buffer
+            .iter()
+            .all(|&b| b == OctColor::colors_byte(OctColor::Blue, OctColor::Blue)));
+    }
+
+    #[test]
+    fn tri_display_chromatic_rgb() {
+        use super::TriDisplay;
+        use crate::color::{ChromaticColor, TriColor};
+        use crate::epd2in13bc::Display2in13bc;
+        use embedded_graphics::pixelcolor::Rgb888;
+
+        let mut display = Display2in13bc::default();
+        assert_eq!(display.chromatic_color(), ChromaticColor::Red);
+        assert_eq!(
+            display.color_rgb(TriColor::Chromatic),
+            Rgb888::new(0xff, 0x00, 0x00)
+        );
+
+        let buffer = display.buffer()[0];
+        display.set_chromatic_color(ChromaticColor::Yellow);
+        assert_eq!(
+            display.color_rgb(TriColor::Chromatic),
+            Rgb888::new(0xff, 0xff, 0x00)
+        );
+        assert_eq!(display.color_rgb(TriColor::Black), Rgb888::new(0, 0, 0));
+        assert_eq!(display.buffer()[0], buffer);
+    }
+
+    #[test]
+    fn tri_display_without_chromatic_color_is_red() {
+        use super::TriDisplay;
+        use crate::color::{ChromaticColor, TriColor};
+        use embedded_graphics::pixelcolor::Rgb888;
+
+        // a display implemented before the chromatic color existed
+        struct OldDisplay([u8; 2]);
+        impl DrawTarget for OldDisplay {
+            type Color = TriColor;
+            type Error = core::convert::Infallible;
+            fn draw_iter<I>(&mut self, _pixels: I) -> Result<(), Self::Error>
+            where
+                I: IntoIterator<Item = Pixel<Self::Color>>,
+            {
+                Ok(())
+            }
+        }
+        impl OriginDimensions for OldDisplay {
+            fn size(&self) -> Size {
+                Size::new(8, 1)
+            }
+        }
+        impl TriDisplay for OldDisplay {
+            fn buffer(&self) -> &[u8] {
+                &self.0
+            }
+            fn get_mut_buffer(&mut self) -> &mut [u8] {
+                &mut self.0
+            }
+            fn set_rotation(&mut self, _rotation: DisplayRotation) {}
+            fn rotation(&self) -> DisplayRotation {
+                DisplayRotation::Rotate0
+            }
+            fn chromatic_offset(&self) -> usize {
+                1
+            }
+            fn bw_buffer(&self) -> &[u8] {
+                &self.0[..1]
+            }
+            fn chromatic_buffer(&self) -> &[u8] {
+                &self.0[1..]
+            }
+        }
+
+        let mut display = OldDisplay([0xFF; 2]);
+        display.set_chromatic_color(ChromaticColor::Yellow);
+        assert_eq!(display.chromatic_color(), ChromaticColor::Red);
+        assert_eq!(
+            display.color_rgb(TriColor::Chromatic),
+            Rgb888::new(0xff, 0x00, 0x00)
+        );
+    }
+
+    #[test]
+    fn show_once_sends_drawing() {
+        use super::show_once;
+        use crate::epd4in2::{Display4in2, Epd4in2};
+        use crate::mock::{MockDelay, MockPin, MockSpi, Recorder};
+
+        let mock = Recorder::new(true);
+        let _epd: Epd4in2<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay> = show_once(
+            &mut mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            &mut mock.delay(),
+            |display: &mut Display4in2| {
+                let _ = Line::new(Point::new(0, 0), Point::new(7, 0))
+                    .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+                    .draw(display);
+            },
+        )
+        .unwrap();
+
+        // the frame is sent with DataStartTransmission2, refreshed and the panel powered off
+        let frame = mock.data_after(0x13).unwrap();
+        assert_eq!(frame.len(), buffer_len(400, 300));
+        assert_eq!(frame[0], Color::Black.get_byte_value());
+        assert_eq!(frame[1], Color::White.get_byte_value());
+        let commands = mock.commands();
+        let refresh = commands.iter().position(|&c| c == 0x12).unwrap();
+        assert!(commands[refresh..].contains(&0x02));
+    }
+
+    #[test]
+    fn native_partial_window_rotations() {
+        // 16x8 buffer, the text spans x 1..=3 and y 2..=5 in rotated coordinates
+        let area = rect(1, 2, 3, 4);
+        assert_eq!(
+            native_partial_window(area, 16, 8, DisplayRotation::Rotate0),
+            rect(0, 2, 8, 4)
+        );
+        assert_eq!(
+            native_partial_window(area, 16, 8, DisplayRotation::Rotate90),
+            rect(8, 1, 8, 3)
+        );
+        assert_eq!(
+            native_partial_window(area, 16, 8, DisplayRotation::Rotate180),
+            rect(8, 2, 8, 4)
+        );
+        assert_eq!(
+            native_partial_window(area, 16, 8, DisplayRotation::Rotate270),
+            rect(0, 4, 8, 3)
+        );
+    }
+
+    #[test]
+    fn native_partial_window_clips() {
+        assert_eq!(
+            native_partial_window(rect(12, 6, 10, 10), 16, 8, DisplayRotation::Rotate0),
+            rect(8, 6, 8, 2)
+        );
+        assert!(
+            native_partial_window(rect(20, 20, 2, 2), 16, 8, DisplayRotation::Rotate0)
+                .is_zero_sized()
+        );
+        // a width which isn't a multiple of 8 ends on the last column
+        assert_eq!(
+            native_partial_window(rect(0, 0, 2, 2), 122, 250, DisplayRotation::Rotate90),
+            rect(120, 0, 2, 2)
+        );
+    }
+
+    #[test]
+    fn tri_display_color_routing() {
+        use super::TriDisplay;
+        use crate::color::TriColor;
+        use crate::epd2in13bc::Display2in13bc;
+        use crate::epd5in83b_v2::Display5in83;
+
+        // (b/w bit, chromatic bit) of pixel (0, 0) for positive and negative rendering
+        let expected = [
+            (TriColor::Black, (0, 1), (0, 0)),
+            (TriColor::White, (1, 1), (1, 0)),
+            (TriColor::Chromatic, (1, 0), (1, 1)),
+        ];
+        for (color, positive, negative) in expected {
+            let mut display = Display2in13bc::default();
+            Pixel(Point::new(0, 0), color).draw(&mut display).unwrap();
+            let bits = (
+                display.bw_buffer()[0] >> 7,
+                display.chromatic_buffer()[0] >> 7,
+            );
+            assert_eq!(bits, positive, "{:?}", color);
+
+            let mut display = Display5in83::default();
+            Pixel(Point::new(0, 0), color).draw(&mut display).unwrap();
+            let bits = (
+                display.bw_buffer()[0] >> 7,
+                display.chromatic_buffer()[0] >> 7,
+            );
+            assert_eq!(bits, negative, "{:?}", color);
+        }
+
+        // chromatic drawn over black shows, and black drawn over chromatic replaces it
+        let mut display = Display2in13bc::default();
+        Pixel(Point::new(0, 0), TriColor::Black)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(0, 0), TriColor::Chromatic)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.bw_buffer()[0] >> 7, 1);
+        assert_eq!(display.chromatic_buffer()[0] >> 7, 0);
+        Pixel(Point::new(0, 0), TriColor::Black)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(display.bw_buffer()[0] >> 7, 0);
+        assert_eq!(display.chromatic_buffer()[0] >> 7, 1);
+    }
+
+    #[test]
+    fn tri_display_convert_colors() {
+        use super::TriDisplay;
+        use crate::epd2in13bc::Display2in13bc;
+        use embedded_graphics::pixelcolor::{BinaryColor, Rgb565};
+
+        let mut display = Display2in13bc::default();
+        let area = display.bounding_box();
+        Pixel(Point::new(0, 0), BinaryColor::On)
+            .draw(&mut display.convert_colors())
+            .unwrap();
+        let mut target = display.convert_colors();
+        Pixel(Point::new(1, 0), Rgb565::RED)
+            .draw(&mut target)
+            .unwrap();
+        Pixel(Point::new(2, 0), Rgb565::YELLOW)
+            .draw(&mut target)
+            .unwrap();
+        Pixel(Point::new(3, 0), Rgb565::new(2, 4, 2))
+            .draw(&mut target)
+            .unwrap();
+        Pixel(Point::new(4, 0), Rgb565::new(30, 60, 30))
+            .draw(&mut target)
+            .unwrap();
+        Rectangle::new(Point::new(0, 1), Size::new(8, 1))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::RED))
+            .draw(&mut target)
+            .unwrap();
+        assert_eq!(target.bounding_box(), area);
+
+        // black and dark gray in the b/w plane, red and yellow in the chromatic plane
+        assert_eq!(display.bw_buffer()[0], 0b0110_1111);
+        assert_eq!(display.chromatic_buffer()[0], 0b1001_1111);
+        let row = 104 / 8;
+        assert_eq!(display.bw_buffer()[row], 0xFF);
+        assert_eq!(display.chromatic_buffer()[row], 0x00);
+    }
+
+    #[test]
+    fn tri_display_transparent_keeps_pixels() {
+        use super::TriDisplay;
+        use crate::color::{TriColor, TriOverlay};
+        use crate::epd2in13bc::Display2in13bc;
+
+        let mut display = Display2in13bc::default();
+        Rectangle::new(Point::new(0, 0), Size::new(16, 2))
+            .into_styled(PrimitiveStyle::with_fill(TriColor::Chromatic))
+            .draw(&mut display)
+            .unwrap();
+        Line::new(Point::new(0, 2), Point::new(15, 2))
+            .into_styled(PrimitiveStyle::with_stroke(TriColor::Black, 1))
+            .draw(&mut display)
+            .unwrap();
+        let before = display.buffer().to_vec();
+
+        // an overlay with a transparent background only changes its own pixels
+        Rectangle::new(Point::new(0, 0), Size::new(16, 4))
+            .into_styled(PrimitiveStyle::with_fill(TriOverlay::TRANSPARENT))
+            .draw(&mut display.overlay())
+            .unwrap();
+        Pixel(Point::new(0, 0), TriOverlay::TRANSPARENT)
+            .draw(&mut display.overlay())
+            .unwrap();
+        assert_eq!(display.buffer(), &before[..]);
+
+        Pixel(Point::new(0, 3), TriColor::Black.into())
+            .draw(&mut display.overlay())
+            .unwrap();
+        assert_ne!(display.buffer(), &before[..]);
+        assert_eq!(display.bw_buffer()[3 * 104 / 8], 0x7F);
+    }
+
+    #[test]
+    fn quantization_error_of_known_image() {
+        use embedded_graphics::pixelcolor::Rgb888;
+
+        let image = [Rgb888::new(250, 10, 0), Rgb888::new(0, 0, 90)];
+        let mut buffer = [0u8; 1];
+        pack_octcolor(&image, &mut buffer);
+
+        // red and black are the nearest colors
+        let error = octcolor_quantization_error(&image, &buffer);
+        assert_eq!(error.red, (5 * 5) / 2);
+        assert_eq!(error.green, (10 * 10) / 2);
+        assert_eq!(error.blue, (90 * 90) / 2);
+        assert_eq!(error.mean(), (12 + 50 + 4050) / 3);
+
+        // an exactly displayable image has no error
+        let exact = [Rgb888::new(255, 0, 0), Rgb888::new(0, 0, 0)];
+        assert_eq!(
+            octcolor_quantization_error(&exact, &buffer),
+            Default::default()
+        );
+
+        let shown = [Rgb888::new(0, 0, 0); 2];
+        let error = quantization_error(&[Rgb888::new(255, 255, 255); 2], shown);
+        assert_eq!(error.mean(), 65025);
+        assert_eq!(quantization_error(&[], shown).mean(), 0);
+    }
+
+    #[test]
+    fn progress_bar_fills_fraction() {
+        use embedded_graphics::pixelcolor::BinaryColor;
+
+        let mut buffer = [0xFFu8; 2 * 3];
+        let mut display = VarDisplay::new(16, 3, &mut buffer);
+        let bar = rect(0, 1, 16, 2);
+
+        display
+            .draw_progress_bar(bar, 0.25, BinaryColor::On, BinaryColor::Off)
+            .unwrap();
+        assert_eq!(display.buffer(), [0xFF, 0xFF, 0x0F, 0xFF, 0x0F, 0xFF]);
+
+        display
+            .draw_progress_bar(bar, 0.6, BinaryColor::On, BinaryColor::Off)
+            .unwrap();
+        // 9.6 pixels are rounded to 10
+        assert_eq!(display.buffer()[2..4], [0x00, 0x3F]);
+
+        display
+            .draw_progress_bar(bar, 1.5, BinaryColor::On, BinaryColor::Off)
+            .unwrap();
+        assert_eq!(display.buffer()[2..], [0x00; 4]);
+
+        display
+            .draw_progress_bar(bar, -1.0, BinaryColor::On, BinaryColor::Off)
+            .unwrap();
+        assert_eq!(display.buffer(), [0xFF; 6]);
+    }
+
+    #[test]
+    fn test_pattern_checkerboard() {
+        let mut buffer = [0u8; 8];
+        let mut display = VarDisplay::new(16, 4, &mut buffer);
+
+        // black first, a set bit is white
+        display
+            .draw_test_pattern(TestPattern::Checkerboard(1))
+            .unwrap();
+        assert_eq!(
+            display.buffer(),
+            [0x55, 0x55, 0xAA, 0xAA, 0x55, 0x55, 0xAA, 0xAA]
+        );
+
+        display
+            .draw_test_pattern(TestPattern::Checkerboard(2))
+            .unwrap();
+        assert_eq!(
+            display.buffer(),
+            [0x33, 0x33, 0x33, 0x33, 0xCC, 0xCC, 0xCC, 0xCC]
+        );
+
+        // the pattern follows the rotation, a 90° rotated board is mirrored horizontally
+        display.set_rotation(DisplayRotation::Rotate90);
+        display
+            .draw_test_pattern(TestPattern::Checkerboard(1))
+            .unwrap();
+        assert_eq!(
+            display.buffer(),
+            [0xAA, 0xAA, 0x55, 0x55, 0xAA, 0xAA, 0x55, 0x55]
+        );
+    }
+
+    #[test]
+    fn test_pattern_bars_and_gradient() {
+        let mut buffer = [0u8; 2];
+        let mut display = VarDisplay::new(16, 1, &mut buffer);
+
+        display.draw_test_pattern(TestPattern::ColorBars).unwrap();
+        assert_eq!(display.buffer(), [0x00, 0xFF]);
+
+        // dark on the left, white on the right
+        display.draw_test_pattern(TestPattern::Gradient).unwrap();
+        assert_eq!(display.buffer(), [0x0A, 0xFF]);
+    }
+
+    #[test]
+    fn panel_image_matches_image_raw() {
+        use embedded_graphics::image::{Image, ImageRaw};
+        use embedded_graphics::pixelcolor::BinaryColor;
+
+        // 12 pixels wide, so every row has a padding nibble
+        let data = [0xF0, 0x10, 0x0F, 0x20, 0xAA, 0x50];
+
+        let mut buffer = [0u8; 6];
+        let mut display = VarDisplay::new_cleared(12, 3, &mut buffer);
+        PanelImage::new(&data, 12).draw(&mut display).unwrap();
+        // a set bit is white in the buffer, the padding stays white
+        assert_eq!(display.buffer(), [0x0F, 0xEF, 0xF0, 0xDF, 0x55, 0xAF]);
+
+        let mut expected = [0u8; 6];
+        let mut raw_display = VarDisplay::new_cleared(12, 3, &mut expected);
+        Image::new(&ImageRaw::<BinaryColor>::new(&data, 12), Point::zero())
+            .draw(&mut raw_display)
+            .unwrap();
+        assert_eq!(display.buffer(), raw_display.buffer());
+    }
+
+    #[test]
+    fn seven_segment_glyphs() {
+        let mut glyphs = [0u8; SevenSegment::glyphs_len(8, 12)];
+        let digits = SevenSegment::new(&mut glyphs, 8, 12, 2).unwrap();
+        assert_eq!(digits.size(), Size::new(8, 12));
+
+        // only the right segments b and c
+        let one = digits.glyph(1).unwrap();
+        assert_eq!(one.len(), 12);
+        assert_eq!(one[..2], [0xFF, 0xFF]);
+        assert!(one[2..10].iter().all(|&row| row == 0xFC));
+        assert_eq!(one[10..], [0xFF, 0xFF]);
+
+        // all segments, the top bar leaves the corners free
+        let eight = digits.glyph(8).unwrap();
+        assert_eq!(eight[0], 0xC3);
+        assert_eq!(eight[2], 0x3C);
+        // the middle bar joins the sides
+        assert_eq!(eight[5], 0x00);
+        assert_eq!(eight[11], 0xC3);
+
+        // every digit has its own glyph
+        for a in 0..10 {
+            for b in a + 1..10 {
+                assert_ne!(digits.glyph(a), digits.glyph(b));
+            }
+        }
+        assert_eq!(digits.glyph(10), None);
+    }
+
+    #[test]
+    fn seven_segment_refuses_bad_sizes() {
+        let mut glyphs = [0u8; SevenSegment::glyphs_len(16, 12)];
+        assert!(SevenSegment::new(&mut glyphs, 12, 12, 2).is_none());
+        assert!(SevenSegment::new(&mut glyphs, 16, 13, 2).is_none());
+        assert!(SevenSegment::new(&mut glyphs, 0, 12, 2).is_none());
+        assert!(SevenSegment::new(&mut glyphs, 16, 12, 2).is_some());
+    }
+
+    #[test]
+    fn seven_segment_draw_digit() {
+        let mut glyphs = [0u8; SevenSegment::glyphs_len(8, 12)];
+        let digits = SevenSegment::new(&mut glyphs, 8, 12, 2).unwrap();
+
+        let mut buffer = [0u8; 2 * 12];
+        let mut display = VarDisplay::new_cleared(16, 12, &mut buffer);
+        digits
+            .draw_digit(&mut display, 8, Point::new(8, 0))
+            .unwrap();
+        for (row, bytes) in display.buffer().chunks(2).enumerate() {
+            assert_eq!(bytes[0], 0xFF);
+            assert_eq!(bytes[1], digits.glyph(8).unwrap()[row]);
+        }
+
+        // no digit, nothing is drawn
+        let before = display.buffer().to_vec();
+        digits
+            .draw_digit(&mut display, 12, Point::new(0, 0))
+            .unwrap();
+        assert_eq!(display.buffer(), &before[..]);
+    }
+
+    #[test]
+    fn write_pbm_of_buffer() {
+        use embedded_graphics::pixelcolor::BinaryColor;
+        use std::vec::Vec;
+
+        let mut buffer = [0u8; 4];
+        let mut display = VarDisplay::new_cleared(16, 2, &mut buffer);
+        let _ = Pixel(Point::new(0, 0), BinaryColor::On).draw(&mut display);
+        let _ = Pixel(Point::new(15, 1), BinaryColor::On).draw(&mut display);
+
+        let mut pbm = Vec::new();
+        display.write_pbm(&mut pbm).unwrap();
+        assert_eq!(pbm[..8], *b"P4\n16 2\n");
+        // a set bit is black
+        assert_eq!(pbm[8..], [0x80, 0x00, 0x00, 0x01]);
+
+        // the image isn't rotated
+        display.set_rotation(DisplayRotation::Rotate90);
+        let mut rotated = Vec::new();
+        display.write_pbm(&mut rotated).unwrap();
+        assert_eq!(rotated, pbm);
+    }
+
+    #[test]
+    fn write_pgm_of_gray_buffer() {
+        use crate::epd4in2::Display4in2Gray;
+        use crate::graphics::GrayDisplay;
+        use embedded_graphics::pixelcolor::Gray4;
+        use std::vec::Vec;
+
+        let mut display = Display4in2Gray::default();
+        let _ = Pixel(Point::new(0, 0), Gray4::BLACK).draw(&mut display);
+        let _ = Pixel(Point::new(1, 0), Gray4::new(5)).draw(&mut display);
+
+        let mut pgm = Vec::new();
+        display.write_pgm(&mut pgm).unwrap();
+        let header = b"P5\n400 300\n3\n";
+        assert_eq!(pgm[..header.len()], *header);
+        assert_eq!(pgm.len(), header.len() + 400 * 300);
+        assert_eq!(pgm[header.len()..header.len() + 3], [0, 1, 3]);
+    }
+
+    #[test]
+    fn gray_buffer_exports_to_both_depths() {
+        use crate::epd4in2::Display4in2Gray;
+        use crate::graphics::GrayDisplay;
+        use embedded_graphics::pixelcolor::Gray4;
+
+        let mut display = Display4in2Gray::default();
+        let _ = Pixel(Point::new(0, 0), Gray4::BLACK).draw(&mut display);
+        let _ = Pixel(Point::new(1, 0), Gray4::new(5)).draw(&mut display);
+        let _ = Pixel(Point::new(2, 0), Gray4::new(10)).draw(&mut display);
+        let _ = Pixel(Point::new(9, 1), Gray4::BLACK).draw(&mut display);
+
+        let mut bw = [0u8; 400 / 8 * 300];
+        display.to_1bpp(Gray4::new(8), &mut bw);
+        assert_eq!(bw[0], 0b0011_1111);
+        assert_eq!(bw[50..52], [0xFF, 0b1011_1111]);
+        assert!(bw[52..].iter().all(|&byte| byte == 0xFF));
+
+        // a lower threshold keeps only the black pixels
+        display.to_1bpp(Gray4::new(3), &mut bw);
+        assert_eq!(bw[0], 0b0111_1111);
+
+        let mut gray = [0u8; 400 / 4 * 300];
+        display.to_2bpp(&mut gray);
+        assert_eq!(gray[0], 0b0001_1011);
+        assert_eq!(gray[..], display.buffer()[..]);
+    }
+
+    #[test]
+    fn draw_centered_text_on_rotated_display() {
+        use crate::epd2in9::Display2in9;
+        use embedded_graphics::{
+            mono_font::{ascii::FONT_6X10, MonoTextStyle},
+            pixelcolor::BinaryColor,
+            text::Text,
+        };
+
+        let style = MonoTextStyle::new(&FONT_6X10, BinaryColor::On);
+        let text = Text::new("Ready", Point::new(3, 40), style);
+
+        let mut display = Display2in9::default();
+        display.set_rotation(DisplayRotation::Rotate90);
+        let top_left = display.draw_centered(&text).unwrap();
+        assert_eq!(top_left, Point::new((296 - 30) / 2, (128 - 10) / 2));
+
+        // the same as drawing the text with its top left corner at the center
+        let mut expected = Display2in9::default();
+        expected.set_rotation(DisplayRotation::Rotate90);
+        let shift = top_left - text.bounding_box().top_left;
+        let _ = Text::new("Ready", Point::new(3, 40) + shift, style).draw(&mut expected);
+        assert!(display.buffer() == expected.buffer());
+        assert!(display.buffer().iter().any(|&byte| byte != 0xFF));
+    }
+
+    #[test]
+    fn scroll_horizontal_shifts_bits() {
+        let mut buffer = [0u8; 2];
+        let mut display = VarDisplay::new(16, 1, &mut buffer);
+        let content = [0x0F, 0x3C];
+
+        let mut shifted = |pixels, background| {
+            display.get_mut_buffer().copy_from_slice(&content);
+            display.scroll_horizontal(pixels, background);
+            [display.buffer()[0], display.buffer()[1]]
+        };
+        assert_eq!(shifted(1, Color::White), [0x87, 0x9E]);
+        assert_eq!(shifted(1, Color::Black), [0x07, 0x9E]);
+        assert_eq!(shifted(9, Color::White), [0xFF, 0x87]);
+        assert_eq!(shifted(-1, Color::White), [0x1E, 0x79]);
+        assert_eq!(shifted(-9, Color::White), [0x79, 0xFF]);
+        assert_eq!(shifted(16, Color::Black), [0x00, 0x00]);
+        assert_eq!(shifted(0, Color::Black), content);
+    }
+
+    #[test]
+    fn scroll_horizontal_padding_and_rotation() {
+        // the padding of a 12 pixel row isn't shifted into the row
+        let mut buffer = [0u8; 2];
+        let mut display = VarDisplay::new(12, 1, &mut buffer);
+        display.get_mut_buffer().copy_from_slice(&[0xF0, 0x0F]);
+        display.scroll_horizontal(-4, Color::Black);
+        assert_eq!(display.buffer(), [0x00, 0x00]);
+
+        // rotated by 90 degrees the rows of the buffer move
+        let mut buffer = [0u8; 2];
+        let mut display = VarDisplay::new(8, 2, &mut buffer);
+        display.set_rotation(DisplayRotation::Rotate90);
+        display.get_mut_buffer().copy_from_slice(&[0x0F, 0x3C]);
+        display.scroll_horizontal(1, Color::White);
+        assert_eq!(display.buffer(), [0xFF, 0x0F]);
+    }
 }
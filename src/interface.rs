@@ -1,10 +1,242 @@
-use crate::traits::Command;
+use crate::traits::{Command, DisplayError, DisplayState, RefreshLut};
 use core::marker::PhantomData;
 use embedded_hal::{
-    blocking::{delay::*, spi::Write},
+    blocking::{
+        delay::*,
+        spi::{Transfer, Write},
+    },
     digital::v2::*,
 };
 
+/// Timing settings for the connection to the display
+///
+/// The defaults match what the drivers always did, only change them if your panel
+/// needs it. Set them with [WaveshareDisplay::set_interface_config](crate::prelude::WaveshareDisplay::set_interface_config)
+/// or [WaveshareDisplay::new_with_config](crate::prelude::WaveshareDisplay::new_with_config).
+///
+/// ```
+/// use epd_waveshare::prelude::*;
+///
+/// // pulse reset three times, keeping it low for 20ms each
+/// let config = InterfaceConfig::new().reset_pulses(3).reset_low_ms(20);
+/// ```
+// configs with hooks compare by the address of the function, which is good enough here
+#[cfg_attr(
+    any(feature = "trace", test),
+    allow(unpredictable_function_pointer_comparisons)
+)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceConfig {
+    reset_pulses: u8,
+    reset_high_ms: u8,
+    reset_low_ms: Option<u8>,
+    inter_byte_delay: Option<(u32, fn(u32))>,
+    vertical_flip: bool,
+    power_on_settle_ms: u8,
+    lut_load_order: Option<LutLoadOrder>,
+    busy_poll_interval_ms: u8,
+    chunk_size: usize,
+    #[cfg(any(feature = "trace", test))]
+    trace: Option<fn(Trace)>,
+}
+
+/// When the init sequence writes the LUT, relative to the power on of the controller
+///
+/// Set it with [InterfaceConfig::lut_load_order] if a panel comes out faint with the
+/// order the driver uses by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LutLoadOrder {
+    /// The LUT is written while the controller is still powered off
+    BeforePowerOn,
+    /// The LUT is written once the power on is done
+    AfterPowerOn,
+}
+
+/// Default number of data bytes sent with a single spi write, see [InterfaceConfig::chunk_size]
+pub const DEFAULT_CHUNK_SIZE: usize = 256;
+/// Longest chunk which is built on the stack, e.g. of a repeated byte while clearing a frame
+const MAX_REPEAT_CHUNK: usize = 256;
+
+/// A transfer to the display, passed to the hook of [InterfaceConfig::trace]
+///
+/// The opcodes are listed in the `command` module of the respective driver.
+#[cfg(any(feature = "trace", test))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trace {
+    /// A command with its opcode
+    Command(u8),
+    /// Data with the number of bytes sent
+    Data(usize),
+}
+
+impl InterfaceConfig {
+    /// Creates the default config
+    pub const fn new() -> Self {
+        InterfaceConfig {
+            reset_pulses: 1,
+            reset_high_ms: 10,
+            reset_low_ms: None,
+            inter_byte_delay: None,
+            vertical_flip: false,
+            power_on_settle_ms: 0,
+            lut_load_order: None,
+            busy_poll_interval_ms: 20,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            #[cfg(any(feature = "trace", test))]
+            trace: None,
+        }
+    }
+
+    /// Sets how often the reset pin is pulsed low during a reset (default: 1)
+    ///
+    /// Some panels come up more reliably with several pulses, e.g. at cold boot.
+    pub const fn reset_pulses(mut self, pulses: u8) -> Self {
+        self.reset_pulses = pulses;
+        self
+    }
+
+    /// Sets how long the reset pin is held high before every pulse (default: 10ms)
+    pub const fn reset_high_ms(mut self, ms: u8) -> Self {
+        self.reset_high_ms = ms;
+        self
+    }
+
+    /// Sets how long the reset pin is held low for every pulse
+    ///
+    /// Defaults to the duration recommended for the respective display.
+    pub const fn reset_low_ms(mut self, ms: u8) -> Self {
+        self.reset_low_ms = Some(ms);
+        self
+    }
+
+    /// Waits `us` microseconds between two bytes of data (default: no delay)
+    ///
+    /// Only needed for very slow connections, e.g. through level shifters which can't keep
+    /// up with back to back transfers. The data path has no access to the delay of the
+    /// driver, so `delay_us` has to block for the given number of microseconds itself,
+    /// e.g. by calling the busy wait of your HAL.
+    ///
+    /// ```
+    /// use epd_waveshare::prelude::*;
+    ///
+    /// fn busy_wait_us(us: u32) {
+    ///     // e.g. cortex_m::asm::delay(us * CYCLES_PER_US)
+    /// #   let _ = us;
+    /// }
+    ///
+    /// let config = InterfaceConfig::new().inter_byte_delay_us(5, busy_wait_us);
+    /// ```
+    pub const fn inter_byte_delay_us(mut self, us: u32, delay_us: fn(u32)) -> Self {
+        self.inter_byte_delay = if us == 0 { None } else { Some((us, delay_us)) };
+        self
+    }
+
+    /// Waits `ms` milliseconds after the power on of the init sequence (default: 0ms)
+    ///
+    /// The wait starts once the busy line reports the power on as done. Some units only
+    /// accept commands reliably a bit later, without the wait their first frame can come
+    /// out corrupted. Only used by the drivers which power the controller on during init.
+    pub const fn power_on_settle_ms(mut self, ms: u8) -> Self {
+        self.power_on_settle_ms = ms;
+        self
+    }
+
+    /// Waits `ms` milliseconds between two reads of the status while busy (default: 20ms)
+    ///
+    /// A shorter interval ends fast partial refreshes sooner, a longer one saves power
+    /// during slow full refreshes. Only used by the drivers which read the status while
+    /// waiting: Epd1in02 and Epd7in5 V2 with
+    /// [set_status_polling](crate::epd7in5_v2::Epd7in5::set_status_polling). The other
+    /// drivers poll the busy pin without a delay.
+    pub const fn busy_poll_interval_ms(mut self, ms: u8) -> Self {
+        self.busy_poll_interval_ms = ms;
+        self
+    }
+
+    /// Writes the LUT before or after the power on of the init sequence
+    ///
+    /// Defaults to the order of the reference code of the respective display. Some units of
+    /// a panel only take the LUT in the other order and show faint images otherwise. Only
+    /// used by the drivers which write a LUT and power the controller on during init:
+    /// Epd1in02, Epd1in54b, Epd2in13d, Epd2in7, Epd2in7b and Epd4in2.
+    pub const fn lut_load_order(mut self, order: LutLoadOrder) -> Self {
+        self.lut_load_order = Some(order);
+        self
+    }
+
+    /// Uploads full frames bottom row first (default: false)
+    ///
+    /// For panels or wirings which show the frame upside down. The rows are sent in reverse
+    /// order while uploading, so the buffer doesn't need to be flipped in a copy first.
+    /// Applies to `update_frame` and the plane uploads of the tri-color drivers, partial
+    /// frames are sent as they are.
+    pub const fn vertical_flip(mut self, flip: bool) -> Self {
+        self.vertical_flip = flip;
+        self
+    }
+
+    /// Sends data in chunks of up to `bytes` bytes per spi write (default: [DEFAULT_CHUNK_SIZE])
+    ///
+    /// Larger chunks mean fewer calls into the HAL, match it to the optimal transfer size of
+    /// your DMA. Repeated bytes, e.g. of `clear_frame`, are sent in chunks of at most 256
+    /// bytes, which are prepared on the stack. With an
+    /// [inter byte delay](Self::inter_byte_delay_us) every byte is sent on its own.
+    pub const fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = if bytes == 0 { 1 } else { bytes };
+        self
+    }
+
+    /// Calls `hook` for every command and every block of data sent to the display
+    ///
+    /// Meant for porting new panels, e.g. to compare the traffic with the reference code of
+    /// the vendor. Forward the [Trace] to `log` or `defmt` in the hook. Only available with
+    /// the `trace` feature.
+    ///
+    /// ```
+    /// # #[cfg(feature = "trace")] {
+    /// use epd_waveshare::{prelude::*, Trace};
+    ///
+    /// fn print_trace(trace: Trace) {
+    ///     // e.g. log::trace!("{:?}", trace)
+    /// #   let _ = trace;
+    /// }
+    ///
+    /// let config = InterfaceConfig::new().trace(print_trace);
+    /// # }
+    /// ```
+    #[cfg(any(feature = "trace", test))]
+    pub const fn trace(mut self, hook: fn(Trace)) -> Self {
+        self.trace = Some(hook);
+        self
+    }
+}
+
+impl Default for InterfaceConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What to do after a step of an [init table](DisplayInterface::run_init_table)
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Wait {
+    /// Continue with the next step right away
+    None,
+    /// Poll the busy pin until the display is idle
+    Idle,
+    /// Wait for a fixed number of milliseconds
+    Ms(u8),
+}
+
+/// How long [probe](DisplayInterface::probe) waits for the busy line to become idle
+const PROBE_TIMEOUT_MS: u8 = 200;
+/// How long [probe](DisplayInterface::probe) waits for the controller to become busy
+const PROBE_REACTION_MS: u8 = 10;
+
+/// A command address, the data following it and what to wait for afterwards
+pub(crate) type InitStep = (u8, &'static [u8], Wait);
+
 /// The Connection Interface of all (?) Waveshare EPD-Devices
 ///
 pub(crate) struct DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY> {
@@ -20,6 +252,16 @@ pub(crate) struct DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY> {
     dc: DC,
     /// Pin for Resetting
     rst: RST,
+    /// Timing settings
+    config: InterfaceConfig,
+    /// What the driver believes the panel is doing
+    state: DisplayState,
+    /// Shortest allowed time between two refreshes and the clock to measure it
+    min_refresh_interval: Option<(u32, fn() -> u32)>,
+    /// Time of the last refresh, read from the clock of `min_refresh_interval`
+    last_refresh_ms: Option<u32>,
+    /// A full refresh ran since the last init sequence or sleep
+    base_image: bool,
 }
 
 impl<SPI, CS, BUSY, DC, RST, DELAY> DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>
@@ -33,34 +275,136 @@ where
 {
     pub fn new(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
         DisplayInterface {
-            _spi: PhantomData::default(),
-            _delay: PhantomData::default(),
+            _spi: PhantomData,
+            _delay: PhantomData,
             cs,
             busy,
             dc,
             rst,
+            config: InterfaceConfig::new(),
+            state: DisplayState::Uninitialized,
+            min_refresh_interval: None,
+            last_refresh_ms: None,
+            base_image: false,
+        }
+    }
+
+    /// Replaces the timing settings
+    pub(crate) fn set_config(&mut self, config: InterfaceConfig) {
+        self.config = config;
+    }
+
+    /// Remembers that the init sequence of the driver ran
+    pub(crate) fn set_initialized(&mut self) {
+        self.state = DisplayState::Ready;
+        self.base_image = false;
+    }
+
+    /// Remembers that the panel was put to sleep, which loses the base image
+    pub(crate) fn set_sleeping(&mut self) {
+        self.state = DisplayState::Sleeping;
+        self.base_image = false;
+    }
+
+    /// Remembers that a refresh with `lut` was started, a full one leaves a base image
+    pub(crate) fn refreshed(&mut self, lut: RefreshLut) {
+        if lut == RefreshLut::Full {
+            self.base_image = true;
+        }
+    }
+
+    /// Whether a full refresh ran since the last init sequence or sleep
+    pub(crate) fn has_base_image(&self) -> bool {
+        self.base_image
+    }
+
+    /// Fails with [DisplayError::NotInitialized] until the init sequence ran
+    pub(crate) fn check_initialized(&self) -> Result<(), DisplayError<SPI::Error>> {
+        if self.state == DisplayState::Uninitialized {
+            Err(DisplayError::NotInitialized)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The state the driver believes the panel is in, a refresh ends once the busy line
+    /// reports idle
+    pub(crate) fn state(&self, is_busy_low: bool) -> DisplayState {
+        match self.state {
+            DisplayState::Refreshing if !self.is_busy(is_busy_low) => DisplayState::Ready,
+            state => state,
+        }
+    }
+
+    /// Refuses refreshes which follow the previous one within `interval_ms`, 0 disables it
+    pub(crate) fn set_min_refresh_interval(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.min_refresh_interval = if interval_ms == 0 {
+            None
+        } else {
+            Some((interval_ms, now_ms))
+        };
+        self.last_refresh_ms = None;
+    }
+
+    /// Fails with [DisplayError::RefreshTooSoon] if the last refresh was too recent,
+    /// otherwise remembers now as the time of the last refresh and that a refresh runs
+    pub(crate) fn check_refresh_interval(&mut self) -> Result<(), DisplayError<SPI::Error>> {
+        if let Some((interval_ms, now_ms)) = self.min_refresh_interval {
+            let now = now_ms();
+            if let Some(last) = self.last_refresh_ms {
+                // wrapping, so a clock overflowing in between doesn't block the refresh
+                if now.wrapping_sub(last) < interval_ms {
+                    return Err(DisplayError::RefreshTooSoon);
+                }
+            }
+            self.last_refresh_ms = Some(now);
         }
+        self.state = DisplayState::Refreshing;
+        Ok(())
     }
 
     /// Basic function for sending [Commands](Command).
     ///
     /// Enables direct interaction with the device with the help of [data()](DisplayInterface::data())
-    pub(crate) fn cmd<T: Command>(&mut self, spi: &mut SPI, command: T) -> Result<(), SPI::Error> {
+    pub(crate) fn cmd<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        let address = command.address();
         // low for commands
         let _ = self.dc.set_low();
+        #[cfg(any(feature = "trace", test))]
+        self.trace(Trace::Command(address));
 
         // Transfer the command over spi
-        self.write(spi, &[command.address()])
+        self.write(spi, &[address])
     }
 
     /// Basic function for sending an array of u8-values of data over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](Epd4in2::command())
-    pub(crate) fn data(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    pub(crate) fn data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
         // high for data
         let _ = self.dc.set_high();
+        #[cfg(any(feature = "trace", test))]
+        self.trace(Trace::Data(data.len()));
+
+        if self.config.inter_byte_delay.is_none() {
+            for chunk in data.chunks(self.config.chunk_size) {
+                self.write(spi, chunk)?;
+            }
+            return Ok(());
+        }
 
-        for val in data.iter().copied() {
+        for (i, val) in data.iter().copied().enumerate() {
+            if i > 0 {
+                self.inter_byte_delay();
+            }
             // Transfer data one u8 at a time over spi
             self.write(spi, &[val])?;
         }
@@ -68,6 +412,71 @@ where
         Ok(())
     }
 
+    /// Sends `data` with every bit inverted, for panels which take a set bit as black
+    ///
+    /// The bytes are inverted through a buffer on the stack and sent in chunks like
+    /// [data](DisplayInterface::data), but of at most 256 bytes.
+    pub(crate) fn data_inverted(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        // high for data
+        let _ = self.dc.set_high();
+        #[cfg(any(feature = "trace", test))]
+        self.trace(Trace::Data(data.len()));
+
+        let chunk_size = if self.config.inter_byte_delay.is_none() {
+            self.config.chunk_size.min(MAX_REPEAT_CHUNK)
+        } else {
+            1
+        };
+        let mut chunk = [0u8; MAX_REPEAT_CHUNK];
+        for (i, bytes) in data.chunks(chunk_size).enumerate() {
+            if i > 0 {
+                self.inter_byte_delay();
+            }
+            for (inverted, &byte) in chunk.iter_mut().zip(bytes) {
+                *inverted = !byte;
+            }
+            self.write(spi, &chunk[..bytes.len()])?;
+        }
+        Ok(())
+    }
+
+    /// Splits a whole frame of `rows` rows into the parts to send in this order
+    ///
+    /// Yields the frame as one part, or row by row from the bottom if the [InterfaceConfig]
+    /// asks for a vertical flip.
+    pub(crate) fn frame_rows<'b>(
+        &self,
+        buffer: &'b [u8],
+        rows: u32,
+    ) -> impl Iterator<Item = &'b [u8]> + 'b {
+        let part_len = if self.config.vertical_flip && rows > 1 {
+            buffer.len() / rows as usize
+        } else {
+            buffer.len()
+        };
+        buffer.chunks(part_len.max(1)).rev()
+    }
+
+    /// Sends a whole frame of `rows` rows as data, see [frame_rows](DisplayInterface::frame_rows)
+    pub(crate) fn frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        rows: u32,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        for (i, part) in self.frame_rows(buffer, rows).enumerate() {
+            if i > 0 {
+                self.inter_byte_delay();
+            }
+            self.data(spi, part)?;
+        }
+        Ok(())
+    }
+
     /// Basic function for sending [Commands](Command) and the data belonging to it.
     ///
     /// TODO: directly use ::write? cs wouldn't needed to be changed twice than
@@ -76,11 +485,27 @@ where
         spi: &mut SPI,
         command: T,
         data: &[u8],
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         self.cmd(spi, command)?;
         self.data(spi, data)
     }
 
+    /// Basic function for sending a [Command](Command) which is followed by a fixed delay
+    ///
+    /// Used where the datasheet asks for a waiting time instead of polling the busy pin,
+    /// e.g. because busy isn't reliable yet during that phase.
+    pub(crate) fn cmd_then_delay<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        command: T,
+        ms: u8,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.cmd(spi, command)?;
+        delay.delay_ms(ms);
+        Ok(())
+    }
+
     /// Basic function for sending the same byte of data (one u8) multiple times over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](ConnectionInterface::command())
@@ -89,18 +514,101 @@ where
         spi: &mut SPI,
         val: u8,
         repetitions: u32,
-    ) -> Result<(), SPI::Error> {
+    ) -> Result<(), DisplayError<SPI::Error>> {
         // high for data
         let _ = self.dc.set_high();
+        #[cfg(any(feature = "trace", test))]
+        self.trace(Trace::Data(repetitions as usize));
+        if self.config.inter_byte_delay.is_none() {
+            let chunk = [val; MAX_REPEAT_CHUNK];
+            let chunk_size = self.config.chunk_size.min(MAX_REPEAT_CHUNK);
+            let mut remaining = repetitions as usize;
+            while remaining > 0 {
+                let len = remaining.min(chunk_size);
+                self.write(spi, &chunk[..len])?;
+                remaining -= len;
+            }
+            return Ok(());
+        }
+
         // Transfer data (u8) over spi
-        for _ in 0..repetitions {
+        for i in 0..repetitions {
+            if i > 0 {
+                self.inter_byte_delay();
+            }
             self.write(spi, &[val])?;
         }
         Ok(())
     }
 
+    /// Waits the power on settle time of the [InterfaceConfig]
+    ///
+    /// Called by the init sequences right after the busy wait following the power on.
+    pub(crate) fn power_on_settle(&self, delay: &mut DELAY) {
+        if self.config.power_on_settle_ms > 0 {
+            delay.delay_ms(self.config.power_on_settle_ms);
+        }
+    }
+
+    /// Waits the busy poll interval of the [InterfaceConfig]
+    pub(crate) fn busy_poll_delay(&self, delay: &mut DELAY) {
+        if self.config.busy_poll_interval_ms > 0 {
+            delay.delay_ms(self.config.busy_poll_interval_ms);
+        }
+    }
+
+    /// Whether the init sequence writes the LUT before the power on
+    ///
+    /// `default` is the order the driver uses unless the [InterfaceConfig] picks one.
+    pub(crate) fn lut_before_power_on(&self, default: LutLoadOrder) -> bool {
+        self.config.lut_load_order.unwrap_or(default) == LutLoadOrder::BeforePowerOn
+    }
+
+    /// Waits between two bytes of data if the [InterfaceConfig] asks for it
+    fn inter_byte_delay(&self) {
+        if let Some((us, delay_us)) = self.config.inter_byte_delay {
+            delay_us(us);
+        }
+    }
+
+    /// Passes a transfer to the trace hook of the [InterfaceConfig]
+    #[cfg(any(feature = "trace", test))]
+    fn trace(&self, trace: Trace) {
+        if let Some(hook) = self.config.trace {
+            hook(trace);
+        }
+    }
+
+    /// Sends a static table of commands, e.g. the fixed part of an init sequence
+    ///
+    /// Keeping the sequence as data in flash instead of a chain of calls saves code size
+    /// and keeps the drivers short.
+    pub(crate) fn run_init_table(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        table: &[InitStep],
+        is_busy_low: bool,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        for &(command, data, wait) in table {
+            self.cmd(spi, command)?;
+            if !data.is_empty() {
+                self.data(spi, data)?;
+            }
+
+            match wait {
+                Wait::None => {}
+                Wait::Idle => self.wait_until_idle(is_busy_low),
+                Wait::Ms(ms) => delay.delay_ms(ms),
+            }
+        }
+        Ok(())
+    }
+
     // spi write helper/abstraction function
-    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), SPI::Error> {
+    //
+    // SPI errors are never retried, only blocking spi (which can't return WouldBlock) is supported
+    fn write(&mut self, spi: &mut SPI, data: &[u8]) -> Result<(), DisplayError<SPI::Error>> {
         // activate spi with cs low
         let _ = self.cs.set_low();
 
@@ -121,6 +629,69 @@ where
         Ok(())
     }
 
+    /// Resets the controller and checks that it reacts like a connected panel
+    ///
+    /// The busy line has to be idle after the reset, become busy after `command` and be
+    /// idle again afterwards. The controller is reset once more at the end, so it needs an
+    /// init before it is used.
+    pub(crate) fn probe<T: Command + Copy>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        command: T,
+        reset_ms: u8,
+        is_busy_low: bool,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.probe_with_status(spi, delay, command, None, reset_ms, is_busy_low)
+    }
+
+    /// Like [probe](DisplayInterface::probe), but sends `status` before every further read
+    /// of the busy line, for controllers which only update it after a status read
+    pub(crate) fn probe_with_status<T: Command + Copy>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        command: T,
+        status: Option<T>,
+        reset_ms: u8,
+        is_busy_low: bool,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        self.state = DisplayState::Uninitialized;
+        self.reset(delay, reset_ms);
+        if !self.poll_busy(spi, delay, status, is_busy_low, false, PROBE_TIMEOUT_MS)? {
+            // stuck busy
+            return Ok(false);
+        }
+
+        self.cmd(spi, command)?;
+        let present = self.poll_busy(spi, delay, status, is_busy_low, true, PROBE_REACTION_MS)?
+            && self.poll_busy(spi, delay, status, is_busy_low, false, PROBE_TIMEOUT_MS)?;
+        self.reset(delay, reset_ms);
+        Ok(present)
+    }
+
+    /// Polls the busy line about once per millisecond until it is `busy`, gives up after `timeout_ms`
+    fn poll_busy<T: Command + Copy>(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        status: Option<T>,
+        is_busy_low: bool,
+        busy: bool,
+        timeout_ms: u8,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        for _ in 0..timeout_ms {
+            if self.is_busy(is_busy_low) == busy {
+                return Ok(true);
+            }
+            if let Some(status) = status {
+                self.cmd(spi, status)?;
+            }
+            delay.delay_ms(1);
+        }
+        Ok(self.is_busy(is_busy_low) == busy)
+    }
+
     /// Waits until device isn't busy anymore (busy == HIGH)
     ///
     /// This is normally handled by the more complicated commands themselves,
@@ -137,10 +708,29 @@ where
     pub(crate) fn wait_until_idle(&mut self, is_busy_low: bool) {
         // //tested: worked without the delay for all tested devices
         // //self.delay_ms(1);
+        let mut waited = false;
         while self.is_busy(is_busy_low) {
             // //tested: REMOVAL of DELAY: it's only waiting for the signal anyway and should continue work asap
             // //old: shorten the time? it was 100 in the beginning
             // //self.delay_ms(5);
+            waited = true;
+        }
+        // drivers also wait right before starting a refresh, that wait doesn't end it
+        if waited && self.state == DisplayState::Refreshing {
+            self.state = DisplayState::Ready;
+        }
+    }
+
+    /// Waits like [wait_until_idle](DisplayInterface::wait_until_idle), but sleeps the busy
+    /// poll interval of the [InterfaceConfig] between two reads of the busy pin
+    pub(crate) fn wait_until_idle_with_delay(&mut self, is_busy_low: bool, delay: &mut DELAY) {
+        let mut waited = false;
+        while self.is_busy(is_busy_low) {
+            self.busy_poll_delay(delay);
+            waited = true;
+        }
+        if waited && self.state == DisplayState::Refreshing {
+            self.state = DisplayState::Ready;
         }
     }
 
@@ -169,15 +759,309 @@ where
     /// The timing of keeping the reset pin low seems to be important and different per device.
     /// Most displays seem to require keeping it low for 10ms, but the 7in5_v2 only seems to reset
     /// properly with 2ms
+    ///
+    /// `duration` is only used if the [InterfaceConfig] doesn't override the low time.
     pub(crate) fn reset(&mut self, delay: &mut DELAY, duration: u8) {
-        let _ = self.rst.set_high();
-        delay.delay_ms(10);
+        let low_ms = self.config.reset_low_ms.unwrap_or(duration);
+        for _ in 0..self.config.reset_pulses {
+            let _ = self.rst.set_high();
+            delay.delay_ms(self.config.reset_high_ms);
 
-        let _ = self.rst.set_low();
-        delay.delay_ms(duration);
+            let _ = self.rst.set_low();
+            delay.delay_ms(low_ms);
+        }
         let _ = self.rst.set_high();
         //TODO: the upstream libraries always sleep for 200ms here
         // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
         delay.delay_ms(200);
     }
 }
+
+impl<SPI, CS, BUSY, DC, RST, DELAY> DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8> + Transfer<u8, Error = <SPI as Write<u8>>::Error>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Reads `buffer.len()` bytes of data, e.g. after a command returning a value
+    ///
+    /// Only works if the data line of the controller is connected to MISO,
+    /// which isn't the case for most Waveshare boards.
+    pub(crate) fn read(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &mut [u8],
+    ) -> Result<(), DisplayError<<SPI as Write<u8>>::Error>> {
+        // high for data
+        let _ = self.dc.set_high();
+
+        let _ = self.cs.set_low();
+        let result = spi.transfer(buffer);
+        let _ = self.cs.set_high();
+
+        result?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Event, MockDelay, MockPin, MockSpi, Recorder};
+
+    #[test]
+    fn init_table_replays_steps() {
+        const TABLE: &[InitStep] = &[
+            (0x00, &[0x1F, 0x0D], Wait::None),
+            (0x04, &[], Wait::Idle),
+            (0x50, &[0x97], Wait::Ms(10)),
+        ];
+
+        // busy for one poll after the power on
+        let mock = Recorder::new(true);
+        mock.busy_for(1);
+        let mut interface: DisplayInterface<
+            MockSpi,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockDelay,
+        > = DisplayInterface::new(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        interface
+            .run_init_table(&mut mock.spi(), &mut mock.delay(), TABLE, true)
+            .unwrap();
+
+        assert_eq!(
+            mock.events(),
+            [
+                Event::Command(0x00),
+                Event::data(&[0x1F, 0x0D]),
+                Event::Command(0x04),
+                Event::Command(0x50),
+                Event::data(&[0x97]),
+                Event::Delay(10),
+            ]
+        );
+    }
+
+    #[test]
+    fn inter_byte_delay_between_bytes() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        static CALLS: AtomicU32 = AtomicU32::new(0);
+        static TOTAL_US: AtomicU32 = AtomicU32::new(0);
+        fn delay_us(us: u32) {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            TOTAL_US.fetch_add(us, Ordering::SeqCst);
+        }
+
+        let mock = Recorder::new(true);
+        let mut interface: DisplayInterface<
+            MockSpi,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockDelay,
+        > = DisplayInterface::new(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        // no delay by default
+        interface.data(&mut mock.spi(), &[1, 2, 3]).unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+
+        interface.set_config(InterfaceConfig::new().inter_byte_delay_us(5, delay_us));
+        interface.data(&mut mock.spi(), &[1, 2, 3]).unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+        interface.data_x_times(&mut mock.spi(), 0xFF, 4).unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 5);
+        assert_eq!(TOTAL_US.load(Ordering::SeqCst), 25);
+
+        // the bytes themselves are unchanged
+        assert_eq!(
+            mock.events(),
+            [Event::data(&[1, 2, 3, 1, 2, 3, 0xFF, 0xFF, 0xFF, 0xFF])]
+        );
+    }
+
+    #[test]
+    fn data_is_sent_in_chunks() {
+        let mock = Recorder::new(true);
+        let mut interface: DisplayInterface<
+            MockSpi,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockDelay,
+        > = DisplayInterface::new(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        let data = [0x5A; 600];
+        interface.data(&mut mock.spi(), &data).unwrap();
+        assert_eq!(mock.writes(), 3);
+
+        interface.set_config(InterfaceConfig::new().chunk_size(64));
+        interface.data(&mut mock.spi(), &data).unwrap();
+        assert_eq!(mock.writes(), 3 + 10);
+        interface.data_x_times(&mut mock.spi(), 0x5A, 100).unwrap();
+        assert_eq!(mock.writes(), 3 + 10 + 2);
+
+        // repeated bytes are limited by the chunk on the stack
+        interface.set_config(InterfaceConfig::new().chunk_size(1000));
+        interface.data_x_times(&mut mock.spi(), 0x5A, 600).unwrap();
+        assert_eq!(mock.writes(), 3 + 10 + 2 + 3);
+
+        // the bytes themselves are unchanged
+        assert_eq!(mock.events(), [Event::data(&[0x5A; 1900])]);
+    }
+
+    #[test]
+    fn inverted_data_is_sent_in_chunks() {
+        let mock = Recorder::new(true);
+        let mut interface: DisplayInterface<
+            MockSpi,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockDelay,
+        > = DisplayInterface::new(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        let data = [0x5A; 600];
+        interface.data_inverted(&mut mock.spi(), &data).unwrap();
+        assert_eq!(mock.writes(), 3);
+
+        interface.set_config(InterfaceConfig::new().chunk_size(64));
+        interface.data_inverted(&mut mock.spi(), &data).unwrap();
+        assert_eq!(mock.writes(), 3 + 10);
+
+        // limited by the buffer on the stack
+        interface.set_config(InterfaceConfig::new().chunk_size(1000));
+        interface.data_inverted(&mut mock.spi(), &data).unwrap();
+        assert_eq!(mock.writes(), 3 + 10 + 3);
+
+        assert_eq!(mock.events(), [Event::data(&[0xA5; 1800])]);
+    }
+
+    #[test]
+    fn trace_reports_commands_and_data() {
+        use std::cell::RefCell;
+        use std::vec::Vec;
+
+        std::thread_local! {
+            static TRACES: RefCell<Vec<Trace>> = const { RefCell::new(Vec::new()) };
+        }
+        fn capture(trace: Trace) {
+            TRACES.with(|traces| traces.borrow_mut().push(trace));
+        }
+
+        let mock = Recorder::new(true);
+        let mut interface: DisplayInterface<
+            MockSpi,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockDelay,
+        > = DisplayInterface::new(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        // nothing is traced without a hook
+        interface
+            .cmd_with_data(&mut mock.spi(), 0x12, &[1])
+            .unwrap();
+        assert!(TRACES.with(|traces| traces.borrow().is_empty()));
+
+        interface.set_config(InterfaceConfig::new().trace(capture));
+        interface
+            .cmd_with_data(&mut mock.spi(), 0x24, &[1, 2, 3])
+            .unwrap();
+        interface.data_x_times(&mut mock.spi(), 0xFF, 4).unwrap();
+        assert_eq!(
+            TRACES.with(|traces| traces.borrow().clone()),
+            [Trace::Command(0x24), Trace::Data(3), Trace::Data(4)]
+        );
+
+        // the steps of an init table are traced like single commands
+        TRACES.with(|traces| traces.borrow_mut().clear());
+        const TABLE: &[InitStep] = &[(0x01, &[0x07, 0x08], Wait::None), (0x04, &[], Wait::Ms(1))];
+        interface
+            .run_init_table(&mut mock.spi(), &mut mock.delay(), TABLE, true)
+            .unwrap();
+        assert_eq!(
+            TRACES.with(|traces| traces.borrow().clone()),
+            [Trace::Command(0x01), Trace::Data(2), Trace::Command(0x04)]
+        );
+    }
+
+    #[test]
+    fn probe_responsive_and_dead_panel() {
+        let probe = |mock: &Recorder| {
+            let mut interface: DisplayInterface<
+                MockSpi,
+                MockPin,
+                MockPin,
+                MockPin,
+                MockPin,
+                MockDelay,
+            > = DisplayInterface::new(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+            interface
+                .probe(&mut mock.spi(), &mut mock.delay(), 0x12, 2, false)
+                .unwrap()
+        };
+
+        // idle after the reset, busy for a moment after the command
+        let responsive = Recorder::new(false);
+        responsive.idle_for(1);
+        responsive.busy_for(3);
+        assert!(probe(&responsive));
+        assert_eq!(responsive.commands(), [0x12]);
+        // reset before and after
+        let resets = responsive
+            .events()
+            .iter()
+            .filter(|event| **event == Event::Reset(false))
+            .count();
+        assert_eq!(resets, 2);
+
+        // the busy line never changes
+        let dead = Recorder::new(false);
+        assert!(!probe(&dead));
+
+        let stuck = Recorder::new(false);
+        stuck.busy_for(300);
+        assert!(!probe(&stuck));
+        assert_eq!(stuck.commands(), []);
+    }
+
+    #[test]
+    fn probe_reads_the_status_while_polling() {
+        let mock = Recorder::new(false);
+        let mut interface: DisplayInterface<
+            MockSpi,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockPin,
+            MockDelay,
+        > = DisplayInterface::new(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        mock.idle_for(1);
+        mock.busy_for(3);
+        let present = interface
+            .probe_with_status(
+                &mut mock.spi(),
+                &mut mock.delay(),
+                0x04,
+                Some(0x71),
+                2,
+                false,
+            )
+            .unwrap();
+        assert!(present);
+        // the first busy read reacts to the command, a status read before each further one
+        assert_eq!(mock.commands(), [0x04, 0x71, 0x71]);
+    }
+}
@@ -12,7 +12,7 @@
 //!
 //!```rust, no_run
 //!# use embedded_hal_mock::*;
-//!# fn main() -> Result<(), MockError> {
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
 //!use embedded_graphics::{
 //!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 //!};
@@ -56,13 +56,28 @@
 //!
 //! ### SPI
 //!
-//! MISO is not connected/available. SPI_MODE_0 is used (CPHL = 0, CPOL = 0) with 8 bits per word, MSB first.
+//! MISO is not connected/available. SPI_MODE_0 ([SPI_MODE]) is used (CPHL = 0, CPOL = 0) with 8 bits per word, MSB first.
 //!
 //! Maximum speed tested by myself was 8Mhz but more should be possible (Ben Krasnow used 18Mhz with his implemenation)
 //!
+//! Only blocking SPI is supported, which is why all drivers require [`embedded_hal::blocking::spi::Write`].
+//! Every error returned by the SPI peripheral is treated as a hard error and passed on to the caller,
+//! there are no retries. nb-style peripherals can be used through the
+//! [`blocking::spi::write::Default`](embedded_hal::blocking::spi::write::Default) adapter of `embedded-hal`,
+//! which already waits on `WouldBlock` by itself. A blocking adapter around an async or nb driver
+//! must never return a transient "not ready" condition as an error.
+//!
+//! ### Features
+//!
+//! - `graphics` (default): drawing with embedded-graphics, see the `graphics` module
+//! - `std`: writing display buffers as PBM/PGM images on the host, e.g. `Display::write_pbm`
+//!
 #![no_std]
 #![deny(missing_docs)]
 
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
 #[cfg(feature = "graphics")]
 pub mod graphics;
 
@@ -70,38 +85,72 @@ mod traits;
 
 pub mod color;
 
+pub mod framebuffer;
+
 /// Interface for the physical connection between display and the controlling device
 mod interface;
+#[cfg(feature = "trace")]
+pub use crate::interface::Trace;
+pub use crate::interface::{InterfaceConfig, LutLoadOrder, DEFAULT_CHUNK_SIZE};
+pub use crate::traits::{update_and_display_pair, DisplayError};
 
+pub mod epd1in02;
 pub mod epd1in54;
+pub mod epd1in54_v2;
 pub mod epd1in54b;
 pub mod epd1in54c;
 pub mod epd2in13_v2;
 pub mod epd2in13bc;
+pub mod epd2in13d;
+pub mod epd2in36g;
+pub mod epd2in7;
 pub mod epd2in7b;
 pub mod epd2in9;
 pub mod epd2in9_v2;
 pub mod epd2in9bc;
+pub mod epd2in9d;
+pub mod epd3in52;
+pub mod epd4in01f;
 pub mod epd4in2;
 pub mod epd5in65f;
+pub mod epd5in79;
 pub mod epd5in83b_v2;
+pub mod epd5in83g;
 pub mod epd7in5;
 pub mod epd7in5_hd;
 pub mod epd7in5_v2;
 
+pub mod owned;
+
+pub mod panel_setting;
+pub use crate::panel_setting::PanelSettingBuilder;
+
+pub mod rle;
+
 pub(crate) mod type_a;
+pub(crate) mod type_d;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(all(test, feature = "graphics"))]
+mod golden;
 
 /// Includes everything important besides the chosen Display
 pub mod prelude {
-    pub use crate::color::{Color, OctColor, TriColor};
+    pub use crate::color::{ChromaticColor, Color, OctColor, QuadColor, TriColor, TriOverlay};
     pub use crate::traits::{
-        QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+        ColorPlane, DirectRam, DisplayError, DisplayState, DriverOutputControl, QuickRefresh,
+        RefreshLut, SplitRefresh, WaveshareDisplay, WaveshareThreeColorDisplay,
     };
 
+    pub use crate::interface::{InterfaceConfig, LutLoadOrder};
     pub use crate::SPI_MODE;
 
     #[cfg(feature = "graphics")]
-    pub use crate::graphics::{Display, DisplayRotation, OctDisplay, TriDisplay};
+    pub use crate::graphics::{
+        Display, DisplayRotation, GrayDisplay, OctDisplay, QuadDisplay, TestPattern, TriDisplay,
+    };
 }
 
 /// Computes the needed buffer length. Takes care of rounding up in case width
@@ -114,14 +163,27 @@ pub mod prelude {
 /// \[XXXXX210\]\[76543210\]...\[76543210\] | height
 /// \[XXXXX210\]\[76543210\]...\[76543210\] v
 pub const fn buffer_len(width: usize, height: usize) -> usize {
-    (width + 7) / 8 * height
+    width.div_ceil(8) * height
 }
 
 use embedded_hal::spi::{Mode, Phase, Polarity};
 
-/// SPI mode -
+/// SPI mode the displays expect (mode 0: CPOL = 0, CPHA = 0)
+///
+/// The drivers can't configure the bus themselves, so make sure the SPI peripheral is set
+/// up with this mode, otherwise the panels show garbage.
 /// For more infos see [Requirements: SPI](index.html#spi)
 pub const SPI_MODE: Mode = Mode {
     phase: Phase::CaptureOnFirstTransition,
     polarity: Polarity::IdleLow,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spi_mode_is_mode_0() {
+        assert!(SPI_MODE == embedded_hal::spi::MODE_0);
+    }
+}
@@ -0,0 +1,258 @@
+//! Recording test doubles for the display interface
+//!
+//! All pins, the spi bus and the delay share one event log, so a test can assert
+//! on the exact command/data sequence a driver sends to the controller.
+
+extern crate std;
+
+use core::convert::Infallible;
+use embedded_hal::{
+    blocking::{
+        delay::DelayMs,
+        spi::{Transfer, Write},
+    },
+    digital::v2::{InputPin, OutputPin},
+};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, vec::Vec};
+
+/// Something the driver did to the hardware
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Event {
+    /// A byte sent while DC was low
+    Command(u8),
+    /// Consecutive bytes sent while DC was high
+    Data(Vec<u8>),
+    /// A level written to the reset pin
+    Reset(bool),
+    /// A blocking delay in milliseconds
+    Delay(u32),
+    /// A read of the busy pin, only recorded after [log_busy_reads](Recorder::log_busy_reads)
+    BusyRead,
+}
+
+impl Event {
+    pub fn data(bytes: &[u8]) -> Self {
+        Event::Data(bytes.to_vec())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    events: Vec<Event>,
+    dc_high: bool,
+    busy_idle: bool,
+    busy_script: VecDeque<bool>,
+    read_script: VecDeque<u8>,
+    failing_writes: usize,
+    writes: usize,
+    log_busy_reads: bool,
+}
+
+/// Hands out connected spi, pin and delay doubles
+#[derive(Clone, Default)]
+pub(crate) struct Recorder(Rc<RefCell<State>>);
+
+impl Recorder {
+    /// Creates a recorder whose busy pin reports an idle display, taking the
+    /// polarity of the busy line into account
+    pub fn new(is_busy_low: bool) -> Self {
+        let recorder = Recorder::default();
+        recorder.0.borrow_mut().busy_idle = is_busy_low;
+        recorder
+    }
+
+    pub fn spi(&self) -> MockSpi {
+        MockSpi(self.clone())
+    }
+
+    pub fn cs(&self) -> MockPin {
+        MockPin(self.clone(), Role::Cs)
+    }
+
+    pub fn busy(&self) -> MockPin {
+        MockPin(self.clone(), Role::Busy)
+    }
+
+    pub fn dc(&self) -> MockPin {
+        MockPin(self.clone(), Role::Dc)
+    }
+
+    pub fn rst(&self) -> MockPin {
+        MockPin(self.clone(), Role::Rst)
+    }
+
+    pub fn delay(&self) -> MockDelay {
+        MockDelay(self.clone())
+    }
+
+    /// Lets the next `reads` busy pin reads report a busy display
+    pub fn busy_for(&self, reads: usize) {
+        let mut state = self.0.borrow_mut();
+        let busy = !state.busy_idle;
+        state.busy_script.extend(core::iter::repeat_n(busy, reads));
+    }
+
+    /// Lets the next `reads` busy pin reads report an idle display, e.g. a glitch after [busy_for](Recorder::busy_for)
+    pub fn idle_for(&self, reads: usize) {
+        let mut state = self.0.borrow_mut();
+        let idle = state.busy_idle;
+        state.busy_script.extend(core::iter::repeat_n(idle, reads));
+    }
+
+    /// Lets the next `writes` spi writes fail with [SpiFault], nothing of them is recorded
+    pub fn fail_writes(&self, writes: usize) {
+        self.0.borrow_mut().failing_writes += writes;
+    }
+
+    /// Records every read of the busy pin as [Event::BusyRead] from now on
+    pub fn log_busy_reads(&self) {
+        self.0.borrow_mut().log_busy_reads = true;
+    }
+
+    /// Lets the next spi reads return `bytes`, reads return 0 afterwards
+    pub fn reply(&self, bytes: &[u8]) {
+        self.0.borrow_mut().read_script.extend(bytes);
+    }
+
+    /// Number of spi writes, each write sends a chunk of bytes
+    pub fn writes(&self) -> usize {
+        self.0.borrow().writes
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.0.borrow().events.clone()
+    }
+
+    /// Every command byte in the order it was sent
+    pub fn commands(&self) -> Vec<u8> {
+        self.0
+            .borrow()
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Command(command) => Some(*command),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Data sent directly after the first occurrence of `command`
+    pub fn data_after(&self, command: u8) -> Option<Vec<u8>> {
+        let state = self.0.borrow();
+        let mut events = state
+            .events
+            .iter()
+            .filter(|event| !matches!(event, Event::Delay(_) | Event::Reset(_)))
+            .skip_while(|event| **event != Event::Command(command));
+        events.next()?;
+        match events.next() {
+            Some(Event::Data(data)) => Some(data.clone()),
+            _ => Some(Vec::new()),
+        }
+    }
+
+    /// Forgets everything recorded so far
+    pub fn clear(&self) {
+        self.0.borrow_mut().events.clear();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Cs,
+    Busy,
+    Dc,
+    Rst,
+}
+
+/// The error of the spi writes which [fail_writes](Recorder::fail_writes) lets fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SpiFault;
+
+pub(crate) struct MockSpi(Recorder);
+
+impl Write<u8> for MockSpi {
+    type Error = SpiFault;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let mut state = (self.0).0.borrow_mut();
+        if state.failing_writes > 0 {
+            state.failing_writes -= 1;
+            return Err(SpiFault);
+        }
+        state.writes += 1;
+        if !state.dc_high {
+            let commands = words.iter().map(|&word| Event::Command(word));
+            state.events.extend(commands);
+        } else if let Some(Event::Data(data)) = state.events.last_mut() {
+            data.extend_from_slice(words);
+        } else {
+            state.events.push(Event::Data(words.to_vec()));
+        }
+        Ok(())
+    }
+}
+
+impl Transfer<u8> for MockSpi {
+    type Error = SpiFault;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        let mut state = (self.0).0.borrow_mut();
+        for word in words.iter_mut() {
+            *word = state.read_script.pop_front().unwrap_or(0);
+        }
+        Ok(words)
+    }
+}
+
+pub(crate) struct MockPin(Recorder, Role);
+
+impl MockPin {
+    fn set(&mut self, high: bool) {
+        let mut state = (self.0).0.borrow_mut();
+        match self.1 {
+            Role::Dc => state.dc_high = high,
+            Role::Rst => state.events.push(Event::Reset(high)),
+            Role::Cs | Role::Busy => (),
+        }
+    }
+}
+
+impl OutputPin for MockPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set(true);
+        Ok(())
+    }
+}
+
+impl InputPin for MockPin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        let mut state = (self.0).0.borrow_mut();
+        if state.log_busy_reads {
+            state.events.push(Event::BusyRead);
+        }
+        let idle = state.busy_idle;
+        Ok(state.busy_script.pop_front().unwrap_or(idle))
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+pub(crate) struct MockDelay(Recorder);
+
+impl DelayMs<u8> for MockDelay {
+    fn delay_ms(&mut self, ms: u8) {
+        (self.0).0.borrow_mut().events.push(Event::Delay(ms.into()));
+    }
+}
@@ -0,0 +1,394 @@
+//! Driver wrapper which owns the SPI peripheral and the delay
+//!
+//! Threading `&mut spi` and `&mut delay` through every call is needed as soon as the
+//! SPI bus is shared with other devices. With only a single display on the bus,
+//! [OwnedEpd] keeps both and saves repeating them.
+//!
+//! It can also put the display to sleep once it wasn't updated for a while, see
+//! [set_auto_sleep](OwnedEpd::set_auto_sleep).
+//!
+//! # Example
+//!
+//!```rust, no_run
+//!# use embedded_hal_mock::*;
+//!# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
+//!use embedded_graphics::{
+//!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
+//!};
+//!use epd_waveshare::{epd4in2::*, owned::OwnedEpd, prelude::*};
+//!#
+//!# let expectations = [];
+//!# let spi = spi::Mock::new(&expectations);
+//!# let expectations = [];
+//!# let cs_pin = pin::Mock::new(&expectations);
+//!# let busy_in = pin::Mock::new(&expectations);
+//!# let dc = pin::Mock::new(&expectations);
+//!# let rst = pin::Mock::new(&expectations);
+//!# let delay = delay::MockNoop::new();
+//!
+//!let mut epd = OwnedEpd::<Epd4in2<_, _, _, _, _, _>, _, _>::connect(spi, cs_pin, busy_in, dc, rst, delay)?;
+//!
+//!let mut display = Display4in2::default();
+//!let _ = Line::new(Point::new(0, 120), Point::new(0, 295))
+//!    .into_styled(PrimitiveStyle::with_stroke(Black, 1))
+//!    .draw(&mut display);
+//!
+//!epd.update_and_display_frame(display.buffer())?;
+//!epd.sleep()?;
+//!
+//!// get the SPI back
+//!let (_epd, _spi, _delay) = epd.release();
+//!# Ok(())
+//!# }
+//!```
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::*,
+};
+
+use crate::traits::{DisplayError, WaveshareDisplay};
+
+/// A driver together with the SPI peripheral and delay it uses
+///
+/// The methods mirror the ones of [WaveshareDisplay] without the `spi` and `delay` arguments.
+/// Everything else is reachable through [epd](OwnedEpd::epd) and [epd_mut](OwnedEpd::epd_mut).
+pub struct OwnedEpd<EPD, SPI, DELAY> {
+    epd: EPD,
+    spi: SPI,
+    delay: DELAY,
+    /// Idle time in ms after which the display is put to sleep
+    auto_sleep: Option<u32>,
+    /// Last time passed to [tick](OwnedEpd::tick)
+    now: u32,
+    /// Time of the last update of the display
+    last_update: u32,
+    asleep: bool,
+}
+
+impl<EPD, SPI, DELAY> OwnedEpd<EPD, SPI, DELAY> {
+    /// Wraps an already created driver
+    pub fn new(epd: EPD, spi: SPI, delay: DELAY) -> Self {
+        OwnedEpd {
+            epd,
+            spi,
+            delay,
+            auto_sleep: None,
+            now: 0,
+            last_update: 0,
+            asleep: false,
+        }
+    }
+
+    /// Creates and initialises a new driver, see [WaveshareDisplay::new]
+    pub fn connect<CS, BUSY, DC, RST>(
+        mut spi: SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        mut delay: DELAY,
+    ) -> Result<Self, DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        let epd = EPD::new(&mut spi, cs, busy, dc, rst, &mut delay)?;
+        Ok(Self::new(epd, spi, delay))
+    }
+
+    /// Puts the display to sleep once it wasn't updated for `idle_ms`, `None` disables this
+    ///
+    /// There is no clock in embedded-hal, so the time has to be passed in with
+    /// [tick](OwnedEpd::tick). The display is put to sleep by the first tick after the idle
+    /// time passed, the next update wakes it up again.
+    pub fn set_auto_sleep(&mut self, idle_ms: Option<u32>) {
+        self.auto_sleep = idle_ms;
+    }
+
+    /// Advances the time to `now_ms` and puts the display to sleep if it was idle long enough
+    ///
+    /// `now_ms` is a timestamp from any monotonic millisecond timer, it may wrap around.
+    /// Returns true if the display was put to sleep by this call.
+    pub fn tick<CS, BUSY, DC, RST>(&mut self, now_ms: u32) -> Result<bool, DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.now = now_ms;
+        match self.auto_sleep {
+            Some(idle_ms) if !self.asleep && now_ms.wrapping_sub(self.last_update) >= idle_ms => {
+                self.sleep()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Returns true if the display was put to sleep and not woken up since
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
+
+    /// Remembers an update of the display at the time of the last tick
+    ///
+    /// Wakes the display up first if it was put to sleep.
+    fn touch<CS, BUSY, DC, RST>(&mut self) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        if self.asleep {
+            self.wake_up()?;
+        }
+        self.last_update = self.now;
+        Ok(())
+    }
+
+    /// Returns the driver, the SPI peripheral and the delay
+    pub fn release(self) -> (EPD, SPI, DELAY) {
+        (self.epd, self.spi, self.delay)
+    }
+
+    /// Returns the wrapped driver
+    pub fn epd(&self) -> &EPD {
+        &self.epd
+    }
+
+    /// Returns the wrapped driver mutably
+    pub fn epd_mut(&mut self) -> &mut EPD {
+        &mut self.epd
+    }
+
+    /// See [WaveshareDisplay::update_frame]
+    pub fn update_frame<CS, BUSY, DC, RST>(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.touch()?;
+        self.epd
+            .update_frame(&mut self.spi, buffer, &mut self.delay)
+    }
+
+    /// See [WaveshareDisplay::display_frame]
+    pub fn display_frame<CS, BUSY, DC, RST>(&mut self) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.touch()?;
+        self.epd.display_frame(&mut self.spi, &mut self.delay)
+    }
+
+    /// See [WaveshareDisplay::update_and_display_frame]
+    pub fn update_and_display_frame<CS, BUSY, DC, RST>(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.touch()?;
+        self.epd
+            .update_and_display_frame(&mut self.spi, buffer, &mut self.delay)
+    }
+
+    /// See [WaveshareDisplay::clear_frame]
+    pub fn clear_frame<CS, BUSY, DC, RST>(&mut self) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.touch()?;
+        self.epd.clear_frame(&mut self.spi, &mut self.delay)
+    }
+
+    /// See [WaveshareDisplay::clear_and_display]
+    pub fn clear_and_display<CS, BUSY, DC, RST>(&mut self) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.touch()?;
+        self.epd.clear_and_display(&mut self.spi, &mut self.delay)
+    }
+
+    /// See [WaveshareDisplay::sleep]
+    pub fn sleep<CS, BUSY, DC, RST>(&mut self) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.epd.sleep(&mut self.spi, &mut self.delay)?;
+        self.asleep = true;
+        Ok(())
+    }
+
+    /// See [WaveshareDisplay::wake_up]
+    pub fn wake_up<CS, BUSY, DC, RST>(&mut self) -> Result<(), DisplayError<SPI::Error>>
+    where
+        SPI: Write<u8>,
+        CS: OutputPin,
+        BUSY: InputPin,
+        DC: OutputPin,
+        RST: OutputPin,
+        DELAY: DelayMs<u8>,
+        EPD: WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>,
+    {
+        self.epd.wake_up(&mut self.spi, &mut self.delay)?;
+        self.last_update = self.now;
+        self.asleep = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::epd4in2::Epd4in2;
+    use crate::mock::{Event, MockDelay, MockPin, MockSpi, Recorder};
+
+    type Epd = Epd4in2<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay>;
+
+    #[test]
+    fn connect_initialises() {
+        let mock = Recorder::new(true);
+        let _epd = OwnedEpd::<Epd, _, _>::connect(
+            mock.spi(),
+            mock.cs(),
+            mock.busy(),
+            mock.dc(),
+            mock.rst(),
+            mock.delay(),
+        )
+        .unwrap();
+
+        assert_eq!(mock.events()[0], Event::Reset(true));
+        assert!(mock.commands().contains(&0x04));
+    }
+
+    #[test]
+    fn calls_without_spi_and_delay() {
+        let mock = Recorder::new(true);
+        let mut epd: Epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let mut epd = OwnedEpd::new(epd, mock.spi(), mock.delay());
+
+        epd.display_frame().unwrap();
+        assert_eq!(mock.events(), [Event::Command(0x12)]);
+
+        mock.clear();
+        epd.sleep().unwrap();
+        assert_eq!(mock.commands().last(), Some(&0x07));
+
+        let (_epd, _spi, _delay) = epd.release();
+    }
+
+    #[test]
+    fn auto_sleep_after_idle_time() {
+        let mock = Recorder::new(true);
+        let mut epd: Epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let mut epd = OwnedEpd::new(epd, mock.spi(), mock.delay());
+        epd.set_auto_sleep(Some(1000));
+
+        assert!(!epd.tick(100).unwrap());
+        epd.display_frame().unwrap();
+        assert!(!epd.tick(1099).unwrap());
+        assert!(!epd.is_asleep());
+        assert!(!mock.commands().contains(&0x07));
+
+        assert!(epd.tick(1100).unwrap());
+        assert!(epd.is_asleep());
+        assert_eq!(mock.commands().last(), Some(&0x07));
+
+        // only once
+        mock.clear();
+        assert!(!epd.tick(5000).unwrap());
+        assert_eq!(mock.events(), []);
+
+        // the timer may wrap around
+        epd.wake_up().unwrap();
+        epd.tick(u32::MAX).unwrap();
+        epd.display_frame().unwrap();
+        assert!(!epd.tick(998).unwrap());
+        assert!(epd.tick(999).unwrap());
+    }
+
+    #[test]
+    fn update_after_auto_sleep_wakes_up() {
+        let mock = Recorder::new(true);
+        let mut epd: Epd = Epd4in2::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        let mut epd = OwnedEpd::new(epd, mock.spi(), mock.delay());
+        epd.set_auto_sleep(Some(1000));
+        assert!(epd.tick(1000).unwrap());
+
+        mock.clear();
+        let buffer = [0xFF; crate::epd4in2::WIDTH as usize / 8 * crate::epd4in2::HEIGHT as usize];
+        epd.update_frame(&buffer).unwrap();
+        assert!(!epd.is_asleep());
+        // reset and init sequence before the frame
+        let events = mock.events();
+        assert_eq!(events[0], Event::Reset(true));
+        let commands = mock.commands();
+        let power_on = commands.iter().position(|c| *c == 0x04).unwrap();
+        let frame = commands.iter().position(|c| *c == 0x13).unwrap();
+        assert!(power_on < frame);
+
+        // awake now, the next update is sent right away
+        mock.clear();
+        epd.display_frame().unwrap();
+        assert_eq!(mock.events(), [Event::Command(0x12)]);
+    }
+}
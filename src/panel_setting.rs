@@ -0,0 +1,136 @@
+//! Named bits of the panel setting register of the UC81xx controllers
+//!
+//! Most panels with a `PanelSetting` (0x00) command, e.g. the 1.54" B, 2.13" B/C and D,
+//! 2.9" B/C and D, 4.2", 5.83" B V2 and 7.5" V2, take the same layout for its first byte.
+//! The drivers build it with [PanelSettingBuilder], which can also be used to send an own
+//! setting with [update_raw](crate::traits::WaveshareDisplay::update_raw).
+//!
+//! ```
+//! use epd_waveshare::PanelSettingBuilder;
+//!
+//! // black/white with the LUT from the registers, as the 4.2" uses it
+//! const SETTING: u8 = PanelSettingBuilder::new()
+//!     .black_white(true)
+//!     .register_lut(true)
+//!     .build();
+//! assert_eq!(SETTING, 0x3F);
+//! ```
+
+/// Builds the first byte of the panel setting
+///
+/// Starts from `0x0F`: resolution bits 0, LUT from OTP, black/white/red mode, gates
+/// scanning up, sources shifting right, booster on and no soft reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelSettingBuilder(u8);
+
+impl Default for PanelSettingBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PanelSettingBuilder {
+    const RES: u8 = 0xC0;
+    const REG: u8 = 0x20;
+    const KW: u8 = 0x10;
+    const UD: u8 = 0x08;
+    const SHL: u8 = 0x04;
+    const SHD_N: u8 = 0x02;
+    const RST_N: u8 = 0x01;
+
+    /// Creates the default setting, see [PanelSettingBuilder]
+    pub const fn new() -> Self {
+        PanelSettingBuilder(Self::UD | Self::SHL | Self::SHD_N | Self::RST_N)
+    }
+
+    const fn with(self, bit: u8, set: bool) -> Self {
+        if set {
+            PanelSettingBuilder(self.0 | bit)
+        } else {
+            PanelSettingBuilder(self.0 & !bit)
+        }
+    }
+
+    /// Sets the RES bits, only the lowest two bits of `res` are used
+    ///
+    /// The resolutions they select depend on the controller, e.g. 0 to 3 are 96x230,
+    /// 96x252, 128x296 and 160x296 on the UC8151. Most drivers send the resolution with its
+    /// own command, which overrides them.
+    pub const fn resolution(self, res: u8) -> Self {
+        PanelSettingBuilder((self.0 & !Self::RES) | ((res & 0x03) << 6))
+    }
+
+    /// Takes the LUT from the registers instead of the OTP (REG)
+    pub const fn register_lut(self, register: bool) -> Self {
+        self.with(Self::REG, register)
+    }
+
+    /// Selects the black/white mode instead of the black/white/red one (KW/R)
+    pub const fn black_white(self, black_white: bool) -> Self {
+        self.with(Self::KW, black_white)
+    }
+
+    /// Scans the gates up, from the first one to the last, instead of down (UD)
+    pub const fn scan_up(self, up: bool) -> Self {
+        self.with(Self::UD, up)
+    }
+
+    /// Shifts the sources right, from the first one to the last, instead of left (SHL)
+    pub const fn shift_right(self, right: bool) -> Self {
+        self.with(Self::SHL, right)
+    }
+
+    /// Turns the booster on (SHD_N)
+    pub const fn booster(self, on: bool) -> Self {
+        self.with(Self::SHD_N, on)
+    }
+
+    /// Soft resets the controller when the setting is sent (RST_N cleared)
+    ///
+    /// The controller returns to its default setting afterwards.
+    pub const fn soft_reset(self, reset: bool) -> Self {
+        self.with(Self::RST_N, !reset)
+    }
+
+    /// Returns the byte to send
+    pub const fn build(self) -> u8 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_the_bytes_of_the_drivers() {
+        // 5.83" B V2
+        assert_eq!(PanelSettingBuilder::new().build(), 0x0F);
+        // 2.13" B/C and 2.9" B/C
+        assert_eq!(PanelSettingBuilder::new().resolution(2).build(), 0x8F);
+        // 1.54" B
+        assert_eq!(PanelSettingBuilder::new().resolution(3).build(), 0xCF);
+        // 7.5" V2
+        assert_eq!(PanelSettingBuilder::new().black_white(true).build(), 0x1F);
+        // 2.13" D and 2.9" D
+        let flexible = PanelSettingBuilder::new()
+            .resolution(2)
+            .register_lut(true)
+            .black_white(true);
+        assert_eq!(flexible.build(), 0xBF);
+    }
+
+    #[test]
+    fn bits_can_be_cleared_again() {
+        let setting = PanelSettingBuilder::new()
+            .resolution(0xFF)
+            .scan_up(false)
+            .shift_right(false)
+            .booster(false)
+            .soft_reset(true);
+        assert_eq!(setting.build(), 0xC0);
+        assert_eq!(setting.resolution(1).build(), 0x40);
+        assert_eq!(setting.soft_reset(false).booster(true).build(), 0xC3);
+        assert_eq!(PanelSettingBuilder::default(), PanelSettingBuilder::new());
+    }
+}
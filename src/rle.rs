@@ -0,0 +1,180 @@
+//! Run-length encoding of frames stored in flash
+//!
+//! E-paper frames are mostly large areas of a single color, so they shrink a lot with a
+//! simple run-length encoding. Drivers with an `update_frame_rle` decode such a stream while
+//! sending it, without a decoded copy of the frame in RAM.
+//!
+//! The stream is a sequence of packets, each starting with a control byte `c`:
+//!
+//! - `c < 0x80`: the next `c + 1` bytes are copied as they are (1 to 128 literal bytes)
+//! - `c >= 0x80`: the next byte is repeated `c - 0x7E` times (a run of 2 to 129 bytes)
+//!
+//! The decoded bytes have the layout of the buffer passed to `update_frame`.
+//!
+//! ```
+//! use epd_waveshare::rle;
+//!
+//! let frame = [0xFF; 300];
+//! let mut compressed = [0u8; 8];
+//! let len = rle::encode(&frame, &mut compressed).unwrap();
+//! assert_eq!(&compressed[..len], &[0xFF, 0xFF, 0xFF, 0xFF, 0xA8, 0xFF]);
+//! assert_eq!(rle::decoded_len(&compressed[..len]), Some(300));
+//! ```
+
+/// Longest literal of a single packet
+const MAX_LITERAL: usize = 128;
+/// Shortest run which is encoded as a run
+const MIN_RUN: usize = 2;
+/// Longest run of a single packet
+const MAX_RUN: usize = 129;
+
+/// A decoded packet of a run-length encoded stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Packet<'a> {
+    /// Bytes to send as they are
+    Literal(&'a [u8]),
+    /// A byte to send the given number of times
+    Run(u8, u32),
+}
+
+/// Iterates over the packets of a stream, yields `Err(())` once if the stream is truncated
+pub(crate) fn packets(rle: &[u8]) -> impl Iterator<Item = Result<Packet<'_>, ()>> {
+    let mut rest = rle;
+    core::iter::from_fn(move || {
+        let (&control, tail) = rest.split_first()?;
+        let packet = if control < 0x80 {
+            let len = control as usize + 1;
+            if tail.len() < len {
+                rest = &[];
+                return Some(Err(()));
+            }
+            let (literal, tail) = tail.split_at(len);
+            rest = tail;
+            Packet::Literal(literal)
+        } else {
+            let Some((&byte, tail)) = tail.split_first() else {
+                rest = &[];
+                return Some(Err(()));
+            };
+            rest = tail;
+            Packet::Run(byte, u32::from(control) - 0x7E)
+        };
+        Some(Ok(packet))
+    })
+}
+
+/// Returns the number of bytes `rle` decodes to, or `None` if the stream is truncated
+pub fn decoded_len(rle: &[u8]) -> Option<usize> {
+    packets(rle).try_fold(0, |len, packet| match packet {
+        Ok(Packet::Literal(literal)) => Some(len + literal.len()),
+        Ok(Packet::Run(_, count)) => Some(len + count as usize),
+        Err(()) => None,
+    })
+}
+
+/// Encodes `data` into `out` and returns the length of the encoded stream
+///
+/// Returns `None` if `out` is too small, which can't happen if it is at least
+/// `data.len() + data.len().div_ceil(128)` bytes long. Usually done on the host, e.g. in a
+/// build script, to store compressed images in flash.
+pub fn encode(data: &[u8], out: &mut [u8]) -> Option<usize> {
+    let mut written = 0;
+    let mut push = |bytes: &[u8]| {
+        let target = out.get_mut(written..written + bytes.len())?;
+        target.copy_from_slice(bytes);
+        written += bytes.len();
+        Some(())
+    };
+
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&byte| byte == data[i])
+            .count();
+        if run < MIN_RUN {
+            i += 1;
+            if i - literal_start == MAX_LITERAL {
+                push(&[(MAX_LITERAL - 1) as u8])?;
+                push(&data[literal_start..i])?;
+                literal_start = i;
+            }
+            continue;
+        }
+
+        if literal_start < i {
+            push(&[(i - literal_start - 1) as u8])?;
+            push(&data[literal_start..i])?;
+        }
+        push(&[(run + 0x7E) as u8, data[i]])?;
+        i += run;
+        literal_start = i;
+    }
+    if literal_start < data.len() {
+        push(&[(data.len() - literal_start - 1) as u8])?;
+        push(&data[literal_start..])?;
+    }
+    Some(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packets_of_a_stream() {
+        let rle = [0x02, 1, 2, 3, 0x80, 0xAA, 0xFF, 0x00];
+        let mut packets = packets(&rle);
+        assert_eq!(packets.next(), Some(Ok(Packet::Literal(&[1, 2, 3]))));
+        assert_eq!(packets.next(), Some(Ok(Packet::Run(0xAA, 2))));
+        assert_eq!(packets.next(), Some(Ok(Packet::Run(0x00, 129))));
+        assert_eq!(packets.next(), None);
+        assert_eq!(decoded_len(&rle), Some(134));
+    }
+
+    #[test]
+    fn truncated_streams() {
+        assert_eq!(decoded_len(&[]), Some(0));
+        assert_eq!(decoded_len(&[0x03, 1, 2]), None);
+        assert_eq!(decoded_len(&[0x00, 1, 0x85]), None);
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let mut data = [0u8; 700];
+        for (i, byte) in data.iter_mut().enumerate() {
+            // long runs, short runs and a stretch of literals longer than a packet
+            *byte = match i {
+                0..=299 => 0xFF,
+                300..=301 => 0x0F,
+                302..=499 => i as u8,
+                _ => 0x00,
+            };
+        }
+
+        let mut out = [0u8; 720];
+        let len = encode(&data, &mut out).unwrap();
+        assert!(len < 230);
+        assert_eq!(decoded_len(&out[..len]), Some(data.len()));
+
+        let mut decoded = [0u8; 700];
+        let mut pos = 0;
+        for packet in packets(&out[..len]) {
+            match packet.unwrap() {
+                Packet::Literal(literal) => {
+                    decoded[pos..pos + literal.len()].copy_from_slice(literal);
+                    pos += literal.len();
+                }
+                Packet::Run(byte, count) => {
+                    decoded[pos..pos + count as usize].fill(byte);
+                    pos += count as usize;
+                }
+            }
+        }
+        assert_eq!(decoded, data);
+
+        assert_eq!(encode(&data, &mut out[..10]), None);
+    }
+}
@@ -1,28 +1,127 @@
+use crate::interface::InterfaceConfig;
 use core::marker::Sized;
 use embedded_hal::{
     blocking::{delay::*, spi::Write},
     digital::v2::*,
 };
 
+/// Errors returned by the display drivers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayError<E> {
+    /// Writing to the SPI bus failed
+    Spi(E),
+    /// A frame was sent before the display was initialised
+    ///
+    /// Happens after creating the driver with [new_without_init](WaveshareDisplay::new_without_init)
+    /// without calling [wake_up](WaveshareDisplay::wake_up) afterwards.
+    NotInitialized,
+    /// The display doesn't support the requested operation
+    ///
+    /// E.g. partial refreshes on panels without them, fall back to a full refresh instead.
+    Unsupported,
+    /// A refresh was refused as it followed the previous one too soon
+    ///
+    /// Only returned after enabling the guard with
+    /// [set_min_refresh_interval_ms](WaveshareDisplay::set_min_refresh_interval_ms).
+    RefreshTooSoon,
+    /// The data doesn't match the display, e.g. a compressed frame of the wrong size
+    InvalidData,
+}
+
+impl<E> From<E> for DisplayError<E> {
+    fn from(error: E) -> Self {
+        DisplayError::Spi(error)
+    }
+}
+
+impl<E: core::fmt::Debug> core::fmt::Display for DisplayError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DisplayError::Spi(error) => write!(f, "SPI error: {:?}", error),
+            DisplayError::NotInitialized => write!(f, "Display is not initialised"),
+            DisplayError::Unsupported => write!(f, "Operation is not supported by the display"),
+            DisplayError::RefreshTooSoon => write!(f, "Refreshed too soon after the last refresh"),
+            DisplayError::InvalidData => write!(f, "Data doesn't match the display"),
+        }
+    }
+}
+
 /// All commands need to have this trait which gives the address of the command
 /// which needs to be send via SPI with activated CommandsPin (Data/Command Pin in CommandMode)
 pub(crate) trait Command {
     fn address(self) -> u8;
 }
 
+/// Raw addresses, used by [WaveshareDisplay::update_raw]
+impl Command for u8 {
+    fn address(self) -> u8 {
+        self
+    }
+}
+
+/// What the driver believes the panel is doing, see [WaveshareDisplay::state]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayState {
+    /// The init sequence didn't run yet
+    Uninitialized,
+    /// Initialised and idle
+    Ready,
+    /// A refresh was started and the busy line didn't report its end yet
+    Refreshing,
+    /// Put to sleep, [wake_up](WaveshareDisplay::wake_up) makes it ready again
+    Sleeping,
+}
+
 /// Seperates the different LUT for the Display Refresh process
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Default)]
 pub enum RefreshLut {
     /// The "normal" full Lookuptable for the Refresh-Sequence
+    #[default]
     Full,
     /// The quick LUT where not the full refresh sequence is followed.
     /// This might lead to some
     Quick,
 }
 
-impl Default for RefreshLut {
-    fn default() -> Self {
-        RefreshLut::Full
+/// The DriverOutputControl (0x01) setting of the SSD16xx controllers
+///
+/// Sets the number of gate lines, i.e. the rows of the panel in its native orientation, and
+/// the gate scan direction. A wrong number truncates or shifts the image. The drivers
+/// default to their `HEIGHT`, other values are only needed for custom panels.
+///
+/// ```
+/// use epd_waveshare::prelude::DriverOutputControl;
+///
+/// // the last gate line is sent, lowest byte first
+/// assert_eq!(DriverOutputControl::new(296).to_bytes(), [0x27, 0x01, 0x00]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriverOutputControl {
+    /// Number of gate lines
+    pub gate_lines: u16,
+    /// The scan bits of the third byte: TB in bit 0, SM in bit 1 and GD in bit 2
+    pub scan: u8,
+}
+
+impl DriverOutputControl {
+    /// `gate_lines` rows, scanned from the first gate on
+    pub const fn new(gate_lines: u16) -> Self {
+        DriverOutputControl {
+            gate_lines,
+            scan: 0x00,
+        }
+    }
+
+    /// Sets the scan bits, see the datasheet of the controller
+    pub const fn scan(mut self, scan: u8) -> Self {
+        self.scan = scan & 0x07;
+        self
+    }
+
+    /// The data bytes of the command
+    pub const fn to_bytes(self) -> [u8; 3] {
+        let last_gate = self.gate_lines.saturating_sub(1);
+        [last_gate as u8, (last_gate >> 8) as u8, self.scan]
     }
 }
 
@@ -45,7 +144,7 @@ where
     /// This function calls [reset](WaveshareDisplay::reset),
     /// so you don't need to call reset your self when trying to wake your device up
     /// after setting it to sleep.
-    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>>;
 }
 
 /// Functions to interact with three color panels
@@ -67,19 +166,50 @@ where
         spi: &mut SPI,
         black: &[u8],
         chromatic: &[u8],
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Update only the black/white data of the display.
     ///
     /// This must be finished by calling `update_chromatic_frame`.
-    fn update_achromatic_frame(&mut self, spi: &mut SPI, black: &[u8]) -> Result<(), SPI::Error>;
+    fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Update only the chromatic data of the display.
     ///
     /// This should be preceded by a call to `update_achromatic_frame`.
     /// This data takes precedence over the black/white data.
-    fn update_chromatic_frame(&mut self, spi: &mut SPI, chromatic: &[u8])
-        -> Result<(), SPI::Error>;
+    fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>>;
+
+    /// Refreshes only the pixels of one plane
+    ///
+    /// None of the supported controllers can do this: their refresh always runs the waveform
+    /// for both planes, also when only the black/white plane changed, so this fails with
+    /// [DisplayError::Unsupported]. A full [display_frame](WaveshareDisplay::display_frame)
+    /// is required instead.
+    fn display_frame_plane(
+        &mut self,
+        _spi: &mut SPI,
+        _delay: &mut DELAY,
+        _plane: ColorPlane,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        Err(DisplayError::Unsupported)
+    }
+}
+
+/// The two planes of the frame of a three color display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorPlane {
+    /// The black/white plane
+    Achromatic,
+    /// The plane of the chromatic color, e.g. red
+    Chromatic,
 }
 
 /// All the functions to interact with the EPDs
@@ -90,7 +220,7 @@ where
 ///
 ///```rust, no_run
 ///# use embedded_hal_mock::*;
-///# fn main() -> Result<(), MockError> {
+///# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
 ///use embedded_graphics::{
 ///    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///};
@@ -147,19 +277,159 @@ where
         dc: DC,
         rst: RST,
         delay: &mut DELAY,
-    ) -> Result<Self, SPI::Error>
+    ) -> Result<Self, DisplayError<SPI::Error>>
     where
         Self: Sized;
 
+    /// Creates a new driver without sending anything to the device
+    ///
+    /// Neither the reset nor the init sequence are run.
+    ///
+    /// Frames are refused with [DisplayError::NotInitialized] until either
+    /// [wake_up](WaveshareDisplay::wake_up) (re)initialised the device or
+    /// [assume_initialized](WaveshareDisplay::assume_initialized) was called.
+    /// The latter is only safe if the panel kept its configuration since the last init,
+    /// e.g. when the MCU woke up from its own deep sleep while the display stayed powered
+    /// and was not put to sleep with [sleep](WaveshareDisplay::sleep).
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self
+    where
+        Self: Sized;
+
+    /// Creates a new driver like [new](WaveshareDisplay::new), but with custom interface timings
+    ///
+    /// The `config` is already used for the reset which happens during the initialisation.
+    /// The drivers of this crate run the same init sequence as [new](WaveshareDisplay::new),
+    /// the default implementation falls back to [wake_up](WaveshareDisplay::wake_up).
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>>
+    where
+        Self: Sized,
+    {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.wake_up(spi, delay)?;
+        Ok(epd)
+    }
+
+    /// Replaces the timing settings of the connection, e.g. the shape of the reset
+    ///
+    /// They take effect with the next command, so e.g. a changed reset shape is used by the
+    /// next [wake_up](WaveshareDisplay::wake_up).
+    fn set_interface_config(&mut self, config: InterfaceConfig);
+
+    /// Refuses refreshes sooner than `interval_ms` after the previous one (default: disabled)
+    ///
+    /// Refreshing too often can damage the panel, this guards against e.g. a buggy loop.
+    /// [display_frame](WaveshareDisplay::display_frame) then fails with
+    /// [DisplayError::RefreshTooSoon] instead of refreshing. `now_ms` returns the current
+    /// time in milliseconds, e.g. from a tick counter, and may wrap around. Pass 0 as
+    /// `interval_ms` to disable the guard again.
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32);
+
+    /// Allows sending frames without running the init sequence first
+    ///
+    /// See [new_without_init](WaveshareDisplay::new_without_init) for when this is safe.
+    fn assume_initialized(&mut self);
+
+    /// Returns what the driver believes the panel is doing
+    ///
+    /// Follows the calls to the driver: [Ready](DisplayState::Ready) after the init sequence
+    /// or [wake_up](WaveshareDisplay::wake_up), [Refreshing](DisplayState::Refreshing) from
+    /// the start of a refresh while the busy line reports it, and
+    /// [Sleeping](DisplayState::Sleeping) after [sleep](WaveshareDisplay::sleep). The panel
+    /// isn't queried, e.g. a panel power cycled behind the driver's back isn't noticed.
+    fn state(&self) -> DisplayState;
+
+    /// Whether a partial refresh has a base image to start from
+    ///
+    /// A partial refresh only drives the pixels which differ from the image in the
+    /// controller, which is lost in deep sleep and when the init sequence resets the
+    /// controller. Until a full refresh ran since the last init sequence, e.g. in
+    /// [wake_up](WaveshareDisplay::wake_up), or [sleep](WaveshareDisplay::sleep), the first
+    /// partial refresh comes out garbled. Drivers without partial refresh always return
+    /// `false`.
+    fn can_partial_refresh(&self) -> bool {
+        false
+    }
+
+    /// Sends `data` after the command with the address `command`, without any packing
+    ///
+    /// This is the escape hatch for data which is already laid out the way the controller
+    /// expects it, e.g. a precomputed plane or a command the driver doesn't know about.
+    /// Nothing is checked, the bytes are sent as they are. Look up the addresses in the
+    /// datasheet of the controller.
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>>;
+
+    /// Checks that a panel is connected, without running the init sequence
+    ///
+    /// Resets the controller and sends a command which keeps it busy for a moment, a
+    /// connected panel has to pull the busy line accordingly. Returns `false` if the busy
+    /// line doesn't react or stays busy, e.g. because nothing is plugged in. Create the
+    /// driver with [new_without_init](WaveshareDisplay::new_without_init) to probe it, it
+    /// needs a [wake_up](WaveshareDisplay::wake_up) before it is used afterwards.
+    fn probe(&mut self, spi: &mut SPI, delay: &mut DELAY)
+        -> Result<bool, DisplayError<SPI::Error>>;
+
     /// Let the device enter deep-sleep mode to save power.
     ///
     /// The deep sleep mode returns to standby with a hardware reset.
-    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn sleep(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Wakes the device up from sleep
     ///
     /// Also reintialises the device if necessary.
-    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn wake_up(&mut self, spi: &mut SPI, delay: &mut DELAY)
+        -> Result<(), DisplayError<SPI::Error>>;
+
+    /// Runs the whole init sequence like [wake_up](WaveshareDisplay::wake_up), and repeats it
+    /// up to `retries` more times while it fails
+    ///
+    /// Helps on marginal power supplies, where the first init after a cold boot sometimes
+    /// fails and a second one succeeds. Every attempt starts with a hardware reset. Returns
+    /// the error of the last attempt if all of them failed.
+    ///
+    /// Waiting for the busy pin has no timeout, so only errors of the bus lead to a retry.
+    fn init_with_retries(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        retries: u8,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        let mut result = self.wake_up(spi, delay);
+        for _ in 0..retries {
+            if result.is_ok() {
+                break;
+            }
+            result = self.wake_up(spi, delay);
+        }
+        result
+    }
+
+    /// Displays `buffer` and lets the device enter deep-sleep mode afterwards
+    ///
+    /// This is the recommended way to shut down: a panel which loses power without
+    /// being put to sleep can keep a high voltage on its pixels and get damaged.
+    fn flush_and_sleep(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.update_and_display_frame(spi, buffer, delay)?;
+        self.sleep(spi, delay)
+    }
 
     /// Sets the backgroundcolor for various commands like [clear_frame](WaveshareDisplay::clear_frame)
     fn set_background_color(&mut self, color: Self::DisplayColor);
@@ -179,27 +449,35 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Transmits partial data to the SRAM of the EPD
     ///
     /// (x,y) is the top left corner
     ///
     /// BUFFER needs to be of size: width / 8 * height !
+    ///
+    /// Fails with [DisplayError::Unsupported] on displays without partial updates.
     fn update_partial_frame(
         &mut self,
-        spi: &mut SPI,
-        buffer: &[u8],
-        x: u32,
-        y: u32,
-        width: u32,
-        height: u32,
-    ) -> Result<(), SPI::Error>;
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        Err(DisplayError::Unsupported)
+    }
 
     /// Displays the frame data from SRAM
     ///
     /// This function waits until the device isn`t busy anymore
-    fn display_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Provide a combined update&display and save some time (skipping a busy check in between)
     fn update_and_display_frame(
@@ -207,12 +485,49 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Clears the frame buffer on the EPD with the declared background color
     ///
+    /// Like [update_frame](WaveshareDisplay::update_frame) this only fills the controller
+    /// RAM, the panel keeps showing the old image until the next
+    /// [display_frame](WaveshareDisplay::display_frame). Use
+    /// [clear_and_display](WaveshareDisplay::clear_and_display) to do both.
+    ///
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
-    fn clear_frame(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>>;
+
+    /// Clears the frame buffer with the background color and refreshes the panel with it
+    fn clear_and_display(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.clear_frame(spi, delay)?;
+        self.display_frame(spi, delay)
+    }
+
+    /// Runs `cycles` full refreshes with the background color to bring a cold panel to a workable state
+    ///
+    /// In cold environments the first refreshes often look washed out or leave ghosting.
+    /// The number of cycles depends on the temperature, so it's up to the caller to decide
+    /// on it, e.g. with the help of a temperature sensor. This clears the frame in the
+    /// controller RAM.
+    fn warm_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+        cycles: u8,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        for _ in 0..cycles {
+            self.clear_and_display(spi, delay)?;
+        }
+        Ok(())
+    }
 
     /// Trait for using various Waveforms from different LUTs
     /// E.g. for partial refreshes
@@ -222,11 +537,31 @@ where
     /// WARNING: Quick Refresh might lead to ghosting-effects/problems with your display. Especially for the 4.2in Display!
     ///
     /// If None is used the old value will be loaded on the LUTs once more
+    ///
+    /// Fails with [DisplayError::Unsupported] on displays without selectable LUTs.
     fn set_lut(
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
+
+    /// Returns the LUT which was set last with [set_lut](WaveshareDisplay::set_lut)
+    ///
+    /// Displays which don't support switching the LUT always return [RefreshLut::Full].
+    fn refresh_lut(&self) -> RefreshLut {
+        RefreshLut::Full
+    }
+
+    /// Returns how many partial refreshes per second the panel can keep up with
+    ///
+    /// The rate is derived from the partial refresh time in the specification of the panel,
+    /// rounded down. Updating faster than this leaves artifacts on the panel.
+    ///
+    /// Displays without partial updates, or with partial updates which are as slow as a
+    /// full refresh, return 0.
+    fn max_partial_refresh_hz(&self) -> u32 {
+        0
+    }
 
     /// Checks if the display is busy transmitting data
     ///
@@ -234,6 +569,15 @@ where
     /// but in the case you send data and commands directly you might need to check
     /// if the device is still busy
     fn is_busy(&self) -> bool;
+
+    /// Checks if the display is busy like [is_busy](WaveshareDisplay::is_busy), but only
+    /// reports an idle display if `samples` reads of the busy pin in a row agree
+    ///
+    /// Filters out spurious idle readings of noisy busy lines. Stops reading at the first
+    /// busy sample, `0` samples count as one.
+    fn is_busy_filtered(&self, samples: u8) -> bool {
+        (0..samples.max(1)).any(|_| self.is_busy())
+    }
 }
 
 /// Allows quick refresh support for displays that support it; lets you send both
@@ -247,7 +591,7 @@ where
 /// Example:
 ///```rust, no_run
 ///# use embedded_hal_mock::*;
-///# fn main() -> Result<(), MockError> {
+///# fn main() -> Result<(), epd_waveshare::DisplayError<MockError>> {
 ///# use embedded_graphics::{
 ///#   pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
 ///# };
@@ -296,7 +640,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Updates the new frame.
     fn update_new_frame(
@@ -304,10 +648,14 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Displays the new frame
-    fn display_new_frame(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), SPI::Error>;
+    fn display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Updates and displays the new frame.
     fn update_and_display_new_frame(
@@ -315,7 +663,7 @@ where
         spi: &mut SPI,
         buffer: &[u8],
         delay: &mut DELAY,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Updates the old frame for a portion of the display.
     fn update_partial_old_frame(
@@ -326,7 +674,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Updates the new frame for a portion of the display.
     fn update_partial_new_frame(
@@ -337,7 +685,7 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
 
     /// Clears the partial frame buffer on the EPD with the declared background color
     /// The background color can be changed with [`WaveshareDisplay::set_background_color`]
@@ -348,5 +696,111 @@ where
         y: u32,
         width: u32,
         height: u32,
-    ) -> Result<(), SPI::Error>;
+    ) -> Result<(), DisplayError<SPI::Error>>;
+}
+
+/// Controllers with SSD168x style RAM address counters, which allow writing the
+/// black/white RAM at any byte position without sending a whole frame.
+///
+/// This is what [`DirectDisplay`](crate::graphics::DirectDisplay) builds upon.
+///
+/// Implemented for the type A displays ([epd1in54](crate::epd1in54) and [epd2in9](crate::epd2in9)).
+pub trait DirectRam<SPI>
+where
+    SPI: Write<u8>,
+{
+    /// Width of the controller RAM in pixels
+    const RAM_WIDTH: u32;
+    /// Height of the controller RAM in pixels
+    const RAM_HEIGHT: u32;
+
+    /// Writes `data` into the RAM, starting at the byte column `x_byte` of row `y`
+    ///
+    /// The controller increments the x address counter after every byte,
+    /// so `data` may cover several consecutive bytes of the same row.
+    fn write_ram(
+        &mut self,
+        spi: &mut SPI,
+        x_byte: u32,
+        y: u32,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>>;
+}
+
+/// Displays whose refresh can be started without waiting for it to end
+///
+/// Lets several panels refresh at the same time, see [update_and_display_pair].
+///
+/// Implemented for [epd1in54](crate::epd1in54), [epd2in9](crate::epd2in9) and
+/// [epd4in2](crate::epd4in2).
+pub trait SplitRefresh<SPI, CS, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    /// Starts displaying the frame in the controller RAM and returns right away
+    ///
+    /// Nothing is waited for, neither before nor after the refresh command, so the display
+    /// has to be idle already.
+    fn start_refresh(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>>;
+
+    /// Waits for the end of a refresh started with [start_refresh](SplitRefresh::start_refresh)
+    fn finish_refresh(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>>;
+
+    /// Waits until the display is idle, sleeping the
+    /// [busy poll interval](crate::InterfaceConfig::busy_poll_interval_ms) between the polls
+    fn wait_for_idle(&mut self, delay: &mut DELAY);
+}
+
+/// Uploads a frame to each of two displays and refreshes both at the same time
+///
+/// Both frames are sent first, once both displays are idle the two refreshes are started
+/// right after each other and only then waited for. The busy lines are polled with the
+/// [busy poll interval](crate::InterfaceConfig::busy_poll_interval_ms) of each display. This keeps the visible offset between
+/// two neighbouring panels, e.g. of a video wall, as small as possible. Both displays share
+/// the SPI bus, each with its own chip select.
+///
+/// If starting the refresh of `second` fails, `first` is refreshing already.
+#[allow(clippy::too_many_arguments)]
+pub fn update_and_display_pair<SPI, CS1, BUSY1, DC1, RST1, CS2, BUSY2, DC2, RST2, DELAY, A, B>(
+    spi: &mut SPI,
+    delay: &mut DELAY,
+    first: &mut A,
+    first_buffer: &[u8],
+    second: &mut B,
+    second_buffer: &[u8],
+) -> Result<(), DisplayError<SPI::Error>>
+where
+    SPI: Write<u8>,
+    CS1: OutputPin,
+    BUSY1: InputPin,
+    DC1: OutputPin,
+    RST1: OutputPin,
+    CS2: OutputPin,
+    BUSY2: InputPin,
+    DC2: OutputPin,
+    RST2: OutputPin,
+    DELAY: DelayMs<u8>,
+    A: SplitRefresh<SPI, CS1, BUSY1, DC1, RST1, DELAY>,
+    B: SplitRefresh<SPI, CS2, BUSY2, DC2, RST2, DELAY>,
+{
+    first.update_frame(spi, first_buffer, delay)?;
+    second.update_frame(spi, second_buffer, delay)?;
+    first.wait_for_idle(delay);
+    second.wait_for_idle(delay);
+
+    first.start_refresh(spi)?;
+    second.start_refresh(spi)?;
+
+    first.finish_refresh(spi, delay)?;
+    second.finish_refresh(spi, delay)
 }
@@ -17,6 +17,10 @@ pub(crate) enum Command {
     ///     0.. B[2:0]
     ///     Default: Set A[8:0] = 0x127 and B[2:0] = 0x0
     DriverOutputControl = 0x01,
+    /// Gate driving voltage, 1 Databyte
+    GateDrivingVoltage = 0x03,
+    /// Source driving voltage VSH1, VSH2 and VSL, 3 Databytes
+    SourceDrivingVoltage = 0x04,
     /// Booster Soft start control
     ///     3 Databytes:
     ///     1.. A[6:0]
@@ -39,6 +43,8 @@ pub(crate) enum Command {
 
     SwReset = 0x12,
 
+    TemperatureSensorSelection = 0x18,
+
     TemperatureSensorControl = 0x1A,
 
     MasterActivation = 0x20,
@@ -63,6 +69,9 @@ pub(crate) enum Command {
 
     BorderWaveformControl = 0x3C,
 
+    /// Option for the end of the LUT, 1 Databyte
+    EndOption = 0x3F,
+
     SetRamXAddressStartEndPosition = 0x44,
 
     SetRamYAddressStartEndPosition = 0x45,
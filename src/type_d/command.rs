@@ -0,0 +1,114 @@
+//! SPI Commands for the Waveshare 2.13" and 2.9" D flexible E-Ink Displays
+
+use crate::traits;
+
+/// Epd2in13d and Epd2in9d commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift
+    /// direction, booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0".
+    PowerOff = 0x02,
+
+    /// Turning On the Power
+    ///
+    /// After the Power ON command, the driver will power on following the Power ON
+    /// sequence. Once complete, the BUSY signal will become "1".
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The deep sleep mode would return to stand-by by hardware reset.
+    ///
+    /// The only one parameter is a check code, the command would be excuted if check code = 0xA5.
+    DeepSleep = 0x07,
+
+    /// This command starts transmitting data and write them into SRAM.
+    ///
+    /// OLD_DATA
+    DataStartTransmission1 = 0x10,
+
+    /// After this command is issued, driver will refresh display (data/VCOM) according to
+    /// SRAM data and LUT.
+    DisplayRefresh = 0x12,
+
+    /// This command starts transmitting data and write them into SRAM.
+    ///
+    /// NEW_DATA
+    DataStartTransmission2 = 0x13,
+
+    /// This command stores VCOM Look-Up Table with 7 groups of data.
+    LutForVcom = 0x20,
+
+    /// This command stores White-to-White Look-Up Table with 7 groups of data.
+    LutWhiteToWhite = 0x21,
+
+    /// This command stores Black-to-White Look-Up Table with 7 groups of data.
+    LutBlackToWhite = 0x22,
+
+    /// This command stores White-to-Black Look-Up Table with 7 groups of data.
+    LutWhiteToBlack = 0x23,
+
+    /// This command stores Black-to-Black Look-Up Table with 7 groups of data.
+    LutBlackToBlack = 0x24,
+
+    /// The command controls the PLL clock frequency.
+    PllControl = 0x30,
+
+    /// This command indicates the interval of Vcom and data output. When setting the
+    /// vertical back porch, the total blanking will be kept (20 Hsync).
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// This command defines the resolution setting.
+    TconResolution = 0x61,
+
+    /// This command reads the IC status.
+    GetStatus = 0x71,
+
+    /// This command sets VCOM_DC value.
+    VcmDcSetting = 0x82,
+
+    /// This command sets the partial window.
+    PartialWindow = 0x90,
+
+    /// This command makes the display enter partial mode.
+    PartialIn = 0x91,
+
+    /// This command makes the display exit partial mode and enter normal mode.
+    PartialOut = 0x92,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::DisplayRefresh.address(), 0x12);
+        assert_eq!(Command::LutBlackToBlack.address(), 0x24);
+        assert_eq!(Command::PartialOut.address(), 0x92);
+    }
+}
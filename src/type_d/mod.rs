@@ -0,0 +1,554 @@
+//! Shared driver of the D flexible displays, which only differ in their size
+
+use embedded_hal::{
+    blocking::{delay::*, spi::Write},
+    digital::v2::{InputPin, OutputPin},
+};
+
+use crate::color::Color;
+use crate::interface::{DisplayInterface, InterfaceConfig, LutLoadOrder};
+use crate::panel_setting::PanelSettingBuilder;
+use crate::traits::{
+    DisplayError, DisplayState, InternalWiAdditions, RefreshLut, WaveshareDisplay,
+};
+
+pub(crate) mod command;
+use self::command::Command;
+
+pub(crate) mod constants;
+use self::constants::*;
+
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+pub(crate) const IS_BUSY_LOW: bool = true;
+/// Value of the bit of a black pixel in the frame memory of the panel
+const BLACK_BIT: bool = false;
+
+/// Driver of the D displays with a size of `WIDTH` x `HEIGHT` pixels
+///
+/// Used through [Epd2in13d](crate::epd2in13d::Epd2in13d) and [Epd2in9d](crate::epd2in9d::Epd2in9d).
+#[must_use = "the panel should be put to sleep before the driver is dropped"]
+pub struct EpdTypeD<SPI, CS, BUSY, DC, RST, DELAY, const WIDTH: u32, const HEIGHT: u32> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, CS, BUSY, DC, RST, DELAY>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY, const WIDTH: u32, const HEIGHT: u32>
+    InternalWiAdditions<SPI, CS, BUSY, DC, RST, DELAY>
+    for EpdTypeD<SPI, CS, BUSY, DC, RST, DELAY, WIDTH, HEIGHT>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn init(&mut self, spi: &mut SPI, delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        // Reset the device
+        self.interface.reset(delay, 10);
+
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x03, 0x00, 0x2B, 0x2B, 0x03])?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])?;
+        let lut_first = self
+            .interface
+            .lut_before_power_on(LutLoadOrder::AfterPowerOn);
+        if lut_first {
+            self.set_lut(spi, None)?;
+        }
+        self.command(spi, Command::PowerOn)?;
+        self.wait_until_idle(spi)?;
+        self.interface.power_on_settle(delay);
+
+        // LUT from registers, black/white, scan up, shift right, booster on, VCOM to 0V fast
+        let setting = PanelSettingBuilder::new()
+            .resolution(2)
+            .register_lut(true)
+            .black_white(true)
+            .build();
+        self.cmd_with_data(spi, Command::PanelSetting, &[setting, 0x0D])?;
+        // 100Hz
+        self.cmd_with_data(spi, Command::PllControl, &[0x3A])?;
+        self.send_resolution(spi)?;
+
+        if !lut_first {
+            self.set_lut(spi, None)?;
+        }
+        self.interface.set_initialized();
+        Ok(())
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY, const WIDTH: u32, const HEIGHT: u32>
+    WaveshareDisplay<SPI, CS, BUSY, DC, RST, DELAY>
+    for EpdTypeD<SPI, CS, BUSY, DC, RST, DELAY, WIDTH, HEIGHT>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    type DisplayColor = Color;
+    fn new(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+
+        epd.init(spi, delay)?;
+
+        Ok(epd)
+    }
+
+    fn new_with_config(
+        spi: &mut SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        delay: &mut DELAY,
+        config: InterfaceConfig,
+    ) -> Result<Self, DisplayError<SPI::Error>> {
+        let mut epd = Self::new_without_init(cs, busy, dc, rst);
+        epd.set_interface_config(config);
+        epd.init(spi, delay)?;
+        Ok(epd)
+    }
+
+    fn new_without_init(cs: CS, busy: BUSY, dc: DC, rst: RST) -> Self {
+        let interface = DisplayInterface::new(cs, busy, dc, rst);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        EpdTypeD {
+            interface,
+            color,
+            refresh: RefreshLut::Full,
+        }
+    }
+
+    fn set_interface_config(&mut self, config: InterfaceConfig) {
+        self.interface.set_config(config);
+    }
+
+    fn set_min_refresh_interval_ms(&mut self, interval_ms: u32, now_ms: fn() -> u32) {
+        self.interface.set_min_refresh_interval(interval_ms, now_ms);
+    }
+
+    fn assume_initialized(&mut self) {
+        self.interface.set_initialized();
+    }
+
+    fn state(&self) -> DisplayState {
+        self.interface.state(IS_BUSY_LOW)
+    }
+
+    fn can_partial_refresh(&self) -> bool {
+        self.interface.has_base_image()
+    }
+
+    fn update_raw(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn probe(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<bool, DisplayError<SPI::Error>> {
+        // the busy pin only updates after reading the status, see wait_until_idle
+        self.interface.probe_with_status(
+            spi,
+            delay,
+            Command::PowerOn,
+            Some(Command::GetStatus),
+            10,
+            IS_BUSY_LOW,
+        )
+    }
+
+    fn wake_up(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.init(spi, delay)
+    }
+
+    fn sleep(&mut self, spi: &mut SPI, _delay: &mut DELAY) -> Result<(), DisplayError<SPI::Error>> {
+        self.wait_until_idle(spi)?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xF7])?;
+        self.command(spi, Command::PowerOff)?;
+        self.wait_until_idle(spi)?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5])?;
+        self.interface.set_sleeping();
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle(spi)?;
+
+        // the old data is always sent inverted to white, as done by the reference code
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface.data_x_times(
+            spi,
+            !Color::White.panel_byte(BLACK_BIT),
+            WIDTH / 8 * HEIGHT,
+        )?;
+
+        self.interface.cmd(spi, Command::DataStartTransmission2)?;
+        self.interface.frame(spi, buffer, HEIGHT)?;
+        Ok(())
+    }
+
+    fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle(spi)?;
+
+        // x has to be a multiple of 8, the last 3 bits are ignored
+        let x_end = x + width - 1;
+        let y_end = y + height - 1;
+        self.command(spi, Command::PartialIn)?;
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (x & 0xF8) as u8,
+                (x_end | 0x07) as u8,
+                (y >> 8) as u8,
+                y as u8,
+                (y_end >> 8) as u8,
+                y_end as u8,
+                0x28,
+            ],
+        )?;
+
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)?;
+        self.command(spi, Command::PartialOut)?;
+        Ok(())
+    }
+
+    fn display_frame(
+        &mut self,
+        spi: &mut SPI,
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.interface.check_refresh_interval()?;
+        self.interface
+            .cmd_then_delay(spi, delay, Command::DisplayRefresh, 10)?;
+        self.wait_until_idle(spi)?;
+        self.interface.refreshed(self.refresh);
+        Ok(())
+    }
+
+    fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.update_frame(spi, buffer, delay)?;
+        self.display_frame(spi, delay)?;
+        Ok(())
+    }
+
+    fn clear_frame(
+        &mut self,
+        spi: &mut SPI,
+        _delay: &mut DELAY,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.check_initialized()?;
+        self.wait_until_idle(spi)?;
+
+        let color_value = self.color.panel_byte(BLACK_BIT);
+
+        self.command(spi, Command::DataStartTransmission1)?;
+        self.interface
+            .data_x_times(spi, !color_value, WIDTH / 8 * HEIGHT)?;
+
+        self.command(spi, Command::DataStartTransmission2)?;
+        self.interface
+            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)?;
+        Ok(())
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        match self.refresh {
+            RefreshLut::Full => self.set_lut_helper(
+                spi,
+                0x28,
+                &LUT_VCOM_FULL,
+                &LUT_WW_FULL,
+                &LUT_BW_FULL,
+                &LUT_WB_FULL,
+                &LUT_BB_FULL,
+            ),
+            // the partial refresh LUT, VCOM DC at 0V avoids ghosting around the window
+            RefreshLut::Quick => self.set_lut_helper(
+                spi,
+                0x00,
+                &LUT_VCOM_QUICK,
+                &LUT_WW_QUICK,
+                &LUT_BW_QUICK,
+                &LUT_WB_QUICK,
+                &LUT_BB_QUICK,
+            ),
+        }
+    }
+
+    fn refresh_lut(&self) -> RefreshLut {
+        self.refresh
+    }
+
+    fn max_partial_refresh_hz(&self) -> u32 {
+        // both flexible panels, the 2.13inch and the 2.9inch e-Paper (D), list 0.3s for a
+        // partial refresh in their specification on the Waveshare wiki, rounded down
+        3
+    }
+
+    fn is_busy(&self) -> bool {
+        self.interface.is_busy(IS_BUSY_LOW)
+    }
+}
+
+impl<SPI, CS, BUSY, DC, RST, DELAY, const WIDTH: u32, const HEIGHT: u32>
+    EpdTypeD<SPI, CS, BUSY, DC, RST, DELAY, WIDTH, HEIGHT>
+where
+    SPI: Write<u8>,
+    CS: OutputPin,
+    BUSY: InputPin,
+    DC: OutputPin,
+    RST: OutputPin,
+    DELAY: DelayMs<u8>,
+{
+    fn command(&mut self, spi: &mut SPI, command: Command) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd(spi, command)
+    }
+
+    fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.interface.cmd_with_data(spi, command, data)
+    }
+
+    fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        // the busy pin only updates after reading the status
+        while self.interface.is_busy(IS_BUSY_LOW) {
+            self.interface.cmd(spi, Command::GetStatus)?;
+        }
+        Ok(())
+    }
+
+    fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), DisplayError<SPI::Error>> {
+        let w = self.width();
+        let h = self.height();
+
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[w as u8, (h >> 8) as u8, h as u8],
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        vcom_dc: u8,
+        lut_vcom: &[u8],
+        lut_ww: &[u8],
+        lut_bw: &[u8],
+        lut_wb: &[u8],
+        lut_bb: &[u8],
+    ) -> Result<(), DisplayError<SPI::Error>> {
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[vcom_dc])?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xB7])?;
+        self.cmd_with_data(spi, Command::LutForVcom, lut_vcom)?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, lut_ww)?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, lut_bw)?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, lut_wb)?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, lut_bb)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Event, MockDelay, MockPin, MockSpi, Recorder};
+
+    type Small = EpdTypeD<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay, 104, 212>;
+    type Large = EpdTypeD<MockSpi, MockPin, MockPin, MockPin, MockPin, MockDelay, 128, 296>;
+
+    #[test]
+    fn partial_frame_window() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Small::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.update_partial_frame(&mut mock.spi(), &[0xAA; 4], 10, 100, 16, 2)
+            .unwrap();
+        assert_eq!(
+            mock.events(),
+            [
+                Event::Command(0x91),
+                Event::Command(0x90),
+                Event::data(&[8, 31, 0, 100, 0, 101, 0x28]),
+                Event::Command(0x13),
+                Event::data(&[0xAA; 4]),
+                Event::Command(0x92),
+            ]
+        );
+    }
+
+    #[test]
+    fn busy_wait_polls_status() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Small::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+
+        mock.busy_for(2);
+        epd.sleep(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert_eq!(
+            mock.events(),
+            [
+                Event::Command(0x71),
+                Event::Command(0x71),
+                Event::Command(0x50),
+                Event::data(&[0xF7]),
+                Event::Command(0x02),
+                Event::Command(0x07),
+                Event::data(&[0xA5]),
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_covers_the_size() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Large::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+
+        epd.clear_frame(&mut mock.spi(), &mut mock.delay()).unwrap();
+        let data = mock.data_after(0x13).unwrap();
+        assert_eq!(data.len(), 128 / 8 * 296);
+    }
+
+    fn partial_refresh_needs_a_full_one_after_sleep<const WIDTH: u32, const HEIGHT: u32>() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd: EpdTypeD<_, _, _, _, _, MockDelay, WIDTH, HEIGHT> =
+            EpdTypeD::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        epd.assume_initialized();
+        assert!(!epd.can_partial_refresh());
+
+        // a quick refresh doesn't leave a base image behind
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Quick))
+            .unwrap();
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert!(!epd.can_partial_refresh());
+
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Full))
+            .unwrap();
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert!(epd.can_partial_refresh());
+
+        // switching to the quick LUT keeps it
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Quick))
+            .unwrap();
+        assert!(epd.can_partial_refresh());
+
+        // the init sequence resets the controller
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert!(!epd.can_partial_refresh());
+        epd.set_lut(&mut mock.spi(), Some(RefreshLut::Full))
+            .unwrap();
+        epd.display_frame(&mut mock.spi(), &mut mock.delay())
+            .unwrap();
+        assert!(epd.can_partial_refresh());
+
+        epd.sleep(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert!(!epd.can_partial_refresh());
+        epd.wake_up(&mut mock.spi(), &mut mock.delay()).unwrap();
+        assert!(!epd.can_partial_refresh());
+    }
+
+    #[test]
+    fn partial_refresh_needs_a_full_one_after_sleep_for_both_sizes() {
+        partial_refresh_needs_a_full_one_after_sleep::<104, 212>();
+        partial_refresh_needs_a_full_one_after_sleep::<128, 296>();
+    }
+
+    #[test]
+    fn max_partial_refresh_hz() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let epd = Small::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        assert_eq!(epd.max_partial_refresh_hz(), 3);
+    }
+
+    #[test]
+    fn probe_reads_the_status_while_busy() {
+        let mock = Recorder::new(IS_BUSY_LOW);
+        let mut epd = Small::new_without_init(mock.cs(), mock.busy(), mock.dc(), mock.rst());
+        mock.idle_for(1);
+        mock.busy_for(2);
+        assert!(epd.probe(&mut mock.spi(), &mut mock.delay()).unwrap());
+        assert_eq!(mock.commands(), [0x04, 0x71]);
+    }
+}